@@ -85,6 +85,9 @@ pub async fn yank_integrations_data(
 pub struct ImportMedia {
     pub user_id: i32,
     pub input: DeployImportInput,
+    /// Only import the items with these identifiers in the source
+    #[serde(default)]
+    pub only_items: Option<Vec<String>>,
 }
 
 impl Job for ImportMedia {
@@ -95,7 +98,11 @@ pub async fn import_media(information: ImportMedia, ctx: JobContext) -> Result<(
     tracing::info!("Importing media");
     ctx.data::<Arc<ImporterService>>()
         .unwrap()
-        .import_from_source(information.user_id, information.input)
+        .import_from_source(
+            information.user_id,
+            information.input,
+            information.only_items,
+        )
         .await
         .unwrap();
     Ok(())
@@ -150,6 +157,10 @@ pub async fn after_media_seen_job(
         information.seen.id
     );
     let media_service = ctx.data::<Arc<MiscellaneousService>>().unwrap();
+    media_service
+        .recalculate_popularity(information.seen.metadata_id)
+        .await
+        .ok();
     if information.seen.dropped {
         media_service
             .remove_media_item_from_collection(
@@ -181,6 +192,14 @@ pub async fn after_media_seen_job(
             .await
             .ok();
     } else if information.seen.progress == 100 {
+        media_service
+            .add_media_to_completed_collection(
+                information.seen.user_id,
+                information.seen.metadata_id,
+                information.metadata_lot,
+            )
+            .await
+            .ok();
         media_service
             .remove_media_item_from_collection(
                 &information.seen.user_id,
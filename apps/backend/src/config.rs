@@ -1,11 +1,15 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
+use enum_meta::Meta;
+use regex::Regex;
 use schematic::{derive_enum, Config, ConfigEnum, ConfigLoader, ValidateError};
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 
 use crate::{
     graphql::PROJECT_NAME,
+    miscellaneous::DefaultCollection,
     providers::{audible::AudibleService, itunes::ITunesService, tmdb::TmdbService},
     traits::{IsFeatureEnabled, MediaProviderLanguages},
 };
@@ -80,6 +84,10 @@ pub struct GoogleBooksConfig {}
 pub struct OpenlibraryConfig {
     /// The image sizes to fetch from Openlibrary.
     pub cover_image_size: OpenlibraryCoverImageSize,
+    /// Whether the covers of the editions of a book will be used when the book
+    /// does not have a cover of its own.
+    #[setting(default = true)]
+    pub prefer_edition_covers: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Config)]
@@ -130,7 +138,20 @@ pub struct ExerciseConfig {
 
 #[derive(Debug, Serialize, Deserialize, Clone, Config)]
 #[config(rename_all = "snake_case", env_prefix = "MEDIA_")]
-pub struct MediaConfig {}
+pub struct MediaConfig {
+    /// The number of seconds for which search results and details fetched from
+    /// providers are cached in memory.
+    #[setting(default = 600)]
+    pub provider_cache_ttl: u64,
+    /// The maximum number of provider responses to keep in memory.
+    #[setting(default = 1000)]
+    pub provider_cache_size: usize,
+    /// The number of refreshes in a row for which the provider returns no
+    /// details after which a media item is shown as unavailable. Set to `0` to
+    /// never mark media as unavailable.
+    #[setting(default = 3)]
+    pub unavailable_after_failed_refreshes: i32,
+}
 
 fn validate_tmdb_locale(value: &str) -> Result<(), ValidateError> {
     if !TmdbService::supported_languages().contains(&value.to_owned()) {
@@ -161,6 +182,12 @@ pub struct MoviesTmdbConfig {
     pub locale: String,
 }
 
+impl IsFeatureEnabled for MoviesTmdbConfig {
+    fn is_enabled(&self) -> bool {
+        !self.access_token.is_empty()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Config)]
 pub struct MovieConfig {
     /// Settings related to TMDB (movies).
@@ -190,6 +217,12 @@ pub struct ListenNotesConfig {
     pub api_token: String,
 }
 
+impl IsFeatureEnabled for ListenNotesConfig {
+    fn is_enabled(&self) -> bool {
+        !self.api_token.is_empty()
+    }
+}
+
 fn validate_itunes_locale(
     value: &str,
     _partial: &PartialITunesConfig,
@@ -243,6 +276,12 @@ pub struct ShowsTmdbConfig {
     pub locale: String,
 }
 
+impl IsFeatureEnabled for ShowsTmdbConfig {
+    fn is_enabled(&self) -> bool {
+        !self.access_token.is_empty()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Config)]
 pub struct ShowConfig {
     /// Settings related to TMDB (shows).
@@ -349,12 +388,67 @@ pub struct SchedulerConfig {
     /// the background.
     #[setting(default = 5)]
     pub rate_limit_num: i32,
+    /// The maximum number of metadata update jobs that are processed at the
+    /// same time. Values below 1 are treated as 1.
+    #[setting(default = 2)]
+    pub metadata_update_concurrency: usize,
     /// Deploy a job every x hours that performs user cleanup and summary
     /// calculation.
     #[setting(default = 12)]
     pub user_cleanup_every: i32,
 }
 
+impl SchedulerConfig {
+    /// The number of metadata update jobs that are actually allowed to run at
+    /// the same time.
+    pub fn effective_metadata_update_concurrency(&self) -> usize {
+        self.metadata_update_concurrency.max(1)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Config)]
+#[config(rename_all = "snake_case", env_prefix = "SUMMARY_")]
+pub struct SummaryConfig {
+    /// Whether to fall back to the average episode runtime of a show or podcast
+    /// when an episode does not have a runtime of its own.
+    pub use_average_episode_runtime: bool,
+}
+
+fn validate_username_pattern(
+    value: &str,
+    _partial: &PartialUsersConfig,
+    _context: &(),
+) -> Result<(), ValidateError> {
+    if Regex::new(value).is_err() {
+        return Err(ValidateError::new(format!(
+            "This is not a valid regular expression: {:?}",
+            value
+        )));
+    }
+    Ok(())
+}
+
+/// A collection that is created for every new user.
+#[derive(Debug, Serialize, Deserialize, Clone, Config, PartialEq, Eq)]
+#[config(rename_all = "snake_case")]
+pub struct DefaultCollectionConfig {
+    /// The name of the collection.
+    pub name: String,
+    /// The description of the collection.
+    pub description: Option<String>,
+}
+
+fn default_user_collections(_ctx: &()) -> Option<Vec<DefaultCollectionConfig>> {
+    Some(
+        DefaultCollection::iter()
+            .map(|c| DefaultCollectionConfig {
+                name: c.to_string(),
+                description: Some(c.meta().to_owned()),
+            })
+            .collect(),
+    )
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Config)]
 #[config(rename_all = "snake_case", env_prefix = "USERS_")]
 pub struct UsersConfig {
@@ -365,6 +459,73 @@ pub struct UsersConfig {
     /// Whether new users will be allowed to sign up to this instance.
     #[setting(default = true)]
     pub allow_registration: bool,
+    /// The minimum number of characters in a username.
+    #[setting(default = 3)]
+    pub username_min_length: usize,
+    /// The maximum number of characters in a username.
+    #[setting(default = 32)]
+    pub username_max_length: usize,
+    /// A regular expression that all usernames must match.
+    #[setting(validate = validate_username_pattern, default = "^[a-zA-Z0-9_.-]+$")]
+    pub username_pattern: String,
+    /// Usernames that can not be used by anyone.
+    #[setting(default = vec![], parse_env = schematic::env::split_comma)]
+    pub reserved_usernames: Vec<String>,
+    /// The minimum number of characters in a password.
+    #[setting(default = 8)]
+    pub password_min_length: usize,
+    /// Whether passwords must contain both letters and numbers.
+    pub password_require_letters_and_numbers: bool,
+    /// The collections that are created for every new user. These can not be
+    /// deleted or merged by users. Media are automatically added to and removed
+    /// from the "In Progress", "Watchlist" and "Custom" collections, so those
+    /// should usually be kept.
+    #[setting(default = default_user_collections)]
+    pub default_collections: Vec<DefaultCollectionConfig>,
+    /// The name of the collection that media are added to when a user finishes
+    /// them, like "Completed". It is created for a user when needed. Shows and
+    /// podcasts are not added since they are seen one episode at a time. Media
+    /// are not added anywhere if this is not set.
+    pub completed_collection: Option<String>,
+    /// Whether the tokens issued to verify the email of a user are logged at the
+    /// debug level. Emails can not be sent, so this is the only way for an admin
+    /// to pass them on to the users.
+    pub log_email_verification_tokens: bool,
+}
+
+impl UsersConfig {
+    /// Whether a username satisfies the rules configured for this instance.
+    pub fn is_valid_username(&self, username: &str) -> bool {
+        let length = username.chars().count();
+        length >= self.username_min_length
+            && length <= self.username_max_length
+            && Regex::new(&self.username_pattern)
+                .map(|r| r.is_match(username))
+                .unwrap_or(false)
+            && !self
+                .reserved_usernames
+                .iter()
+                .any(|r| r.eq_ignore_ascii_case(username))
+    }
+
+    /// Whether a collection with this name is one of the configured default
+    /// collections.
+    pub fn is_default_collection(&self, name: &str) -> bool {
+        self.default_collections.iter().any(|c| c.name == name)
+    }
+
+    /// Whether a password satisfies the strength rules configured for this
+    /// instance.
+    pub fn is_strong_password(&self, password: &str) -> bool {
+        if password.chars().count() < self.password_min_length {
+            return false;
+        }
+        if self.password_require_letters_and_numbers {
+            return password.chars().any(|c| c.is_alphabetic())
+                && password.chars().any(|c| c.is_ascii_digit());
+        }
+        true
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Config)]
@@ -424,6 +585,9 @@ pub struct AppConfig {
     /// Settings related to shows.
     #[setting(nested)]
     pub shows: ShowConfig,
+    /// Settings related to the user summary.
+    #[setting(nested)]
+    pub summary: SummaryConfig,
     /// Settings related to users.
     #[setting(nested)]
     pub users: UsersConfig,
@@ -471,3 +635,45 @@ pub fn get_app_config() -> Result<AppConfig> {
 
     Ok(result.config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users_config(password_require_letters_and_numbers: bool) -> UsersConfig {
+        UsersConfig {
+            allow_changing_username: true,
+            allow_registration: true,
+            username_min_length: 3,
+            username_max_length: 32,
+            username_pattern: "^[a-zA-Z0-9_.-]+$".to_owned(),
+            reserved_usernames: vec![],
+            password_min_length: 8,
+            password_require_letters_and_numbers,
+            default_collections: default_user_collections(&()).unwrap(),
+            completed_collection: None,
+            log_email_verification_tokens: false,
+        }
+    }
+
+    #[test]
+    fn password_strength_is_enforced() {
+        let config = users_config(false);
+        assert!(!config.is_strong_password("short"));
+        assert!(config.is_strong_password("longenough"));
+
+        let config = users_config(true);
+        assert!(!config.is_strong_password("longenough"));
+        assert!(!config.is_strong_password("12345678"));
+        assert!(config.is_strong_password("longenough1"));
+    }
+
+    #[test]
+    fn builtin_collections_are_the_default_collections() {
+        let config = users_config(false);
+        assert!(config.is_default_collection("In Progress"));
+        assert!(config.is_default_collection("Watchlist"));
+        assert!(config.is_default_collection("Custom"));
+        assert!(!config.is_default_collection("Favorites"));
+    }
+}
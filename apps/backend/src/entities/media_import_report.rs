@@ -4,7 +4,10 @@ use async_graphql::SimpleObject;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::{importer::ImportResultResponse, migrator::MediaImportSource};
+use crate::{
+    importer::{DeployImportInput, ImportResultResponse},
+    migrator::MediaImportSource,
+};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, SimpleObject)]
 #[sea_orm(table_name = "media_import_report")]
@@ -18,6 +21,9 @@ pub struct Model {
     pub finished_on: Option<DateTimeUtc>,
     pub details: Option<ImportResultResponse>,
     pub success: Option<bool>,
+    #[graphql(skip)]
+    #[serde(skip)]
+    pub input: Option<DeployImportInput>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     migrator::{MetadataLot, MetadataSource},
-    miscellaneous::{MediaSpecifics, MetadataCreators, MetadataImages},
+    miscellaneous::{
+        MediaSpecifics, MetadataCreators, MetadataFranchise, MetadataImages, MetadataWatchProviders,
+    },
 };
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, Default)]
@@ -27,6 +29,15 @@ pub struct Model {
     pub creators: MetadataCreators,
     pub source: MetadataSource,
     pub specifics: MediaSpecifics,
+    pub created_by: Option<i32>,
+    pub content_hash: Option<String>,
+    pub watch_providers: Option<MetadataWatchProviders>,
+    pub provider_rating: Option<Decimal>,
+    pub popularity: i32,
+    pub original_language: Option<String>,
+    pub franchise: Option<MetadataFranchise>,
+    pub slug: Option<String>,
+    pub failed_refreshes: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
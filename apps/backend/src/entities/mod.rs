@@ -10,7 +10,9 @@ pub mod metadata;
 pub mod metadata_to_collection;
 pub mod metadata_to_genre;
 pub mod review;
+pub mod review_reaction;
 pub mod seen;
 pub mod summary;
 pub mod user;
+pub mod user_exercise_favorite;
 pub mod user_to_metadata;
@@ -7,7 +7,8 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    miscellaneous::SeenExtraInformation, models::media::Visibility,
+    miscellaneous::{ReviewSpoilerScope, SeenExtraInformation},
+    models::media::Visibility,
     utils::associate_user_with_metadata,
 };
 
@@ -30,6 +31,9 @@ pub struct Model {
     pub extra_information: Option<SeenExtraInformation>,
     #[graphql(skip)]
     pub identifier: Option<String>,
+    pub pinned: bool,
+    #[graphql(skip)]
+    pub spoils_up_to: Option<ReviewSpoilerScope>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
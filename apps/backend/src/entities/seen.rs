@@ -36,6 +36,10 @@ pub struct Model {
     #[serde(skip)]
     pub identifier: Option<String>,
     pub dropped: bool,
+    /// Set when the user consumes a media item again, starting from 2.
+    pub playthrough: Option<i32>,
+    /// A free-text note about this viewing, eg. who it was watched with.
+    pub note: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
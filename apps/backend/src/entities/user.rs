@@ -33,6 +33,12 @@ pub struct Model {
     pub preferences: UserPreferences,
     #[graphql(skip)]
     pub yank_integrations: Option<UserYankIntegrations>,
+    #[graphql(skip)]
+    pub last_yank_integration_id: i32,
+    pub email_verified: bool,
+    #[graphql(skip)]
+    pub email_verification_token: Option<String>,
+    pub created_on: Option<DateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -13,13 +13,14 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub metadata_id: i32,
     pub last_updated_on: DateTimeUtc,
+    pub image_override: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
         belongs_to = "super::metadata::Entity",
-        from = "Column::UserId",
+        from = "Column::MetadataId",
         to = "super::metadata::Column::Id",
         on_update = "Cascade",
         on_delete = "Cascade"
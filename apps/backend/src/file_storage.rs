@@ -2,6 +2,7 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 use aws_sdk_s3::{presigning::PresigningConfig, primitives::ByteStream};
+use futures::future::join_all;
 
 #[derive(Debug)]
 pub struct FileStorageService {
@@ -26,6 +27,40 @@ impl FileStorageService {
             .is_ok()
     }
 
+    /// Whether an object with the given key is present in the bucket.
+    pub async fn exists(&self, key: &str) -> Result<bool> {
+        match self
+            .s3_client
+            .head_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let e = e.into_service_error();
+                if e.is_not_found() {
+                    Ok(false)
+                } else {
+                    Err(e).context("Could not check if file exists")
+                }
+            }
+        }
+    }
+
+    /// Remove an object from the bucket. Keys that do not exist are considered
+    /// to be deleted successfully.
+    pub async fn delete(&self, key: &str) -> bool {
+        self.s3_client
+            .delete_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .send()
+            .await
+            .is_ok()
+    }
+
     pub async fn get_presigned_url(&self, key: String) -> String {
         self.s3_client
             .get_object()
@@ -38,6 +73,10 @@ impl FileStorageService {
             .to_string()
     }
 
+    pub async fn get_presigned_urls(&self, keys: Vec<String>) -> Vec<String> {
+        join_all(keys.into_iter().map(|k| self.get_presigned_url(k))).await
+    }
+
     pub async fn upload_file(&self, key: &str, data: ByteStream) -> Result<()> {
         self.s3_client
             .put_object()
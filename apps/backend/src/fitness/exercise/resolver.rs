@@ -1,30 +1,41 @@
-use std::{env, ffi::OsStr, path::Path, sync::Arc};
+use std::{collections::HashSet, env, ffi::OsStr, path::Path, sync::Arc};
 
 use apalis::{prelude::Storage, sqlite::SqliteStorage};
 use async_graphql::{Context, Error, InputObject, Object, Result};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait,
-    QueryFilter, QueryOrder, QueryTrait,
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseBackend,
+    DatabaseConnection, EntityTrait, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, QueryTrait, Select,
 };
-use sea_query::{Condition, Expr, Func};
+use sea_query::{Condition, Expr, Func, SimpleExpr};
 use serde::{Deserialize, Serialize};
 use slug::slugify;
 
 use crate::{
     background::UpdateExerciseJob,
-    entities::{exercise, prelude::Exercise},
+    entities::{
+        exercise,
+        prelude::{Exercise, UserExerciseFavorite},
+        user_exercise_favorite,
+    },
     file_storage::FileStorageService,
     models::{
-        fitness::{Exercise as GithubExercise, ExerciseAttributes},
+        fitness::{
+            Exercise as GithubExercise, ExerciseAttributes, ExerciseListItem, ExerciseMuscle,
+        },
         SearchResults,
     },
-    utils::{get_case_insensitive_like_query, PAGE_LIMIT},
+    utils::{get_case_insensitive_like_query, user_id_from_ctx, PAGE_LIMIT},
 };
 
 #[derive(Debug, Serialize, Deserialize, InputObject, Clone)]
 pub struct ExercisesListInput {
     pub page: i32,
     pub query: Option<String>,
+    /// Only get the exercises that need no equipment other than the body
+    #[graphql(default)]
+    #[serde(default)]
+    pub bodyweight_only: bool,
 }
 
 #[derive(Default)]
@@ -37,10 +48,38 @@ impl ExerciseQuery {
         &self,
         gql_ctx: &Context<'_>,
         input: ExercisesListInput,
-    ) -> Result<SearchResults<exercise::Model>> {
+    ) -> Result<SearchResults<ExerciseListItem>> {
+        let user_id = user_id_from_ctx(gql_ctx).await.ok();
+        gql_ctx
+            .data_unchecked::<Arc<ExerciseService>>()
+            .exercises_list(input, user_id)
+            .await
+    }
+
+    /// Get the exercises that the logged in user has favorited
+    async fn favorite_exercises(
+        &self,
+        gql_ctx: &Context<'_>,
+        page: i32,
+    ) -> Result<SearchResults<ExerciseListItem>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
         gql_ctx
             .data_unchecked::<Arc<ExerciseService>>()
-            .exercises_list(input)
+            .favorite_exercises(user_id, page)
+            .await
+    }
+
+    /// Get other exercises that work at least one of the primary muscles of
+    /// an exercise.
+    async fn related_exercises(
+        &self,
+        gql_ctx: &Context<'_>,
+        exercise_id: i32,
+        #[graphql(default = 10)] limit: u64,
+    ) -> Result<Vec<exercise::Model>> {
+        gql_ctx
+            .data_unchecked::<Arc<ExerciseService>>()
+            .related_exercises(exercise_id, limit)
             .await
     }
 }
@@ -57,6 +96,20 @@ impl ExerciseMutation {
             .deploy_update_exercise_library_job()
             .await
     }
+
+    /// Add an exercise to the favorites of the logged in user, or remove it if
+    /// it is already there. Returns whether the exercise is now a favorite.
+    async fn toggle_exercise_favorite(
+        &self,
+        gql_ctx: &Context<'_>,
+        exercise_id: i32,
+    ) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<ExerciseService>>()
+            .toggle_exercise_favorite(user_id, exercise_id)
+            .await
+    }
 }
 
 #[derive(Debug)]
@@ -112,10 +165,61 @@ impl ExerciseService {
             .collect())
     }
 
+    /// Replace the storage keys of the images of an exercise with URLs.
+    async fn with_presigned_images(&self, mut ex: exercise::Model) -> exercise::Model {
+        let mut images = vec![];
+        for i in ex.attributes.images {
+            let mut link = self.file_storage.get_presigned_url(i).await;
+            // DEV: For the Expo app, since we are accessing the images on a
+            // mobile device, we need to expose the minio instance and refer
+            // to that in all images.
+            if cfg!(feature = "development") {
+                let minio_url = env::var("S3_URL").unwrap();
+                let minio_public_url = env::var("S3_PUBLIC_URL").unwrap();
+                link = link.replace(&minio_url, &minio_public_url);
+                if let Some((m, _)) = link.split_once("?") {
+                    link = m.to_owned();
+                }
+            }
+            images.push(link);
+        }
+        ex.attributes.images = images;
+        ex
+    }
+
+    async fn related_exercises(
+        &self,
+        exercise_id: i32,
+        limit: u64,
+    ) -> Result<Vec<exercise::Model>> {
+        let Some(ex) = Exercise::find_by_id(exercise_id).one(&self.db).await? else {
+            return Err(Error::new("Exercise not found".to_owned()));
+        };
+        if ex.attributes.primary_muscles.is_empty() {
+            return Ok(vec![]);
+        }
+        let related = Exercise::find()
+            .filter(exercise::Column::Id.ne(exercise_id))
+            .filter(shares_primary_muscle_condition(
+                self.db.get_database_backend(),
+                &ex.attributes.primary_muscles,
+            ))
+            .order_by_asc(exercise::Column::Name)
+            .limit(limit)
+            .all(&self.db)
+            .await?;
+        let mut resp = vec![];
+        for ex in related {
+            resp.push(self.with_presigned_images(ex).await);
+        }
+        Ok(resp)
+    }
+
     async fn exercises_list(
         &self,
         input: ExercisesListInput,
-    ) -> Result<SearchResults<exercise::Model>> {
+        user_id: Option<i32>,
+    ) -> Result<SearchResults<ExerciseListItem>> {
         let query = Exercise::find()
             .apply_if(input.query, |query, v| {
                 query.filter(Condition::all().add(get_case_insensitive_like_query(
@@ -123,37 +227,67 @@ impl ExerciseService {
                     &v,
                 )))
             })
+            .apply_if(input.bodyweight_only.then_some(()), |query, _| {
+                query.filter(bodyweight_only_condition(self.db.get_database_backend()))
+            })
             .order_by_asc(exercise::Column::Name);
+        self.exercises_page(query, input.page, user_id).await
+    }
+
+    async fn favorite_exercises(
+        &self,
+        user_id: i32,
+        page: i32,
+    ) -> Result<SearchResults<ExerciseListItem>> {
+        let query = Exercise::find()
+            .filter(
+                exercise::Column::Id.in_subquery(
+                    UserExerciseFavorite::find()
+                        .select_only()
+                        .column(user_exercise_favorite::Column::ExerciseId)
+                        .filter(user_exercise_favorite::Column::UserId.eq(user_id))
+                        .into_query(),
+                ),
+            )
+            .order_by_asc(exercise::Column::Name);
+        self.exercises_page(query, page, Some(user_id)).await
+    }
+
+    /// Get a page of exercises, marking the ones the user has favorited.
+    async fn exercises_page(
+        &self,
+        query: Select<exercise::Entity>,
+        page: i32,
+        user_id: Option<i32>,
+    ) -> Result<SearchResults<ExerciseListItem>> {
         let total = query.clone().count(&self.db).await?;
         let total: i32 = total.try_into().unwrap();
         let data = query.paginate(&self.db, PAGE_LIMIT.try_into().unwrap());
+        let exercises = data.fetch_page((page - 1).try_into().unwrap()).await?;
+        let favorites: HashSet<i32> = match user_id {
+            Some(user_id) => UserExerciseFavorite::find()
+                .filter(user_exercise_favorite::Column::UserId.eq(user_id))
+                .filter(
+                    user_exercise_favorite::Column::ExerciseId
+                        .is_in(exercises.iter().map(|e| e.id)),
+                )
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|f| f.exercise_id)
+                .collect(),
+            None => HashSet::new(),
+        };
         let mut resp = vec![];
-        for ex in data
-            .fetch_page((input.page - 1).try_into().unwrap())
-            .await?
-        {
-            let mut ex_new = ex.clone();
-            let mut images = vec![];
-            for i in ex.attributes.images {
-                let mut link = self.file_storage.get_presigned_url(i).await;
-                // DEV: For the Expo app, since we are accessing the images on a
-                // mobile device, we need to expose the minio instance and refer
-                // to that in all images.
-                if cfg!(feature = "development") {
-                    let minio_url = env::var("S3_URL").unwrap();
-                    let minio_public_url = env::var("S3_PUBLIC_URL").unwrap();
-                    link = link.replace(&minio_url, &minio_public_url);
-                    if let Some((m, _)) = link.split_once("?") {
-                        link = m.to_owned();
-                    }
-                }
-                images.push(link);
-            }
-            ex_new.attributes.images = images;
-            resp.push(ex_new);
+        for ex in exercises {
+            let favorite = favorites.contains(&ex.id);
+            resp.push(ExerciseListItem {
+                exercise: self.with_presigned_images(ex).await,
+                favorite,
+            });
         }
-        let next_page = if total - ((input.page) * PAGE_LIMIT) > 0 {
-            Some(input.page + 1)
+        let next_page = if total - (page * PAGE_LIMIT) > 0 {
+            Some(page + 1)
         } else {
             None
         };
@@ -164,6 +298,10 @@ impl ExerciseService {
         })
     }
 
+    async fn toggle_exercise_favorite(&self, user_id: i32, exercise_id: i32) -> Result<bool> {
+        toggle_exercise_favorite(&self.db, user_id, exercise_id).await
+    }
+
     async fn deploy_update_exercise_library_job(&self) -> Result<i32> {
         if !self.file_storage.is_enabled().await {
             return Err(Error::new(
@@ -222,3 +360,207 @@ impl ExerciseService {
         Ok(())
     }
 }
+
+/// Favorite an exercise for a user, or remove it from their favorites if it
+/// already is one. Returns whether the exercise is now a favorite.
+async fn toggle_exercise_favorite<C>(db: &C, user_id: i32, exercise_id: i32) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    if Exercise::find_by_id(exercise_id).one(db).await?.is_none() {
+        return Err(Error::new("Exercise not found".to_owned()));
+    }
+    match UserExerciseFavorite::find_by_id((user_id, exercise_id))
+        .one(db)
+        .await?
+    {
+        Some(favorite) => {
+            favorite.delete(db).await?;
+            Ok(false)
+        }
+        None => {
+            user_exercise_favorite::ActiveModel {
+                user_id: ActiveValue::Set(user_id),
+                exercise_id: ActiveValue::Set(exercise_id),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+            Ok(true)
+        }
+    }
+}
+
+/// Exercises that have no equipment, or only need the body. The equipment is
+/// `null` in the JSON attributes when there is none.
+fn bodyweight_only_condition(backend: DatabaseBackend) -> SimpleExpr {
+    let equipment = match backend {
+        DatabaseBackend::MySql => {
+            "JSON_UNQUOTE(JSON_EXTRACT(`exercise`.`attributes`, '$.equipment'))"
+        }
+        DatabaseBackend::Postgres => r#""exercise"."attributes" ->> 'equipment'"#,
+        DatabaseBackend::Sqlite => r#"json_extract("exercise"."attributes", '$.equipment')"#,
+    };
+    // MySQL returns the string `null` for JSON nulls
+    Expr::cust(&format!(
+        "({equipment} IS NULL OR {equipment} IN ('null', 'body only'))"
+    ))
+}
+
+/// Exercises whose primary muscles include any of these muscles.
+fn shares_primary_muscle_condition(
+    backend: DatabaseBackend,
+    muscles: &[ExerciseMuscle],
+) -> SimpleExpr {
+    // the names are the serialized enum variants, so they need no escaping
+    let names = muscles
+        .iter()
+        .map(|m| {
+            serde_json::to_value(m)
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_owned()
+        })
+        .collect::<Vec<_>>();
+    let quoted = names
+        .iter()
+        .map(|n| format!("'{n}'"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let condition = match backend {
+        DatabaseBackend::MySql => format!(
+            "JSON_OVERLAPS(JSON_EXTRACT(`exercise`.`attributes`, '$.primaryMuscles'), '{}')",
+            serde_json::to_string(&names).unwrap()
+        ),
+        DatabaseBackend::Postgres => {
+            format!(r#"("exercise"."attributes" -> 'primaryMuscles')::jsonb ?| array[{quoted}]"#)
+        }
+        DatabaseBackend::Sqlite => {
+            let muscles = r#"json_each("exercise"."attributes", '$.primaryMuscles')"#;
+            format!("EXISTS (SELECT 1 FROM {muscles} WHERE value IN ({quoted}))")
+        }
+    };
+    Expr::cust(&condition)
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::Database;
+    use sea_orm_migration::MigratorTrait;
+
+    use super::*;
+    use crate::{
+        entities::user,
+        migrator::{Migrator, UserLot},
+        models::fitness::{ExerciseCategory, ExerciseEquipment, ExerciseLevel},
+        users::UserPreferences,
+    };
+
+    async fn create_exercise(
+        db: &DatabaseConnection,
+        name: &str,
+        equipment: Option<ExerciseEquipment>,
+        primary_muscles: Vec<ExerciseMuscle>,
+    ) -> exercise::Model {
+        exercise::ActiveModel {
+            name: ActiveValue::Set(name.to_owned()),
+            identifier: ActiveValue::Set(name.to_owned()),
+            attributes: ActiveValue::Set(ExerciseAttributes {
+                force: None,
+                level: ExerciseLevel::Beginner,
+                mechanic: None,
+                equipment,
+                primary_muscles,
+                secondary_muscles: vec![],
+                category: ExerciseCategory::Strength,
+                instructions: vec![],
+                images: vec![],
+                alternate_names: vec![],
+            }),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn exercise_names(db: &DatabaseConnection, condition: SimpleExpr) -> Vec<String> {
+        Exercise::find()
+            .filter(condition)
+            .order_by_asc(exercise::Column::Name)
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn exercises_are_filtered_to_bodyweight_only() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        create_exercise(&db, "Push Up", Some(ExerciseEquipment::BodyOnly), vec![]).await;
+        create_exercise(&db, "Plank", None, vec![]).await;
+        create_exercise(&db, "Curl", Some(ExerciseEquipment::Dumbbell), vec![]).await;
+
+        let names = exercise_names(&db, bodyweight_only_condition(db.get_database_backend())).await;
+        assert_eq!(names, vec!["Plank", "Push Up"]);
+    }
+
+    #[tokio::test]
+    async fn exercises_sharing_a_primary_muscle_are_found() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let muscles = [
+            (
+                "Squat",
+                vec![ExerciseMuscle::Quadriceps, ExerciseMuscle::Glutes],
+            ),
+            ("Hip Thrust", vec![ExerciseMuscle::Glutes]),
+            ("Curl", vec![ExerciseMuscle::Biceps]),
+            ("Back Extension", vec![ExerciseMuscle::LowerBack]),
+        ];
+        for (name, primary_muscles) in muscles {
+            create_exercise(&db, name, None, primary_muscles).await;
+        }
+
+        let condition = shares_primary_muscle_condition(
+            db.get_database_backend(),
+            &[ExerciseMuscle::Glutes, ExerciseMuscle::LowerBack],
+        );
+        let names = exercise_names(&db, condition).await;
+        assert_eq!(names, vec!["Back Extension", "Hip Thrust", "Squat"]);
+    }
+
+    #[tokio::test]
+    async fn toggling_a_favorite_twice_removes_it() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let user = user::ActiveModel {
+            name: ActiveValue::Set("user".to_owned()),
+            password: ActiveValue::Set("password".to_owned()),
+            lot: ActiveValue::Set(UserLot::Normal),
+            preferences: ActiveValue::Set(UserPreferences::default()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let ex = create_exercise(&db, "Squat", None, vec![]).await;
+        let favorites = || {
+            UserExerciseFavorite::find()
+                .filter(user_exercise_favorite::Column::UserId.eq(user.id))
+                .count(&db)
+        };
+
+        assert!(toggle_exercise_favorite(&db, user.id, ex.id).await.unwrap());
+        assert_eq!(favorites().await.unwrap(), 1);
+        assert!(!toggle_exercise_favorite(&db, user.id, ex.id).await.unwrap());
+        assert_eq!(favorites().await.unwrap(), 0);
+        assert!(toggle_exercise_favorite(&db, user.id, ex.id + 1)
+            .await
+            .is_err());
+    }
+}
@@ -24,6 +24,10 @@ pub struct CoreDetails {
     author_name: String,
     repository_link: String,
     username_change_allowed: bool,
+    /// The number of metadata update jobs that are processed every 5 seconds
+    metadata_update_rate_limit: i32,
+    /// The number of metadata update jobs that are processed at the same time
+    metadata_update_concurrency: usize,
 }
 
 #[derive(Debug, SimpleObject, Serialize, Deserialize)]
@@ -44,6 +48,8 @@ impl CoreQuery {
             author_name: AUTHOR.to_owned(),
             repository_link: REPOSITORY_LINK.to_owned(),
             username_change_allowed: config.users.allow_changing_username,
+            metadata_update_rate_limit: config.scheduler.rate_limit_num,
+            metadata_update_concurrency: config.scheduler.effective_metadata_update_concurrency(),
         }
     }
 }
@@ -0,0 +1,126 @@
+use async_graphql::{Error, Result};
+use chrono::{NaiveDate, TimeZone, Utc};
+use graphql_client::{GraphQLQuery, Response};
+use rust_decimal::Decimal;
+
+use crate::{
+    importer::{
+        DeployAnilistImportInput, ImportItem, ImportItemIdentifier, ImportItemRating,
+        ImportItemSeen, ImportResult,
+    },
+    migrator::{MetadataLot, MetadataSource},
+    miscellaneous::DefaultCollection,
+    providers::anilist::URL,
+};
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/providers/anilist/schema.json",
+    query_path = "src/importer/anilist.graphql",
+    response_derives = "Debug"
+)]
+struct ListsQuery;
+
+pub async fn import(input: DeployAnilistImportInput) -> Result<ImportResult> {
+    let mut media = vec![];
+    for (type_, lot) in [
+        (lists_query::MediaType::ANIME, MetadataLot::Anime),
+        (lists_query::MediaType::MANGA, MetadataLot::Manga),
+    ] {
+        let body = ListsQuery::build_query(lists_query::Variables {
+            user_name: input.username.clone(),
+            type_,
+        });
+        let lists = surf::post(URL)
+            .body_json(&body)
+            .unwrap()
+            .await
+            .map_err(|e| Error::new(e.to_string()))?
+            .body_json::<Response<lists_query::ResponseData>>()
+            .await
+            .map_err(|e| Error::new(e.to_string()))?
+            .data
+            .and_then(|d| d.media_list_collection)
+            .ok_or_else(|| Error::new(format!("No lists found for user {}", input.username)))?
+            .lists;
+        for entry in lists
+            .into_iter()
+            .flatten()
+            .flatten()
+            .flat_map(|l| l.entries.into_iter().flatten().flatten())
+        {
+            let mut seen_history = vec![];
+            let mut collections = vec![];
+            let id = Some(format!("anilist-{}", entry.id));
+            match entry.status {
+                Some(lists_query::MediaListStatus::COMPLETED)
+                | Some(lists_query::MediaListStatus::REPEATING) => {
+                    let ended_on = entry.completed_at.and_then(|d| {
+                        NaiveDate::from_ymd_opt(
+                            d.year?.try_into().ok()?,
+                            d.month?.try_into().ok()?,
+                            d.day?.try_into().ok()?,
+                        )
+                    });
+                    seen_history.push(ImportItemSeen {
+                        id,
+                        ended_on: ended_on
+                            .map(|d| Utc.from_utc_datetime(&d.and_hms_opt(0, 0, 0).unwrap())),
+                        show_season_number: None,
+                        show_episode_number: None,
+                        podcast_episode_number: None,
+                        progress: None,
+                        dropped: false,
+                    });
+                }
+                Some(lists_query::MediaListStatus::CURRENT)
+                | Some(lists_query::MediaListStatus::PAUSED)
+                | Some(lists_query::MediaListStatus::DROPPED) => {
+                    seen_history.push(ImportItemSeen {
+                        id,
+                        ended_on: None,
+                        show_season_number: None,
+                        show_episode_number: None,
+                        podcast_episode_number: None,
+                        progress: Some(0),
+                        dropped: matches!(
+                            entry.status,
+                            Some(lists_query::MediaListStatus::DROPPED)
+                        ),
+                    });
+                }
+                Some(lists_query::MediaListStatus::PLANNING) => {
+                    collections.push(DefaultCollection::Watchlist.to_string());
+                }
+                Some(lists_query::MediaListStatus::Other(_)) | None => {}
+            }
+            let mut reviews = vec![];
+            // an unscored entry has a score of zero
+            if let Some(rating) = entry
+                .score
+                .filter(|s| *s > 0.0)
+                .and_then(Decimal::from_f64_retain)
+            {
+                reviews.push(ImportItemRating {
+                    id: None,
+                    review: None,
+                    rating: Some(rating.round_dp(2)),
+                });
+            }
+            media.push(ImportItem {
+                source_id: entry.media_id.to_string(),
+                lot,
+                source: MetadataSource::Anilist,
+                identifier: ImportItemIdentifier::NeedsDetails(entry.media_id.to_string()),
+                seen_history,
+                reviews,
+                collections,
+            });
+        }
+    }
+    Ok(ImportResult {
+        media,
+        failed_items: vec![],
+        collections: vec![],
+    })
+}
@@ -93,6 +93,8 @@ pub async fn import(input: DeployGoodreadsImportInput) -> Result<ImportResult> {
                         show_season_number: None,
                         show_episode_number: None,
                         podcast_episode_number: None,
+                        progress: None,
+                        dropped: false,
                     });
                 }
 
@@ -126,6 +128,10 @@ pub async fn import(input: DeployGoodreadsImportInput) -> Result<ImportResult> {
                         specifics: MediaSpecifics::Book(BookSpecifics {
                             pages: d.book.num_pages.parse().ok(),
                         }),
+                        provider_rating: None,
+                        original_language: None,
+                        franchise: None,
+                        slug: None,
                     })),
                     seen_history,
                     collections: default_collections,
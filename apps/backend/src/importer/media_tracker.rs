@@ -281,6 +281,10 @@ pub async fn import(input: DeployMediaTrackerImportInput) -> Result<ImportResult
                     specifics: MediaSpecifics::Book(BookSpecifics {
                         pages: details.number_of_pages,
                     }),
+                    provider_rating: None,
+                    original_language: None,
+                    franchise: None,
+                    slug: None,
                 })),
                 true => ImportItemIdentifier::NeedsDetails(identifier),
             },
@@ -323,6 +327,8 @@ pub async fn import(input: DeployMediaTrackerImportInput) -> Result<ImportResult
                         show_episode_number: episode_number,
                         // DEV: Since this source does not support podcasts
                         podcast_episode_number: None,
+                        progress: None,
+                        dropped: false,
                     }
                 })
                 .collect(),
@@ -1,8 +1,9 @@
 use std::sync::Arc;
 
 use apalis::{prelude::Storage, sqlite::SqliteStorage};
-use async_graphql::{Context, Enum, InputObject, Object, Result, SimpleObject};
+use async_graphql::{Context, Enum, Error, InputObject, Object, Result, SimpleObject};
 use chrono::{Duration, Utc};
+use itertools::Itertools;
 use rust_decimal::Decimal;
 use sea_orm::{
     prelude::DateTimeUtc, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection,
@@ -19,9 +20,10 @@ use crate::{
         AddMediaToCollection, CreateOrUpdateCollectionInput, MediaDetails, PostReviewInput,
         ProgressUpdateInput,
     },
-    utils::user_id_from_ctx,
+    utils::{coded_error, has_completed_episode, user_id_from_ctx, ErrorCode},
 };
 
+mod anilist;
 mod goodreads;
 mod media_tracker;
 
@@ -39,7 +41,27 @@ pub struct ImportItemRating {
     rating: Option<Decimal>,
 }
 
-#[derive(Debug, InputObject, Serialize, Deserialize, Clone)]
+impl ImportItemRating {
+    fn to_review_input(&self, metadata_id: i32) -> PostReviewInput {
+        let review = self.review.clone();
+        PostReviewInput {
+            identifier: self.id.clone(),
+            rating: self.rating,
+            text: review.as_ref().map(|r| r.text.clone()),
+            spoiler: review.as_ref().map(|r| r.spoiler),
+            date: review.and_then(|r| r.date),
+            visibility: None,
+            metadata_id,
+            review_id: None,
+            season_number: None,
+            episode_number: None,
+            spoils_up_to_season: None,
+            spoils_up_to_episode: None,
+        }
+    }
+}
+
+#[derive(Debug, InputObject, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct DeployMediaTrackerImportInput {
     /// The base url where the resource is present at
     api_url: String,
@@ -47,17 +69,65 @@ pub struct DeployMediaTrackerImportInput {
     api_key: String,
 }
 
-#[derive(Debug, InputObject, Serialize, Deserialize, Clone)]
+#[derive(Debug, InputObject, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct DeployGoodreadsImportInput {
     // The RSS url that can be found from the user's profile
     rss_url: String,
 }
 
-#[derive(Debug, InputObject, Serialize, Deserialize, Clone)]
+#[derive(Debug, InputObject, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DeployAnilistImportInput {
+    /// The name of the user whose anime and manga lists will be imported
+    username: String,
+}
+
+#[derive(Debug, InputObject, Serialize, Deserialize, Clone, PartialEq, Eq, FromJsonQueryResult)]
 pub struct DeployImportInput {
     pub source: MediaImportSource,
     pub media_tracker: Option<DeployMediaTrackerImportInput>,
     pub goodreads: Option<DeployGoodreadsImportInput>,
+    pub anilist: Option<DeployAnilistImportInput>,
+    /// The scale of the ratings in the source. If set, they are converted to
+    /// ratings out of 5 before being saved.
+    pub source_rating_scale: Option<RatingScale>,
+}
+
+impl DeployImportInput {
+    /// A copy of the input without the credentials for the source, so that it
+    /// can be stored in the import report.
+    fn without_secrets(&self) -> Self {
+        let mut input = self.clone();
+        if let Some(media_tracker) = input.media_tracker.as_mut() {
+            media_tracker.api_key = String::new();
+        }
+        input
+    }
+}
+
+/// The scale of the ratings in an import source. Ratings are always stored out
+/// of 5.
+#[derive(Debug, Serialize, Deserialize, Enum, Clone, Copy, PartialEq, Eq)]
+pub enum RatingScale {
+    OutOfFive,
+    OutOfTen,
+    OutOfHundred,
+}
+
+impl RatingScale {
+    /// The highest rating possible on this scale.
+    pub fn max(&self) -> Decimal {
+        match self {
+            Self::OutOfFive => Decimal::from(5),
+            Self::OutOfTen => Decimal::from(10),
+            Self::OutOfHundred => Decimal::from(100),
+        }
+    }
+
+    /// Convert a rating given on this scale to the `target` scale, keeping the
+    /// same proportion of the maximum rating.
+    pub fn convert(&self, rating: Decimal, target: Self) -> Decimal {
+        (rating * target.max() / self.max()).round_dp(2)
+    }
 }
 
 #[derive(Debug, SimpleObject)]
@@ -67,6 +137,10 @@ pub struct ImportItemSeen {
     show_season_number: Option<i32>,
     show_episode_number: Option<i32>,
     podcast_episode_number: Option<i32>,
+    /// The progress of an item that is not finished yet, finished otherwise
+    progress: Option<i32>,
+    /// Whether the item was abandoned at this progress
+    dropped: bool,
 }
 
 #[derive(Debug)]
@@ -95,6 +169,10 @@ pub enum ImportFailStep {
     ItemDetailsFromSource,
     /// Failed to get metadata from the provider (for eg: Openlibrary, IGDB etc.)
     MediaDetailsFromProvider,
+    /// Failed to save a seen history item of media that was imported
+    SeenHistoryToDatabase,
+    /// Failed to save a review of media that was imported
+    ReviewToDatabase,
 }
 
 #[derive(
@@ -109,7 +187,12 @@ pub struct ImportFailedItem {
 
 #[derive(Debug, SimpleObject, Serialize, Deserialize, Eq, PartialEq, Clone)]
 pub struct ImportDetails {
+    /// The number of media that were imported, some of their seen history or
+    /// reviews might still have failed
     pub total: usize,
+    /// The number of media that could not be imported at all
+    #[serde(default)]
+    pub failed: usize,
 }
 
 #[derive(Debug)]
@@ -128,6 +211,21 @@ pub struct ImportResultResponse {
     pub failed_items: Vec<ImportFailedItem>,
 }
 
+/// What an import would do if it was deployed
+#[derive(Debug, SimpleObject)]
+pub struct ImportPreview {
+    pub source: MediaImportSource,
+    /// The number of items that could be resolved to a provider
+    pub matched: usize,
+    /// The number of items that could not be fetched or resolved
+    pub unmatched: usize,
+    /// The number of seen history items that would be created
+    pub seen_count: usize,
+    /// The number of reviews that would be created
+    pub reviews_count: usize,
+    pub failed_items: Vec<ImportFailedItem>,
+}
+
 #[derive(Default)]
 pub struct ImporterQuery;
 
@@ -144,6 +242,19 @@ impl ImporterQuery {
             .media_import_reports(user_id)
             .await
     }
+
+    /// Check what an import would do without committing anything.
+    async fn preview_import(
+        &self,
+        gql_ctx: &Context<'_>,
+        input: DeployImportInput,
+    ) -> Result<ImportPreview> {
+        user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<ImporterService>>()
+            .preview_import(input)
+            .await
+    }
 }
 
 #[derive(Default)]
@@ -163,6 +274,22 @@ impl ImporterMutation {
             .deploy_import(user_id, input)
             .await
     }
+
+    /// Add job to import only the items that failed in an earlier import. Only
+    /// imports deployed after this was added can be retried. The api key of a
+    /// MediaTracker import is not stored, so it has to be given again.
+    async fn retry_import_failures(
+        &self,
+        gql_ctx: &Context<'_>,
+        report_id: i32,
+        api_key: Option<String>,
+    ) -> Result<String> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<ImporterService>>()
+            .retry_import_failures(user_id, report_id, api_key)
+            .await
+    }
 }
 
 pub struct ImporterService {
@@ -185,19 +312,13 @@ impl ImporterService {
         }
     }
 
-    pub async fn deploy_import(
-        &self,
-        user_id: i32,
-        mut input: DeployImportInput,
-    ) -> Result<String> {
+    pub async fn deploy_import(&self, user_id: i32, input: DeployImportInput) -> Result<String> {
         let mut storage = self.import_media.clone();
-        if let Some(s) = input.media_tracker.as_mut() {
-            s.api_url = s.api_url.trim_end_matches('/').to_owned()
-        }
         let job = storage
             .push(ImportMedia {
                 user_id: user_id.into(),
                 input,
+                only_items: None,
             })
             .await
             .unwrap();
@@ -227,17 +348,152 @@ impl ImporterService {
         self.media_service.media_import_reports(user_id).await
     }
 
-    pub async fn import_from_source(&self, user_id: i32, input: DeployImportInput) -> Result<()> {
-        let db_import_job = self
-            .media_service
-            .start_import_job(user_id, input.source)
-            .await?;
-        let mut import = match input.source {
+    async fn fetch_import(&self, input: DeployImportInput) -> Result<ImportResult> {
+        let import = match input.source {
             MediaImportSource::MediaTracker => {
-                media_tracker::import(input.media_tracker.unwrap()).await?
+                let Some(mut i) = input.media_tracker else {
+                    return Err(Error::new("MediaTracker details are required".to_owned()));
+                };
+                i.api_url = i.api_url.trim_end_matches('/').to_owned();
+                media_tracker::import(i).await?
             }
-            MediaImportSource::Goodreads => goodreads::import(input.goodreads.unwrap()).await?,
+            MediaImportSource::Goodreads => {
+                let Some(i) = input.goodreads else {
+                    return Err(Error::new("Goodreads details are required".to_owned()));
+                };
+                goodreads::import(i).await?
+            }
+            MediaImportSource::Anilist => {
+                let Some(i) = input.anilist else {
+                    return Err(Error::new("Anilist details are required".to_owned()));
+                };
+                anilist::import(i).await?
+            }
+        };
+        Ok(import)
+    }
+
+    pub async fn preview_import(&self, input: DeployImportInput) -> Result<ImportPreview> {
+        let source = input.source;
+        let import = self.fetch_import(input).await?;
+        let mut preview = ImportPreview {
+            source,
+            matched: 0,
+            unmatched: import.failed_items.len(),
+            seen_count: 0,
+            reviews_count: 0,
+            failed_items: import.failed_items,
+        };
+        for item in import.media.iter() {
+            let resolved = match &item.identifier {
+                ImportItemIdentifier::NeedsDetails(i) => {
+                    self.media_service
+                        .resolve_media(item.lot, item.source, i)
+                        .await
+                }
+                ImportItemIdentifier::AlreadyFilled(_) => Ok(()),
+            };
+            if let Err(e) = resolved {
+                preview.unmatched += 1;
+                preview.failed_items.push(ImportFailedItem {
+                    lot: item.lot,
+                    step: ImportFailStep::MediaDetailsFromProvider,
+                    identifier: item.source_id.to_owned(),
+                    error: Some(e.message),
+                });
+                continue;
+            }
+            preview.matched += 1;
+            preview.seen_count += item.seen_history.len();
+            preview.reviews_count += item
+                .reviews
+                .iter()
+                .filter(|r| r.to_review_input(0).has_content())
+                .count();
+        }
+        Ok(preview)
+    }
+
+    /// Retry the items that failed in an earlier import, creating a new report.
+    pub async fn retry_import_failures(
+        &self,
+        user_id: i32,
+        report_id: i32,
+        api_key: Option<String>,
+    ) -> Result<String> {
+        let Some(report) = MediaImportReport::find_by_id(report_id).one(&self.db).await? else {
+            return Err(coded_error(
+                ErrorCode::NotFound,
+                "This import report does not exist".to_owned(),
+            ));
         };
+        if report.user_id != user_id {
+            return Err(coded_error(
+                ErrorCode::Forbidden,
+                "This import report does not belong to this user".to_owned(),
+            ));
+        }
+        let (Some(details), Some(mut input)) = (report.details, report.input) else {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "Only finished imports can be retried".to_owned(),
+            ));
+        };
+        if let Some(media_tracker) = input.media_tracker.as_mut() {
+            let Some(api_key) = api_key else {
+                return Err(coded_error(
+                    ErrorCode::Validation,
+                    "The api key must be given again to retry this import".to_owned(),
+                ));
+            };
+            media_tracker.api_key = api_key;
+        }
+        let only_items = details
+            .failed_items
+            .into_iter()
+            .map(|f| f.identifier)
+            .unique()
+            .collect::<Vec<_>>();
+        if only_items.is_empty() {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "This import does not have any failed items".to_owned(),
+            ));
+        }
+        let mut storage = self.import_media.clone();
+        let job = storage
+            .push(ImportMedia {
+                user_id,
+                input,
+                only_items: Some(only_items),
+            })
+            .await?;
+        Ok(job.to_string())
+    }
+
+    pub async fn import_from_source(
+        &self,
+        user_id: i32,
+        input: DeployImportInput,
+        only_items: Option<Vec<String>>,
+    ) -> Result<()> {
+        let db_import_job = self
+            .media_service
+            .start_import_job(user_id, &input.without_secrets())
+            .await?;
+        // Anilist scores are always fetched out of a hundred
+        let source_rating_scale = input.source_rating_scale.or(match input.source {
+            MediaImportSource::Anilist => Some(RatingScale::OutOfHundred),
+            _ => None,
+        });
+        let mut import = self.fetch_import(input).await?;
+        if let Some(only_items) = only_items {
+            import.media.retain(|m| only_items.contains(&m.source_id));
+            import
+                .failed_items
+                .retain(|f| only_items.contains(&f.identifier));
+        }
+        let mut imported = 0;
         for col_details in import.collections.into_iter() {
             self.media_service
                 .create_or_update_collection(&user_id, col_details)
@@ -271,43 +527,87 @@ impl ImporterService {
                     continue;
                 }
             };
+            imported += 1;
             for seen in item.seen_history.iter() {
-                self.media_service
+                if let (Some(season), Some(episode), None | Some(100)) = (
+                    seen.show_season_number,
+                    seen.show_episode_number,
+                    seen.progress,
+                ) {
+                    // episodes seen before are skipped instead of being counted twice
+                    let seen_before =
+                        has_completed_episode(&self.db, user_id, metadata.id, season, episode)
+                            .await
+                            .unwrap_or_default();
+                    if seen_before {
+                        continue;
+                    }
+                }
+                let saved = self
+                    .media_service
                     .progress_update(
                         ProgressUpdateInput {
                             identifier: seen.id.clone(),
                             metadata_id: metadata.id,
-                            progress: Some(100),
+                            progress: Some(seen.progress.unwrap_or(100)),
                             date: seen.ended_on.map(|d| d.date_naive()),
                             show_season_number: seen.show_season_number,
                             show_episode_number: seen.show_episode_number,
                             podcast_episode_number: seen.podcast_episode_number,
+                            rewatch: false,
+                            note: None,
                         },
                         user_id,
                     )
-                    .await?;
+                    .await;
+                if let Err(e) = saved {
+                    import.failed_items.push(ImportFailedItem {
+                        lot: item.lot,
+                        step: ImportFailStep::SeenHistoryToDatabase,
+                        identifier: item.source_id.to_owned(),
+                        error: Some(e.message),
+                    });
+                    continue;
+                }
+                if seen.dropped {
+                    // it might already have been dropped by an earlier import
+                    self.media_service
+                        .progress_update(
+                            ProgressUpdateInput {
+                                identifier: None,
+                                metadata_id: metadata.id,
+                                progress: None,
+                                date: None,
+                                show_season_number: None,
+                                show_episode_number: None,
+                                podcast_episode_number: None,
+                                rewatch: false,
+                                note: None,
+                            },
+                            user_id,
+                        )
+                        .await
+                        .ok();
+                }
             }
             for review in item.reviews.iter() {
-                let text = review.review.clone().map(|r| r.text);
-                let spoiler = review.review.clone().map(|r| r.spoiler);
-                let date = review.review.clone().map(|r| r.date);
-                self.media_service
-                    .post_review(
-                        &user_id,
-                        PostReviewInput {
-                            identifier: review.id.clone(),
-                            rating: review.rating,
-                            text,
-                            spoiler,
-                            date: date.flatten(),
-                            visibility: None,
-                            metadata_id: metadata.id,
-                            review_id: None,
-                            season_number: None,
-                            episode_number: None,
-                        },
-                    )
-                    .await?;
+                let mut input = review.to_review_input(metadata.id);
+                if let Some(scale) = source_rating_scale {
+                    input.rating = input
+                        .rating
+                        .map(|r| scale.convert(r, RatingScale::OutOfFive));
+                }
+                if !input.has_content() {
+                    continue;
+                }
+                if let Err(e) = self.media_service.post_review(&user_id, input).await {
+                    import.failed_items.push(ImportFailedItem {
+                        lot: item.lot,
+                        step: ImportFailStep::ReviewToDatabase,
+                        identifier: item.source_id.to_owned(),
+                        error: Some(e.message),
+                    });
+                }
             }
             for col in item.collections.iter() {
                 self.media_service
@@ -347,10 +647,22 @@ impl ImporterService {
             total = import.media.len(),
             source = db_import_job.source
         );
+        let failed = import
+            .failed_items
+            .iter()
+            .filter(|f| {
+                matches!(
+                    f.step,
+                    ImportFailStep::ItemDetailsFromSource
+                        | ImportFailStep::MediaDetailsFromProvider
+                )
+            })
+            .count();
         let details = ImportResultResponse {
             source: db_import_job.source,
             import: ImportDetails {
-                total: import.media.len() - import.failed_items.len(),
+                total: imported,
+                failed,
             },
             failed_items: import.failed_items,
         };
@@ -360,3 +672,40 @@ impl ImporterService {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn stored_import_input_has_no_api_key() {
+        let input = DeployImportInput {
+            source: MediaImportSource::MediaTracker,
+            media_tracker: Some(DeployMediaTrackerImportInput {
+                api_url: "https://tracker.example.com".to_owned(),
+                api_key: "secret".to_owned(),
+            }),
+            goodreads: None,
+            anilist: None,
+            source_rating_scale: None,
+        };
+        let stored = input.without_secrets();
+        let media_tracker = stored.media_tracker.unwrap();
+        assert_eq!(media_tracker.api_url, "https://tracker.example.com");
+        assert!(media_tracker.api_key.is_empty());
+        assert_eq!(input.media_tracker.unwrap().api_key, "secret");
+    }
+
+    #[test]
+    fn ratings_are_converted_between_scales() {
+        let five = RatingScale::OutOfFive;
+        let ten = RatingScale::OutOfTen;
+        let hundred = RatingScale::OutOfHundred;
+        assert_eq!(five.convert(dec!(5), hundred), dec!(100));
+        assert_eq!(five.convert(dec!(3.5), ten), dec!(7));
+        assert_eq!(hundred.convert(dec!(85), five), dec!(4.25));
+        assert_eq!(ten.convert(dec!(7), ten), dec!(7));
+    }
+}
@@ -7,6 +7,51 @@ use crate::{
     utils::get_base_http_client_config,
 };
 
+pub mod audiobookshelf {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ItemProgress {
+        pub progress: f32,
+        #[serde(default)]
+        pub is_finished: bool,
+    }
+
+    impl ItemProgress {
+        /// The progress out of 100. Finished items are always complete, even if
+        /// the listening position is not at the very end.
+        pub fn percent(&self) -> i32 {
+            if self.is_finished {
+                100
+            } else {
+                (self.progress * 100_f32) as i32
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ItemMetadata {
+        pub asin: Option<String>,
+    }
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ItemMedia {
+        pub metadata: ItemMetadata,
+    }
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Item {
+        pub id: String,
+        pub library_id: String,
+        pub media: ItemMedia,
+    }
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Response {
+        pub library_items: Vec<Item>,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct YankIntegrationMedia {
     pub identifier: String,
@@ -27,62 +72,49 @@ impl IntegrationService {
         &self,
         base_url: &str,
         access_token: &str,
+        library_id: Option<&str>,
     ) -> Result<Vec<YankIntegrationMedia>> {
-        mod models {
-            use super::*;
-
-            #[derive(Debug, Serialize, Deserialize)]
-            pub struct ItemProgress {
-                pub progress: f32,
-            }
-            #[derive(Debug, Serialize, Deserialize)]
-            pub struct ItemMetadata {
-                pub asin: Option<String>,
-            }
-            #[derive(Debug, Serialize, Deserialize)]
-            pub struct ItemMedia {
-                pub metadata: ItemMetadata,
-            }
-            #[derive(Debug, Serialize, Deserialize)]
-            pub struct Item {
-                pub id: String,
-                pub media: ItemMedia,
-            }
-            #[derive(Debug, Serialize, Deserialize)]
-            #[serde(rename_all = "camelCase")]
-            pub struct Response {
-                pub library_items: Vec<Item>,
-            }
-        }
-
+        let base_url = Url::parse(&format!("{}/api/", base_url))
+            .map_err(|e| anyhow!("Invalid Audiobookshelf URL: {e}"))?;
         let client: Client = get_base_http_client_config()
             .add_header(AUTHORIZATION, format!("Bearer {access_token}"))
             .unwrap()
-            .set_base_url(Url::parse(&format!("{}/api/", base_url)).unwrap())
+            .set_base_url(base_url)
             .try_into()
             .unwrap();
-        let resp: models::Response = client
+        let mut resp = client
             .get("me/items-in-progress")
             .await
-            .map_err(|e| anyhow!(e))?
+            .map_err(|e| anyhow!("Could not connect to Audiobookshelf: {e}"))?;
+        if !resp.status().is_success() {
+            return Err(anyhow!(
+                "Audiobookshelf responded with status {}, check the token",
+                resp.status()
+            ));
+        }
+        let resp: audiobookshelf::Response = resp
             .body_json()
             .await
-            .unwrap();
+            .map_err(|e| anyhow!("Unexpected response from Audiobookshelf: {e}"))?;
         let mut media_items = vec![];
-        for item in resp.library_items.iter() {
+        for item in resp
+            .library_items
+            .iter()
+            .filter(|i| library_id.map_or(true, |l| i.library_id == l))
+        {
             if let Some(asin) = item.media.metadata.asin.clone() {
-                let resp: models::ItemProgress = client
+                let resp: audiobookshelf::ItemProgress = client
                     .get(format!("me/progress/{}", item.id))
                     .await
                     .map_err(|e| anyhow!(e))?
                     .body_json()
                     .await
-                    .unwrap();
+                    .map_err(|e| anyhow!(e))?;
                 media_items.push(YankIntegrationMedia {
                     identifier: asin,
                     lot: MetadataLot::AudioBook,
                     source: MetadataSource::Audible,
-                    progress: (resp.progress * 100_f32) as i32,
+                    progress: resp.percent(),
                 });
             }
         }
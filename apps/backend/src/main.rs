@@ -12,8 +12,8 @@ use anyhow::Result;
 use apalis::{
     cron::{CronStream, Schedule},
     layers::{
-        Extension as ApalisExtension, RateLimitLayer as ApalisRateLimitLayer,
-        TraceLayer as ApalisTraceLayer,
+        ConcurrencyLimitLayer as ApalisConcurrencyLimitLayer, Extension as ApalisExtension,
+        RateLimitLayer as ApalisRateLimitLayer, TraceLayer as ApalisTraceLayer,
     },
     prelude::{timer::TokioTimer as SleepTimer, Job as ApalisJob, *},
     sqlite::SqliteStorage,
@@ -23,7 +23,7 @@ use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
 use aws_sdk_s3::config::Region;
 use axum::{
     body::{boxed, Full},
-    extract::Multipart,
+    extract::{Multipart, Path, Query},
     headers::{authorization::Bearer, Authorization},
     http::{header, HeaderMap, Method, StatusCode, Uri},
     response::{Html, IntoResponse, Response},
@@ -38,7 +38,7 @@ use http::header::AUTHORIZATION;
 use rust_embed::RustEmbed;
 use sea_orm::{prelude::DateTimeUtc, Database, DatabaseConnection};
 use sea_orm_migration::MigratorTrait;
-use serde::{Deserialize, Serialize};
+use serde::{de::IntoDeserializer, Deserialize, Serialize};
 use serde_json::json;
 use sqlx::SqlitePool;
 use tokio::try_join;
@@ -60,7 +60,7 @@ use crate::{
     config::AppConfig,
     file_storage::FileStorageService,
     graphql::{get_schema, GraphqlSchema, PROJECT_NAME},
-    migrator::Migrator,
+    migrator::{MetadataLot, Migrator},
     miscellaneous::resolver::MiscellaneousService,
     utils::{create_app_services, user_id_from_token, COOKIE_NAME},
 };
@@ -174,6 +174,7 @@ async fn main() -> Result<()> {
         &after_media_seen_job_storage,
         &update_metadata_job_storage,
         &recalculate_user_summary_job_storage,
+        &pool,
     )
     .await;
 
@@ -220,6 +221,7 @@ async fn main() -> Result<()> {
         .route("/upload", post(upload_handler))
         .route("/graphql", get(graphql_playground).post(graphql_handler))
         .route("/export", get(export))
+        .route("/export/collection/:collection_id", get(export_collection))
         .fallback(static_handler)
         .layer(Extension(app_services.media_service.clone()))
         .layer(Extension(app_services.file_storage_service.clone()))
@@ -239,6 +241,7 @@ async fn main() -> Result<()> {
     tracing::info!("Listening on {}", addr);
 
     let rate_limit_num = config.scheduler.rate_limit_num.try_into().unwrap();
+    let metadata_update_concurrency = config.scheduler.effective_metadata_update_concurrency();
 
     let importer_service_1 = app_services.importer_service.clone();
     let importer_service_2 = app_services.importer_service.clone();
@@ -333,6 +336,9 @@ async fn main() -> Result<()> {
                         rate_limit_num,
                         Duration::new(5, 0),
                     ))
+                    .layer(ApalisConcurrencyLimitLayer::new(
+                        metadata_update_concurrency,
+                    ))
                     .layer(ApalisExtension(media_service_7.clone()))
                     .with_storage(update_metadata_job_storage.clone())
                     .build_fn(update_metadata_job)
@@ -473,15 +479,61 @@ async fn upload_handler(
     Ok(Json(json!(res)))
 }
 
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    /// Export only this page of the media, for large libraries
+    page: Option<u64>,
+    /// Comma separated lots to export, like `Book,Movie`
+    lots: Option<String>,
+}
+
+fn parse_lots(lots: &str) -> Result<Vec<MetadataLot>, serde::de::value::Error> {
+    lots.split(',')
+        .map(|l| MetadataLot::deserialize(l.trim().into_deserializer()))
+        .collect()
+}
+
 async fn export(
     Extension(media_service): Extension<Arc<MiscellaneousService>>,
     Extension(auth_db): Extension<MemoryAuthDb>,
     TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Query(params): Query<ExportParams>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = user_id_from_token(authorization.token().to_owned(), &auth_db)
+        .await
+        .map_err(|e| (StatusCode::FORBIDDEN, Json(json!({"err": e.message}))))?;
+    let lots = params
+        .lots
+        .as_deref()
+        .map(parse_lots)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"err": e.to_string()}))))?;
+    let resp = match params.page {
+        Some(page) => json!(media_service
+            .json_export_page(user_id, page, lots)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"err": e.message}))))?),
+        None => json!(media_service
+            .json_export(user_id, lots)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"err": e.message}))))?),
+    };
+    Ok(Json(resp))
+}
+
+async fn export_collection(
+    Extension(media_service): Extension<Arc<MiscellaneousService>>,
+    Extension(auth_db): Extension<MemoryAuthDb>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(collection_id): Path<i32>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = user_id_from_token(authorization.token().to_owned(), &auth_db)
         .await
         .map_err(|e| (StatusCode::FORBIDDEN, Json(json!({"err": e.message}))))?;
-    let resp = media_service.json_export(user_id).await.unwrap();
+    let resp = media_service
+        .export_collection(user_id, collection_id)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"err": e.message}))))?;
     Ok(Json(json!(resp)))
 }
 
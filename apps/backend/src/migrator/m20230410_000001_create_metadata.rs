@@ -30,9 +30,15 @@ impl MigrationName for Migration {
 pub enum MetadataImageLot {
     #[sea_orm(string_value = "B")]
     Backdrop,
+    #[sea_orm(string_value = "L")]
+    Logo,
+    // images with a role that is not known are treated as posters
     #[default]
+    #[serde(other)]
     #[sea_orm(string_value = "P")]
     Poster,
+    #[sea_orm(string_value = "S")]
+    Still,
 }
 
 // The different types of media that can be stored
@@ -86,6 +92,7 @@ pub enum MetadataImage {
     Copy,
     PartialEq,
     Eq,
+    Hash,
     EnumIter,
     DeriveActiveEnum,
     Deserialize,
@@ -140,6 +147,28 @@ pub enum Metadata {
     Source,
     // details about the media
     Specifics,
+    // the user who created this media item, only set for custom media
+    CreatedBy,
+    // a hash of the details last fetched from the provider, used to skip
+    // updates when nothing has changed
+    ContentHash,
+    // the watch providers of this media item in all regions, fetched lazily
+    WatchProviders,
+    // the aggregate rating of this media item on the provider, on the scale
+    // used by the provider
+    ProviderRating,
+    // the number of `seen` items of this media item across all users
+    Popularity,
+    // the ISO 639-1 code of the language the media was originally made in
+    OriginalLanguage,
+    // the series of media on the provider this media item is part of, like a
+    // movie franchise
+    Franchise,
+    // the slug used by the provider in the URL of this media item
+    Slug,
+    // the number of times in a row the provider returned no details for this
+    // media item when it was refreshed
+    FailedRefreshes,
 }
 
 #[async_trait::async_trait]
@@ -178,6 +207,25 @@ impl MigrationTrait for Migration {
                     .col(ColumnDef::new(Metadata::Creators).json().not_null())
                     .col(ColumnDef::new(Metadata::Source).string_len(2).not_null())
                     .col(ColumnDef::new(Metadata::Specifics).json().not_null())
+                    .col(ColumnDef::new(Metadata::CreatedBy).integer())
+                    .col(ColumnDef::new(Metadata::ContentHash).string())
+                    .col(ColumnDef::new(Metadata::WatchProviders).json())
+                    .col(ColumnDef::new(Metadata::ProviderRating).decimal())
+                    .col(
+                        ColumnDef::new(Metadata::Popularity)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(Metadata::OriginalLanguage).string())
+                    .col(ColumnDef::new(Metadata::Franchise).json())
+                    .col(ColumnDef::new(Metadata::Slug).string())
+                    .col(
+                        ColumnDef::new(Metadata::FailedRefreshes)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
                     .to_owned(),
             )
             .await?;
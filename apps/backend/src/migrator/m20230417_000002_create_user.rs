@@ -24,6 +24,9 @@ pub enum UserToMetadata {
     UserId,
     MetadataId,
     LastUpdatedOn,
+    // A poster URL or uploaded file key that should be used instead of the
+    // images provided by the source
+    ImageOverride,
 }
 
 #[derive(
@@ -48,6 +51,15 @@ pub enum User {
     Preferences,
     // This field can be `NULL` if the user has not enabled any yank integration
     YankIntegrations,
+    // The id given to the most recently created yank integration, so that ids
+    // are never reused after deletions
+    LastYankIntegrationId,
+    EmailVerified,
+    // The token that has to be used to verify the current email, if it has not
+    // been verified yet
+    EmailVerificationToken,
+    // This field can be `NULL` for users created before it was introduced
+    CreatedOn,
 }
 
 #[async_trait::async_trait]
@@ -70,6 +82,20 @@ impl MigrationTrait for Migration {
                     .col(ColumnDef::new(User::Lot).string_len(1).not_null())
                     .col(ColumnDef::new(User::Preferences).json().not_null())
                     .col(ColumnDef::new(User::YankIntegrations).json())
+                    .col(
+                        ColumnDef::new(User::LastYankIntegrationId)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(User::EmailVerified)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(ColumnDef::new(User::EmailVerificationToken).string())
+                    .col(ColumnDef::new(User::CreatedOn).timestamp_with_time_zone())
                     .to_owned(),
             )
             .await?;
@@ -104,6 +130,7 @@ impl MigrationTrait for Migration {
                             .not_null()
                             .default(Expr::current_timestamp()),
                     )
+                    .col(ColumnDef::new(UserToMetadata::ImageOverride).string())
                     .foreign_key(
                         ForeignKey::create()
                             .name("fk-user_metadata-user_id")
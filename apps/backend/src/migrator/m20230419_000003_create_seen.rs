@@ -26,6 +26,10 @@ pub enum Seen {
     ExtraInformation,
     // This will store the ID in case this review was imported
     Identifier,
+    // The number of the viewing when the user consumes a media item again
+    Playthrough,
+    // A free-text note about this particular viewing
+    Note,
 }
 
 #[async_trait::async_trait]
@@ -60,6 +64,8 @@ impl MigrationTrait for Migration {
                     )
                     .col(ColumnDef::new(Seen::Identifier).string())
                     .col(ColumnDef::new(Seen::ExtraInformation).json())
+                    .col(ColumnDef::new(Seen::Playthrough).integer())
+                    .col(ColumnDef::new(Seen::Note).text())
                     .foreign_key(
                         ForeignKey::create()
                             .name("user_to_seen_foreign_key")
@@ -28,6 +28,10 @@ pub enum Review {
     Spoiler,
     // This will store the ID in case this review was imported
     Identifier,
+    // A user can pin at most one of their reviews per media
+    Pinned,
+    // The season and episode of a show up to which this review has spoilers
+    SpoilsUpTo,
 }
 
 #[async_trait::async_trait]
@@ -59,7 +63,14 @@ impl MigrationTrait for Migration {
                             .default(false),
                     )
                     .col(ColumnDef::new(Review::Identifier).string())
+                    .col(
+                        ColumnDef::new(Review::Pinned)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
                     .col(ColumnDef::new(Review::ExtraInformation).json())
+                    .col(ColumnDef::new(Review::SpoilsUpTo).json())
                     .col(
                         ColumnDef::new(Review::Visibility)
                             .string_len(2)
@@ -22,6 +22,8 @@ pub enum MediaImportSource {
     MediaTracker,
     #[sea_orm(string_value = "GO")]
     Goodreads,
+    #[sea_orm(string_value = "AN")]
+    Anilist,
 }
 
 #[derive(Iden)]
@@ -34,6 +36,8 @@ pub enum MediaImportReport {
     Source,
     Details,
     Success,
+    // the input the import was deployed with, used to retry its failed items
+    Input,
 }
 
 #[async_trait::async_trait]
@@ -69,6 +73,7 @@ impl MigrationTrait for Migration {
                     .col(ColumnDef::new(MediaImportReport::FinishedOn).timestamp_with_time_zone())
                     .col(ColumnDef::new(MediaImportReport::Details).json())
                     .col(ColumnDef::new(MediaImportReport::Success).boolean())
+                    .col(ColumnDef::new(MediaImportReport::Input).json())
                     .foreign_key(
                         ForeignKey::create()
                             .name("media_import_report_to_user_foreign_key")
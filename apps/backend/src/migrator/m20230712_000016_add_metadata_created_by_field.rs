@@ -0,0 +1,31 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrator::Metadata;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230712_000016_add_metadata_created_by_field"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Metadata::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Metadata::CreatedBy).integer())
+                    .to_owned(),
+            )
+            .await
+            .ok();
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
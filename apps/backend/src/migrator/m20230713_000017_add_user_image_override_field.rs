@@ -0,0 +1,33 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrator::UserToMetadata;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230713_000017_add_user_image_override_field"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserToMetadata::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(UserToMetadata::ImageOverride).string(),
+                    )
+                    .to_owned(),
+            )
+            .await
+            .ok();
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
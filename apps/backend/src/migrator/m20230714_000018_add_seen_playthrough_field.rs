@@ -0,0 +1,31 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrator::Seen;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230714_000018_add_seen_playthrough_field"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Seen::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Seen::Playthrough).integer())
+                    .to_owned(),
+            )
+            .await
+            .ok();
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
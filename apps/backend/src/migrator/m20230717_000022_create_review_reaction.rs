@@ -0,0 +1,107 @@
+use async_graphql::Enum;
+use sea_orm::{DeriveActiveEnum, EnumIter};
+use sea_orm_migration::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::migrator::{m20230417_000002_create_user::User, Review};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230717_000022_create_review_reaction"
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumIter,
+    DeriveActiveEnum,
+    Deserialize,
+    Serialize,
+    Enum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum ReviewReactionKind {
+    #[sea_orm(string_value = "LI")]
+    Like,
+    #[sea_orm(string_value = "LO")]
+    Love,
+    #[sea_orm(string_value = "LA")]
+    Laugh,
+    #[sea_orm(string_value = "DI")]
+    Dislike,
+}
+
+/// A reaction of a user to a review. A user can have at most one reaction on
+/// a review.
+#[derive(Iden)]
+pub enum ReviewReaction {
+    Table,
+    UserId,
+    ReviewId,
+    Kind,
+    CreatedOn,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReviewReaction::Table)
+                    .col(ColumnDef::new(ReviewReaction::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(ReviewReaction::ReviewId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .name("pk-review_reaction")
+                            .col(ReviewReaction::UserId)
+                            .col(ReviewReaction::ReviewId),
+                    )
+                    .col(
+                        ColumnDef::new(ReviewReaction::Kind)
+                            .string_len(2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReviewReaction::CreatedOn)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-review_reaction-user_id")
+                            .from(ReviewReaction::Table, ReviewReaction::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-review_reaction-review_id")
+                            .from(ReviewReaction::Table, ReviewReaction::ReviewId)
+                            .to(Review::Table, Review::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
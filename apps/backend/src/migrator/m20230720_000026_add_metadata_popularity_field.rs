@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrator::Metadata;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230720_000026_add_metadata_popularity_field"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Metadata::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Metadata::Popularity)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+            .ok();
+        // the same count that is kept up to date when media are seen
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE metadata SET popularity = \
+                 (SELECT COUNT(*) FROM seen WHERE seen.metadata_id = metadata.id)",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
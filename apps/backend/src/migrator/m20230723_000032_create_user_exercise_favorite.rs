@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrator::{
+    m20230417_000002_create_user::User, m20230622_000013_create_exercise::Exercise,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230723_000032_create_user_exercise_favorite"
+    }
+}
+
+/// An exercise that a user has marked as a favorite. A user can favorite an
+/// exercise at most once.
+#[derive(Iden)]
+pub enum UserExerciseFavorite {
+    Table,
+    UserId,
+    ExerciseId,
+    CreatedOn,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserExerciseFavorite::Table)
+                    .col(
+                        ColumnDef::new(UserExerciseFavorite::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserExerciseFavorite::ExerciseId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .name("pk-user_exercise_favorite")
+                            .col(UserExerciseFavorite::UserId)
+                            .col(UserExerciseFavorite::ExerciseId),
+                    )
+                    .col(
+                        ColumnDef::new(UserExerciseFavorite::CreatedOn)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_exercise_favorite-user_id")
+                            .from(UserExerciseFavorite::Table, UserExerciseFavorite::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_exercise_favorite-exercise_id")
+                            .from(
+                                UserExerciseFavorite::Table,
+                                UserExerciseFavorite::ExerciseId,
+                            )
+                            .to(Exercise::Table, Exercise::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
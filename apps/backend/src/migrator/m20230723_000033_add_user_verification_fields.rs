@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrator::m20230417_000002_create_user::User;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230723_000033_add_user_verification_fields"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can only add one column per statement
+        let columns = [
+            ColumnDef::new(User::EmailVerified)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+            ColumnDef::new(User::EmailVerificationToken)
+                .string()
+                .to_owned(),
+            ColumnDef::new(User::CreatedOn)
+                .timestamp_with_time_zone()
+                .to_owned(),
+        ];
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(User::Table)
+                        .add_column_if_not_exists(column)
+                        .to_owned(),
+                )
+                .await
+                .ok();
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrator::{m20230417_000002_create_user::User, Metadata, UserToMetadata};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230725_000035_fix_user_to_metadata_foreign_key"
+    }
+}
+
+#[derive(Iden)]
+enum UserToMetadataBackup {
+    Table,
+}
+
+const COLUMNS: [UserToMetadata; 4] = [
+    UserToMetadata::UserId,
+    UserToMetadata::MetadataId,
+    UserToMetadata::LastUpdatedOn,
+    UserToMetadata::ImageOverride,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The foreign key to `metadata` used to be created from `user_id`, which
+        // deleted the library of user N whenever metadata N was deleted. SQLite
+        // can not alter foreign keys, so the table is rebuilt with the correct
+        // one. Rows pointing to metadata that no longer exists are dropped.
+        let db = manager.get_connection();
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserToMetadataBackup::Table)
+                    .col(ColumnDef::new(UserToMetadata::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(UserToMetadata::MetadataId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserToMetadata::LastUpdatedOn)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UserToMetadata::ImageOverride).string())
+                    .to_owned(),
+            )
+            .await?;
+        let backup = Query::insert()
+            .into_table(UserToMetadataBackup::Table)
+            .columns(COLUMNS)
+            .select_from(
+                Query::select()
+                    .columns(COLUMNS)
+                    .from(UserToMetadata::Table)
+                    .to_owned(),
+            )
+            .map_err(|e| DbErr::Custom(e.to_string()))?
+            .to_owned();
+        db.execute(db.get_database_backend().build(&backup)).await?;
+        manager
+            .drop_table(Table::drop().table(UserToMetadata::Table).to_owned())
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserToMetadata::Table)
+                    .col(ColumnDef::new(UserToMetadata::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(UserToMetadata::MetadataId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .name("pk-user_metadata")
+                            .col(UserToMetadata::UserId)
+                            .col(UserToMetadata::MetadataId),
+                    )
+                    .col(
+                        ColumnDef::new(UserToMetadata::LastUpdatedOn)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(UserToMetadata::ImageOverride).string())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_metadata-user_id")
+                            .from(UserToMetadata::Table, UserToMetadata::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_metadata-metadata_id")
+                            .from(UserToMetadata::Table, UserToMetadata::MetadataId)
+                            .to(Metadata::Table, Metadata::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        let restore = Query::insert()
+            .into_table(UserToMetadata::Table)
+            .columns(COLUMNS)
+            .select_from(
+                Query::select()
+                    .columns(COLUMNS)
+                    .from(UserToMetadataBackup::Table)
+                    .and_where(
+                        Expr::col(UserToMetadata::MetadataId).in_subquery(
+                            Query::select()
+                                .column(Metadata::Id)
+                                .from(Metadata::Table)
+                                .to_owned(),
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .map_err(|e| DbErr::Custom(e.to_string()))?
+            .to_owned();
+        db.execute(db.get_database_backend().build(&restore))
+            .await?;
+        manager
+            .drop_table(Table::drop().table(UserToMetadataBackup::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
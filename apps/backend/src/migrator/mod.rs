@@ -16,6 +16,26 @@ mod m20230621_000012_add_metadata_unique_index;
 mod m20230622_000013_create_exercise;
 mod m20230702_000014_add_user_integrations_field;
 mod m20230707_000015_add_description_and_visibility_fields;
+mod m20230712_000016_add_metadata_created_by_field;
+mod m20230713_000017_add_user_image_override_field;
+mod m20230714_000018_add_seen_playthrough_field;
+mod m20230715_000019_add_metadata_content_hash_field;
+mod m20230716_000020_add_metadata_watch_providers_field;
+mod m20230716_000021_add_metadata_provider_rating_field;
+mod m20230717_000022_create_review_reaction;
+mod m20230718_000023_add_user_last_yank_integration_id_field;
+mod m20230719_000024_add_review_pinned_field;
+mod m20230719_000025_add_review_spoils_up_to_field;
+mod m20230720_000026_add_metadata_popularity_field;
+mod m20230720_000027_add_metadata_original_language_field;
+mod m20230721_000028_add_seen_note_field;
+mod m20230721_000029_add_media_import_report_input_field;
+mod m20230722_000030_add_metadata_franchise_field;
+mod m20230722_000031_add_metadata_slug_field;
+mod m20230723_000032_create_user_exercise_favorite;
+mod m20230723_000033_add_user_verification_fields;
+mod m20230724_000034_add_metadata_failed_refreshes_field;
+mod m20230725_000035_fix_user_to_metadata_foreign_key;
 
 pub use m20230410_000001_create_metadata::{
     Metadata, MetadataImageLot, MetadataLot, MetadataSource,
@@ -23,7 +43,8 @@ pub use m20230410_000001_create_metadata::{
 pub use m20230417_000002_create_user::{UserLot, UserToMetadata};
 pub use m20230419_000003_create_seen::Seen;
 pub use m20230505_000006_create_review::Review;
-pub use m20230509_000008_create_media_import_report::MediaImportSource;
+pub use m20230509_000008_create_media_import_report::{MediaImportReport, MediaImportSource};
+pub use m20230717_000022_create_review_reaction::ReviewReactionKind;
 
 pub struct Migrator;
 
@@ -46,6 +67,26 @@ impl MigratorTrait for Migrator {
             Box::new(m20230622_000013_create_exercise::Migration),
             Box::new(m20230702_000014_add_user_integrations_field::Migration),
             Box::new(m20230707_000015_add_description_and_visibility_fields::Migration),
+            Box::new(m20230712_000016_add_metadata_created_by_field::Migration),
+            Box::new(m20230713_000017_add_user_image_override_field::Migration),
+            Box::new(m20230714_000018_add_seen_playthrough_field::Migration),
+            Box::new(m20230715_000019_add_metadata_content_hash_field::Migration),
+            Box::new(m20230716_000020_add_metadata_watch_providers_field::Migration),
+            Box::new(m20230716_000021_add_metadata_provider_rating_field::Migration),
+            Box::new(m20230717_000022_create_review_reaction::Migration),
+            Box::new(m20230718_000023_add_user_last_yank_integration_id_field::Migration),
+            Box::new(m20230719_000024_add_review_pinned_field::Migration),
+            Box::new(m20230719_000025_add_review_spoils_up_to_field::Migration),
+            Box::new(m20230720_000026_add_metadata_popularity_field::Migration),
+            Box::new(m20230720_000027_add_metadata_original_language_field::Migration),
+            Box::new(m20230721_000028_add_seen_note_field::Migration),
+            Box::new(m20230721_000029_add_media_import_report_input_field::Migration),
+            Box::new(m20230722_000030_add_metadata_franchise_field::Migration),
+            Box::new(m20230722_000031_add_metadata_slug_field::Migration),
+            Box::new(m20230723_000032_create_user_exercise_favorite::Migration),
+            Box::new(m20230723_000033_add_user_verification_fields::Migration),
+            Box::new(m20230724_000034_add_metadata_failed_refreshes_field::Migration),
+            Box::new(m20230725_000035_fix_user_to_metadata_foreign_key::Migration),
         ]
     }
 }
@@ -1,4 +1,4 @@
-use async_graphql::SimpleObject;
+use async_graphql::{Enum, SimpleObject};
 use enum_meta::{meta, Meta};
 use sea_orm::FromJsonQueryResult;
 use serde::{Deserialize, Serialize};
@@ -15,6 +15,27 @@ use crate::{
 
 pub mod resolver;
 
+#[derive(Debug, Serialize, Deserialize, Enum, Clone, PartialEq, Eq, Copy, Default)]
+pub enum MediaSortOrder {
+    Desc,
+    #[default]
+    Asc,
+}
+
+#[derive(Debug, Serialize, Deserialize, Enum, Clone, PartialEq, Eq, Copy, Default)]
+pub enum MediaSortBy {
+    Title,
+    #[default]
+    ReleaseDate,
+    LastSeen,
+    LastUpdated,
+    Rating,
+    /// The number of times the user has completed the media.
+    TimesSeen,
+    /// The runtime of movies and audiobooks. Media without one are sorted last.
+    Runtime,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, FromJsonQueryResult, Eq, PartialEq, Default)]
 #[serde(tag = "t", content = "d")]
 pub enum MediaSpecifics {
@@ -76,6 +97,29 @@ pub struct MetadataCreator {
 )]
 pub struct MetadataCreators(pub Vec<MetadataCreator>);
 
+/// A service on which a media item can be streamed, rented or bought.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, SimpleObject, Hash)]
+pub struct WatchProvider {
+    pub name: String,
+    pub logo: Option<String>,
+    /// The ISO 3166-1 code of the country in which this provider is available
+    pub region: String,
+}
+
+#[derive(Clone, Debug, PartialEq, FromJsonQueryResult, Eq, Serialize, Deserialize, Default)]
+pub struct MetadataWatchProviders(pub Vec<WatchProvider>);
+
+/// A series of media items on the provider that this item is part of, like a
+/// movie franchise. This is unrelated to the collections created by users.
+#[derive(
+    Clone, Debug, PartialEq, FromJsonQueryResult, Eq, Serialize, Deserialize, SimpleObject, Hash,
+)]
+pub struct MetadataFranchise {
+    /// The identifier of the franchise on the provider
+    pub identifier: String,
+    pub name: String,
+}
+
 #[derive(Display, EnumIter)]
 pub enum DefaultCollection {
     Custom,
@@ -115,6 +159,16 @@ pub struct SeenPodcastExtraInformation {
     pub episode: i32,
 }
 
+/// The point in a show up to which a review contains spoilers. When `episode`
+/// is not set, the whole season is spoiled.
+#[derive(
+    Debug, PartialEq, Eq, Serialize, Deserialize, Clone, FromJsonQueryResult, SimpleObject,
+)]
+pub struct ReviewSpoilerScope {
+    pub season: i32,
+    pub episode: Option<i32>,
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, FromJsonQueryResult)]
 pub enum SeenExtraInformation {
     Show(SeenShowExtraInformation),
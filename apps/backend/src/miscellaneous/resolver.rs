@@ -1,12 +1,22 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::Duration as StdDuration,
+};
 
 use apalis::{prelude::Storage as ApalisStorage, sqlite::SqliteStorage};
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
-use async_graphql::{Context, Enum, Error, InputObject, Object, Result, SimpleObject, Union};
-use chrono::{NaiveDate, Utc};
+use async_graphql::{
+    Context, Enum, Error, InputObject, MaybeUndefined, Object, Result, SimpleObject, Union,
+};
+use chrono::{Duration, FixedOffset, Months, NaiveDate, TimeZone, Utc};
+use convert_case::{Case, Casing};
 use cookie::{time::OffsetDateTime, Cookie};
-use enum_meta::Meta;
-use futures::TryStreamExt;
+use futures::{
+    future::join_all,
+    stream::{self, StreamExt},
+    TryStreamExt,
+};
 use http::header::SET_COOKIE;
 use itertools::Itertools;
 use markdown::{
@@ -17,14 +27,16 @@ use rust_decimal::Decimal;
 use sea_orm::{
     prelude::DateTimeUtc, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
     DatabaseBackend, DatabaseConnection, EntityTrait, FromQueryResult, Iden, JoinType, ModelTrait,
-    Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Statement,
+    Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Statement, TransactionTrait,
 };
-use sea_orm::{Iterable, QueryTrait};
+use sea_orm::{ActiveEnum, Iterable, QueryTrait};
 use sea_query::{
-    Alias, Cond, Expr, Func, Keyword, MySqlQueryBuilder, NullOrdering, OrderedStatement,
-    PostgresQueryBuilder, Query, SelectStatement, SqliteQueryBuilder, UnionType, Values,
+    Alias, Cond, Expr, Func, Keyword, MySqlQueryBuilder, NullOrdering, OnConflict,
+    OrderedStatement, PostgresQueryBuilder, Query, SelectStatement, SqliteQueryBuilder, UnionType,
+    Value, Values,
 };
 use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
 use strum::IntoEnumIterator;
 use uuid::Uuid;
 
@@ -35,30 +47,34 @@ use crate::{
         collection, genre, media_import_report, metadata, metadata_to_collection,
         metadata_to_genre,
         prelude::{
-            Collection, Genre, MediaImportReport, Metadata, MetadataToCollection, Review, Seen,
-            Summary, User, UserToMetadata,
+            Collection, Exercise, Genre, MediaImportReport, Metadata, MetadataToCollection,
+            MetadataToGenre, Review, ReviewReaction, Seen, Summary, User, UserExerciseFavorite,
+            UserToMetadata,
         },
-        review, seen, summary, user, user_to_metadata,
+        review, review_reaction, seen, summary, user, user_exercise_favorite, user_to_metadata,
     },
     file_storage::FileStorageService,
     graphql::IdObject,
-    importer::ImportResultResponse,
+    importer::{DeployImportInput, ImportResultResponse},
     integrations::IntegrationService,
     migrator::{
-        MediaImportSource, Metadata as TempMetadata, MetadataImageLot, MetadataLot, MetadataSource,
-        Review as TempReview, Seen as TempSeen, UserLot, UserToMetadata as TempUserToMetadata,
+        Metadata as TempMetadata, MetadataImageLot, MetadataLot, MetadataSource,
+        Review as TempReview, ReviewReactionKind, Seen as TempSeen, UserLot,
+        UserToMetadata as TempUserToMetadata,
     },
     miscellaneous::{
-        CustomService, DefaultCollection, MediaSpecifics, MetadataCreator, MetadataCreators,
-        MetadataImage, MetadataImageUrl, MetadataImages, SeenExtraInformation,
-        SeenPodcastExtraInformation, SeenShowExtraInformation,
+        CustomService, DefaultCollection, MediaSortBy, MediaSortOrder, MediaSpecifics,
+        MetadataCreator, MetadataCreators, MetadataFranchise, MetadataImage, MetadataImageUrl,
+        MetadataImages, MetadataWatchProviders, ReviewSpoilerScope, SeenExtraInformation,
+        SeenPodcastExtraInformation, SeenShowExtraInformation, WatchProvider,
     },
     models::{
         media::{
             AddMediaToCollection, AnimeSpecifics, AudioBookSpecifics, BookSpecifics,
-            CreateOrUpdateCollectionInput, ExportMedia, MangaSpecifics, MediaDetails,
-            MediaListItem, MediaSearchItem, MovieSpecifics, PodcastSpecifics, PostReviewInput,
-            ProgressUpdateInput, ShowSpecifics, UserSummary, VideoGameSpecifics, Visibility,
+            CreateOrUpdateCollectionInput, ExportMedia, ExportMediaPage, MangaSpecifics,
+            MediaDetails, MediaListItem, MediaSearchItem, MovieSpecifics, PodcastSpecifics,
+            PostReviewInput, ProgressUpdateInput, RecentlyFinishedItem, ShowSpecifics,
+            UserMediaSummary, UserRuntimeSummary, UserSummary, VideoGameSpecifics, Visibility,
         },
         SearchResults,
     },
@@ -74,17 +90,45 @@ use crate::{
     },
     traits::{IsFeatureEnabled, MediaProvider, MediaProviderLanguages},
     users::{
-        UserPreferences, UserYankIntegration, UserYankIntegrationSetting, UserYankIntegrations,
+        default_yank_max_progress, default_yank_min_progress, parse_timezone,
+        UserMediaListSortPreferences, UserPreferences, UserYankIntegration,
+        UserYankIntegrationSetting, UserYankIntegrations,
     },
     utils::{
-        get_case_insensitive_like_query, user_auth_token_from_ctx, user_id_from_ctx,
-        user_id_from_token, MemoryAuthDb, SearchInput, COOKIE_NAME, PAGE_LIMIT,
+        associate_user_with_metadata, coded_error, get_case_insensitive_like_query,
+        has_completed_episode, has_error_code, user_auth_token_from_ctx, user_id_from_ctx,
+        user_id_from_token, ErrorCode, MediaNotFoundError, MemoryAuthDb, MemoryCache, SearchInput,
+        COOKIE_NAME, PAGE_LIMIT,
     },
     MemoryAuthData,
 };
 
 type Provider = Box<(dyn MediaProvider + Send + Sync)>;
 
+static RECENTLY_FINISHED_DEFAULT_LIMIT: u64 = 5;
+static RECENTLY_FINISHED_MAX_LIMIT: u64 = 20;
+static MEDIA_REFRESH_COOLDOWN_MINUTES: i64 = 5;
+static RESUME_MEDIA_DEFAULT_LIMIT: usize = 10;
+static RESUME_MEDIA_MAX_LIMIT: usize = 50;
+static SIMILAR_USERS_DEFAULT_LIMIT: usize = 5;
+static SIMILAR_USERS_MAX_LIMIT: usize = 20;
+static TRENDING_MEDIA_DEFAULT_WINDOW_DAYS: i64 = 7;
+static TRENDING_MEDIA_MAX_WINDOW_DAYS: i64 = 365;
+static TRENDING_MEDIA_DEFAULT_LIMIT: u64 = 10;
+static TRENDING_MEDIA_MAX_LIMIT: u64 = 50;
+static MEDIA_RECOMMENDATIONS_LIMIT: usize = 10;
+static MEDIA_SEARCH_COMMIT_LIMIT: usize = 20;
+static MEDIA_DETAILS_BATCH_LIMIT: usize = 50;
+static WATCH_PROVIDERS_CONCURRENCY: usize = 5;
+static WATCH_PROVIDERS_MISS_TTL_SECONDS: u64 = 10 * 60;
+static WATCH_PROVIDERS_MISS_CACHE_SIZE: usize = 1000;
+static EXPORT_PAGE_SIZE: u64 = 100;
+static SEARCH_MATCH_FIELDS: [(metadata::Column, &str); 3] = [
+    (metadata::Column::Title, "title"),
+    (metadata::Column::Description, "description"),
+    (metadata::Column::Creators, "creators"),
+];
+
 #[derive(Debug, Serialize, Deserialize, InputObject, Clone)]
 struct CreateCustomMediaInput {
     title: String,
@@ -115,6 +159,9 @@ struct GraphqlUserYankIntegration {
     lot: UserYankIntegrationLot,
     description: String,
     timestamp: DateTimeUtc,
+    min_progress: i32,
+    max_progress: i32,
+    library_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, InputObject, Clone)]
@@ -123,6 +170,18 @@ struct CreateUserYankIntegrationInput {
     base_url: String,
     #[graphql(secret)]
     token: String,
+    /// Progress below this value will be ignored. Defaults to 1.
+    min_progress: Option<i32>,
+    /// Progress above this value will mark the media as finished. Defaults to 95.
+    max_progress: Option<i32>,
+    /// Only sync items from this library. Defaults to all libraries.
+    library_id: Option<String>,
+}
+
+#[derive(Debug, SimpleObject)]
+struct YankIntegrationTestResult {
+    /// The number of items with progress that were found on the server
+    progress_items: usize,
 }
 
 #[derive(Enum, Clone, Debug, Copy, PartialEq, Eq)]
@@ -175,6 +234,8 @@ struct UserInput {
 enum RegisterErrorVariant {
     UsernameAlreadyExists,
     Disabled,
+    InvalidUsername,
+    WeakPassword,
 }
 
 #[derive(Debug, SimpleObject)]
@@ -225,6 +286,35 @@ struct UpdateUserFeaturePreferenceInput {
     value: bool,
 }
 
+#[derive(Debug, InputObject)]
+struct UpdateSeenItemInput {
+    seen_id: i32,
+    /// Pass `null` to remove the start date
+    started_on: MaybeUndefined<NaiveDate>,
+    /// Pass `null` to remove the finish date. When left out, it is set to today
+    /// if the progress changes to 100 and removed if the progress changes from it
+    finished_on: MaybeUndefined<NaiveDate>,
+    progress: Option<i32>,
+    /// Replace the note of the seen item, an empty note removes it
+    note: Option<String>,
+}
+
+#[derive(Debug, InputObject)]
+struct PopulateCollectionFromExternalInput {
+    collection_name: String,
+    lot: MetadataLot,
+    source: MetadataSource,
+    identifiers: Vec<String>,
+}
+
+#[derive(Debug, SimpleObject)]
+struct PopulateCollectionItemResult {
+    identifier: String,
+    /// The ID of the media item, set when it was added to the collection
+    metadata_id: Option<i32>,
+    error: Option<String>,
+}
+
 #[derive(Debug, InputObject)]
 struct CollectionContentsInput {
     collection_id: i32,
@@ -244,8 +334,23 @@ struct ReviewPostedBy {
     name: String,
 }
 
+#[derive(Debug, InputObject, Default)]
+struct ReviewFilterInput {
+    /// Only return reviews with a rating of at least this value
+    min_rating: Option<Decimal>,
+    /// Exclude reviews that have been marked as spoilers
+    #[graphql(default)]
+    hide_spoilers: bool,
+}
+
+#[derive(Debug, SimpleObject)]
+struct ReviewReactionCount {
+    kind: ReviewReactionKind,
+    count: usize,
+}
+
 #[derive(Debug, SimpleObject)]
-struct ReviewItem {
+pub struct ReviewItem {
     id: i32,
     posted_on: DateTimeUtc,
     rating: Option<Decimal>,
@@ -256,6 +361,15 @@ struct ReviewItem {
     episode_number: Option<i32>,
     posted_by: ReviewPostedBy,
     podcast_episode_id: Option<i32>,
+    reaction_counts: Vec<ReviewReactionCount>,
+    pinned: bool,
+    spoils_up_to: Option<ReviewSpoilerScope>,
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct UserReviewItem {
+    review: ReviewItem,
+    media: MediaSearchItem,
 }
 
 #[derive(Debug, SimpleObject)]
@@ -273,6 +387,55 @@ struct GeneralFeatures {
     signup_allowed: bool,
 }
 
+#[derive(Debug, SimpleObject)]
+struct ServerHealth {
+    /// Whether the database responded to a trivial query
+    database: bool,
+    /// How long the database took to respond, in milliseconds
+    database_latency: Option<i64>,
+    /// Whether file storage has been configured
+    file_storage_enabled: bool,
+    /// Whether the configured bucket could be reached
+    file_storage_reachable: bool,
+    /// Whether everything that has been configured is working
+    healthy: bool,
+}
+
+#[derive(Debug, Enum, Clone, Copy, PartialEq, Eq)]
+enum ImageReferenceLot {
+    Metadata,
+    Exercise,
+    /// An image set by a user to be used instead of the poster of a media item
+    UserImageOverride,
+}
+
+#[derive(Debug, SimpleObject)]
+struct BrokenImageReference {
+    key: String,
+    lot: ImageReferenceLot,
+    /// The id of the metadata or exercise that references the image
+    entity_id: i32,
+}
+
+#[derive(Debug, SimpleObject)]
+struct ImageAudit {
+    /// The number of stored images that were checked
+    checked: usize,
+    broken: Vec<BrokenImageReference>,
+    /// The references that could not be checked, for example because file
+    /// storage could not be reached
+    errored: Vec<BrokenImageReference>,
+}
+
+/// The urls of the images of a media item, grouped by their role.
+#[derive(Debug, Default)]
+struct MetadataImageUrls {
+    posters: Vec<String>,
+    /// Stills are shown along with the backdrops
+    backdrops: Vec<String>,
+    logos: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct MediaBaseData {
     model: metadata::Model,
@@ -295,6 +458,162 @@ struct DetailedMediaSearchResults {
     next_page: Option<i32>,
 }
 
+#[derive(Debug, SimpleObject, Clone)]
+struct CommittedMediaSearchItem {
+    identifier: String,
+    database_id: Option<i32>,
+    /// Why the item could not be committed.
+    error: Option<String>,
+}
+
+#[derive(Debug, SimpleObject, Clone)]
+struct SourcedMediaSearchItem {
+    source: MetadataSource,
+    item: MediaSearchItem,
+    database_id: Option<i32>,
+}
+
+#[derive(Debug, SimpleObject)]
+struct AllSourcesMediaSearchResults {
+    items: Vec<SourcedMediaSearchItem>,
+    /// The sources that could not be searched
+    failed_sources: Vec<MetadataSource>,
+    /// Set if any of the sources has more results
+    next_page: Option<i32>,
+}
+
+#[derive(Debug, SimpleObject, PartialEq, Eq, Default)]
+struct UserStreaks {
+    /// The number of consecutive days, ending today or yesterday, on which the
+    /// user finished at least one media item
+    current: i32,
+    /// The longest such run of days ever
+    longest: i32,
+}
+
+#[derive(Debug, SimpleObject)]
+struct UserDiaryEntry {
+    seen_id: i32,
+    metadata_id: i32,
+    lot: MetadataLot,
+    title: String,
+    image: Option<String>,
+    note: Option<String>,
+}
+
+#[derive(Debug, SimpleObject)]
+struct UserDiaryDay {
+    date: NaiveDate,
+    entries: Vec<UserDiaryEntry>,
+}
+
+#[derive(Debug, SimpleObject, PartialEq, Eq)]
+struct DeleteReviewsResult {
+    deleted: u64,
+    /// The reviews that were not deleted because they belong to other users
+    rejected_ids: Vec<i32>,
+}
+
+/// The number of rows removed by a cleanup.
+#[derive(Debug, SimpleObject)]
+struct CleanupResult {
+    user_to_metadata_removed: usize,
+    metadata_removed: usize,
+}
+
+#[derive(Debug, SimpleObject)]
+struct DuplicateMetadataItem {
+    id: i32,
+    source: MetadataSource,
+    identifier: String,
+}
+
+/// Media items that share the same type, publish year and title (ignoring case
+/// and punctuation), and are probably the same media from different sources.
+#[derive(Debug, SimpleObject)]
+struct DuplicateMetadataGroup {
+    title: String,
+    lot: MetadataLot,
+    publish_year: Option<i32>,
+    /// Ordered by id, so the first item is the oldest one
+    items: Vec<DuplicateMetadataItem>,
+}
+
+/// A background job that has been queued but not picked up by a worker yet.
+#[derive(Debug, SimpleObject)]
+struct PendingJob {
+    id: String,
+    /// The type of the job, for eg: `apalis::UpdateMetadataJob`
+    name: String,
+    /// When the job is scheduled to run
+    run_at: Option<DateTimeUtc>,
+}
+
+/// Where to continue consuming a media item from. The fields that do not apply
+/// to the type of the media are empty.
+#[derive(Debug, SimpleObject, Default, PartialEq, Eq)]
+struct ResumePosition {
+    /// Approximate position in seconds, for media with a known runtime
+    seconds: Option<i32>,
+    /// Approximate page, for books with a known number of pages
+    page: Option<i32>,
+    /// Approximate chapter, for manga with a known number of chapters
+    chapter: Option<i32>,
+    season: Option<i32>,
+    episode: Option<i32>,
+}
+
+#[derive(Debug, SimpleObject)]
+struct ResumeMediaItem {
+    media: MediaSearchItem,
+    progress: i32,
+    last_updated_on: DateTimeUtc,
+    position: ResumePosition,
+}
+
+#[derive(Debug, SimpleObject)]
+struct LibraryLotCount {
+    lot: MetadataLot,
+    count: i64,
+}
+
+#[derive(Debug, SimpleObject)]
+struct SimilarUser {
+    id: i32,
+    name: String,
+    /// The Jaccard index of the completed media of both users, between 0 and 1
+    similarity: f64,
+    /// The number of media items completed by both users
+    shared_media: usize,
+    public_collections: Vec<collection::Model>,
+}
+
+#[derive(Debug, SimpleObject)]
+struct EnumVariantReference {
+    /// The value to use in GraphQL requests
+    value: String,
+    /// A human readable name for the variant
+    display: String,
+}
+
+#[derive(Debug, SimpleObject)]
+struct EnumReference {
+    metadata_lots: Vec<EnumVariantReference>,
+    metadata_sources: Vec<EnumVariantReference>,
+    visibilities: Vec<EnumVariantReference>,
+}
+
+/// A season of a show without its episodes.
+#[derive(Debug, Serialize, Deserialize, SimpleObject, Clone)]
+struct GraphqlShowSeasonSummary {
+    season_number: i32,
+    name: String,
+    overview: Option<String>,
+    poster: Option<String>,
+    publish_date: Option<NaiveDate>,
+    episodes_count: usize,
+}
+
 #[derive(Debug, Serialize, Deserialize, SimpleObject, Clone)]
 struct GraphqlMediaDetails {
     id: i32,
@@ -307,6 +626,7 @@ struct GraphqlMediaDetails {
     genres: Vec<String>,
     poster_images: Vec<String>,
     backdrop_images: Vec<String>,
+    logo_images: Vec<String>,
     publish_year: Option<i32>,
     publish_date: Option<NaiveDate>,
     book_specifics: Option<BookSpecifics>,
@@ -320,13 +640,26 @@ struct GraphqlMediaDetails {
     source_url: Option<String>,
     /// The number of users who have seen this media
     seen_by: i32,
-}
-
-#[derive(Debug, Serialize, Deserialize, Enum, Clone, PartialEq, Eq, Copy, Default)]
-enum MediaSortOrder {
-    Desc,
-    #[default]
-    Asc,
+    /// The number of collections of the current user that contain this media
+    collection_count: i32,
+    /// Whether the current user created this custom media or is an admin
+    is_editable_by_me: bool,
+    /// The seasons of a show, for rendering a season picker
+    seasons: Vec<GraphqlShowSeasonSummary>,
+    /// Where this media can be watched in the user's region, only available for
+    /// TMDb movies and shows
+    watch_providers: Option<Vec<WatchProvider>>,
+    /// The aggregate rating on the provider, on the scale used by it. This is
+    /// separate from the ratings given by users of this service.
+    provider_rating: Option<Decimal>,
+    /// The ISO 639-1 code of the language the media was originally made in
+    original_language: Option<String>,
+    /// The franchise on the provider this media is part of, only available for
+    /// TMDb movies
+    franchise: Option<MetadataFranchise>,
+    /// Whether the provider has stopped returning details for this media,
+    /// likely because it was removed from there
+    unavailable: bool,
 }
 
 impl From<MediaSortOrder> for Order {
@@ -338,16 +671,6 @@ impl From<MediaSortOrder> for Order {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Enum, Clone, PartialEq, Eq, Copy, Default)]
-enum MediaSortBy {
-    Title,
-    #[default]
-    ReleaseDate,
-    LastSeen,
-    LastUpdated,
-    Rating,
-}
-
 #[derive(Debug, Serialize, Deserialize, InputObject, Clone)]
 struct MediaSortInput {
     #[graphql(default)]
@@ -363,6 +686,8 @@ enum MediaGeneralFilter {
     Unrated,
     Dropped,
     Finished,
+    /// Media that the user has not finished yet, ie. their active backlog
+    NotFinished,
     Unseen,
 }
 
@@ -370,17 +695,51 @@ enum MediaGeneralFilter {
 struct MediaFilter {
     general: Option<MediaGeneralFilter>,
     collection: Option<i32>,
+    /// Only return media originally made in this language (ISO 639-1 code)
+    original_language: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, InputObject, Clone)]
 struct MediaListInput {
+    /// The page to get, ignored when `after` is set
+    #[graphql(default = 1)]
     page: i32,
+    /// The `cursor` of the item after which the list should continue. Unlike
+    /// pages, cursors are not affected by media added or removed in the
+    /// meantime and do not get slower on deep pages, but they only work when
+    /// sorting by title, release date or last updated.
+    after: Option<String>,
     lot: MetadataLot,
     sort: Option<MediaSortInput>,
     query: Option<String>,
     filter: Option<MediaFilter>,
 }
 
+/// The value of the sort column of a media list item.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum MediaListCursorKey {
+    Title(String),
+    ReleaseDate(Option<i32>),
+    LastUpdated(DateTimeUtc),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct MediaListCursor {
+    key: MediaListCursorKey,
+    id: i32,
+}
+
+impl MediaListCursor {
+    fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn decode(cursor: &str) -> Result<Self> {
+        serde_json::from_str(cursor)
+            .map_err(|_| coded_error(ErrorCode::Validation, "This cursor is invalid".to_owned()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, InputObject, Clone)]
 struct CollectionInput {
     name: Option<String>,
@@ -430,16 +789,36 @@ impl MiscellaneousQuery {
             .await
     }
 
-    /// Get all the public reviews for a media item.
+    /// Get the public reviews for a media item, newest first.
     async fn media_item_reviews(
         &self,
         gql_ctx: &Context<'_>,
         metadata_id: i32,
-    ) -> Result<Vec<ReviewItem>> {
+        page: Option<i32>,
+        filter: Option<ReviewFilterInput>,
+    ) -> Result<SearchResults<ReviewItem>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .media_item_reviews(
+                &user_id,
+                &metadata_id,
+                page.unwrap_or(1),
+                filter.unwrap_or_default(),
+            )
+            .await
+    }
+
+    /// Get all the reviews written by the currently logged in user, newest first.
+    async fn my_reviews(
+        &self,
+        gql_ctx: &Context<'_>,
+        page: i32,
+    ) -> Result<SearchResults<UserReviewItem>> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
         gql_ctx
             .data_unchecked::<Arc<MiscellaneousService>>()
-            .media_item_reviews(&user_id, &metadata_id)
+            .my_reviews(user_id, page)
             .await
     }
 
@@ -492,23 +871,79 @@ impl MiscellaneousQuery {
     }
 
     /// Get a summary of all the media items that have been consumed by this user.
-    async fn user_summary(&self, gql_ctx: &Context<'_>) -> Result<UserSummary> {
+    /// Also includes the last `recently_finished_limit` completed media items.
+    async fn user_summary(
+        &self,
+        gql_ctx: &Context<'_>,
+        recently_finished_limit: Option<u64>,
+    ) -> Result<UserSummary> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
         gql_ctx
             .data_unchecked::<Arc<MiscellaneousService>>()
-            .user_summary(&user_id)
+            .user_summary(&user_id, recently_finished_limit)
             .await
     }
 
-    /// Get details about a media present in the database.
+    /// Get details about a media present in the database. Logged in users can
+    /// pass `refresh` to update it from its provider first.
     async fn media_details(
         &self,
         gql_ctx: &Context<'_>,
         metadata_id: i32,
+        refresh: Option<bool>,
     ) -> Result<GraphqlMediaDetails> {
+        let user_id = user_id_from_ctx(gql_ctx).await;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        if refresh.unwrap_or_default() {
+            // only logged in users can make requests to the provider
+            user_id.clone()?;
+            service.refresh_metadata(metadata_id).await?;
+        }
+        service.media_details(metadata_id, user_id.ok()).await
+    }
+
+    /// Get the media that the provider of a media item recommends along with it.
+    /// The recommendations are added to the database and their identifiers are
+    /// the ids of the created media.
+    async fn media_details_recommendations(
+        &self,
+        gql_ctx: &Context<'_>,
+        metadata_id: i32,
+    ) -> Result<Vec<MediaSearchItem>> {
+        user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .media_details_recommendations(metadata_id)
+            .await
+    }
+
+    /// Get the other media in the franchise of a media item, in the order they
+    /// were released. Media that are already in the database have their
+    /// `databaseId` set, the others can be added with `commitMedia`.
+    async fn franchise_media(
+        &self,
+        gql_ctx: &Context<'_>,
+        metadata_id: i32,
+    ) -> Result<Vec<MediaSearchItemResponse>> {
+        user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .franchise_media(metadata_id)
+            .await
+    }
+
+    /// Get details about many media present in the database, in the same order as
+    /// the ids. Ids that do not exist are skipped, and at most 50 ids can be
+    /// requested at once.
+    async fn media_details_batch(
+        &self,
+        gql_ctx: &Context<'_>,
+        metadata_ids: Vec<i32>,
+    ) -> Result<Vec<GraphqlMediaDetails>> {
+        let user_id = user_id_from_ctx(gql_ctx).await.ok();
         gql_ctx
             .data_unchecked::<Arc<MiscellaneousService>>()
-            .media_details(metadata_id)
+            .media_details_batch(metadata_ids, user_id)
             .await
     }
 
@@ -526,15 +961,132 @@ impl MiscellaneousQuery {
     }
 
     /// Get all the media items related to a user for a specific media type.
+    /// Pages are fetched by number by default. Passing the `cursor` of the last
+    /// item as `after` instead gives stable pages that stay fast deep into the
+    /// list, at the cost of not being able to jump to an arbitrary page.
     async fn media_list(
         &self,
         gql_ctx: &Context<'_>,
         input: MediaListInput,
     ) -> Result<SearchResults<MediaListItem>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let include_backdrop = gql_ctx
+            .look_ahead()
+            .field("items")
+            .field("backdropImage")
+            .exists();
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .media_list(user_id, input, include_backdrop)
+            .await
+    }
+
+    /// Get the current and longest streak of days on which the user finished
+    /// something.
+    async fn user_streaks(&self, gql_ctx: &Context<'_>) -> Result<UserStreaks> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .user_streaks(user_id)
+            .await
+    }
+
+    /// Get the media finished by the user in a month, grouped by the day they
+    /// were finished on. Items finished without a date are placed on the day
+    /// they were logged, in the user's timezone.
+    async fn user_diary(
+        &self,
+        gql_ctx: &Context<'_>,
+        month: u32,
+        year: i32,
+    ) -> Result<Vec<UserDiaryDay>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .user_diary(user_id, month, year)
+            .await
+    }
+
+    /// Get the users whose completed media overlap the most with that of the
+    /// current user, most similar first.
+    async fn users_with_similar_taste(
+        &self,
+        gql_ctx: &Context<'_>,
+        limit: Option<usize>,
+    ) -> Result<Vec<SimilarUser>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .users_with_similar_taste(user_id, limit)
+            .await
+    }
+
+    /// Get the media that the user has started but not finished, across all
+    /// types of media, along with where to resume them from. The most recently
+    /// updated ones are returned first.
+    async fn resume_media(
+        &self,
+        gql_ctx: &Context<'_>,
+        limit: Option<usize>,
+    ) -> Result<Vec<ResumeMediaItem>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .resume_media(user_id, limit)
+            .await
+    }
+
+    /// Get the media that have been seen the most by all users of this instance
+    /// in the last `window_days` days.
+    async fn trending_media(
+        &self,
+        gql_ctx: &Context<'_>,
+        lot: Option<MetadataLot>,
+        window_days: Option<i64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<MediaSearchItem>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .trending_media(user_id, lot, window_days, limit)
+            .await
+    }
+
+    /// Get the number of media in the user's library for each type of media.
+    async fn library_counts(&self, gql_ctx: &Context<'_>) -> Result<Vec<LibraryLotCount>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .library_counts(user_id)
+            .await
+    }
+
+    /// Get the media in the user's library that are not part of any of their
+    /// collections.
+    async fn uncollected_media(
+        &self,
+        gql_ctx: &Context<'_>,
+        lot: Option<MetadataLot>,
+        page: i32,
+    ) -> Result<SearchResults<MediaSearchItem>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .uncollected_media(user_id, lot, page)
+            .await
+    }
+
+    /// Get the distinct publish years of the media in the user's library, most
+    /// recent first.
+    async fn library_publish_years(
+        &self,
+        gql_ctx: &Context<'_>,
+        lot: Option<MetadataLot>,
+    ) -> Result<Vec<i32>> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
         gql_ctx
             .data_unchecked::<Arc<MiscellaneousService>>()
-            .media_list(user_id, input)
+            .library_publish_years(user_id, lot)
             .await
     }
 
@@ -547,6 +1099,16 @@ impl MiscellaneousQuery {
             .await
     }
 
+    /// Get presigned URLs (valid for 90 minutes) for multiple keys, returned in
+    /// the same order as the keys.
+    async fn get_presigned_urls(&self, gql_ctx: &Context<'_>, keys: Vec<String>) -> Vec<String> {
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .file_storage
+            .get_presigned_urls(keys)
+            .await
+    }
+
     /// Get all the features that are enabled for the service
     async fn core_enabled_features(&self, gql_ctx: &Context<'_>) -> Result<GeneralFeatures> {
         gql_ctx
@@ -555,6 +1117,14 @@ impl MiscellaneousQuery {
             .await
     }
 
+    /// Check whether the database and file storage can be reached.
+    async fn server_health(&self, gql_ctx: &Context<'_>) -> ServerHealth {
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .server_health()
+            .await
+    }
+
     /// Get a user's preferences.
     async fn user_preferences(&self, gql_ctx: &Context<'_>) -> Result<UserPreferences> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
@@ -578,6 +1148,20 @@ impl MiscellaneousQuery {
             .await
     }
 
+    /// Search for a list of media in all the enabled sources of a type at once.
+    /// Results that appear in more than one source are only returned once.
+    async fn media_search_all_sources(
+        &self,
+        gql_ctx: &Context<'_>,
+        lot: MetadataLot,
+        input: SearchInput,
+    ) -> Result<AllSourcesMediaSearchResults> {
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .media_search_all_sources(lot, input)
+            .await
+    }
+
     /// Check if a media with the given metadata and identifier exists in the database.
     async fn media_exists_in_database(
         &self,
@@ -604,6 +1188,28 @@ impl MiscellaneousQuery {
             .await
     }
 
+    /// Get the metadata sources for a lot that have been configured on this
+    /// instance and can be used.
+    async fn enabled_sources_for_lot(
+        &self,
+        gql_ctx: &Context<'_>,
+        lot: MetadataLot,
+    ) -> Vec<MetadataSource> {
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .enabled_sources_for_lot(lot)
+            .await
+    }
+
+    /// Get the variants of the enums commonly needed by clients.
+    async fn enum_reference(&self) -> EnumReference {
+        EnumReference {
+            metadata_lots: enum_variant_references(MetadataLot::iter()),
+            metadata_sources: enum_variant_references(MetadataSource::iter()),
+            visibilities: enum_variant_references(Visibility::iter()),
+        }
+    }
+
     /// Get all languages supported by all the providers.
     async fn providers_language_information(
         &self,
@@ -634,10 +1240,54 @@ impl MiscellaneousQuery {
             .user_auth_tokens(user_id)
             .await
     }
-}
 
-#[derive(Default)]
-pub struct MiscellaneousMutation;
+    /// Check whether the images stored in file storage that are referenced by
+    /// media, exercises and image overrides still exist. Only the first `limit`
+    /// references are checked if it is given. Only available to admins.
+    async fn audit_images(
+        &self,
+        gql_ctx: &Context<'_>,
+        limit: Option<usize>,
+    ) -> Result<ImageAudit> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        service.admin_account_guard(user_id).await?;
+        service.audit_images(limit).await
+    }
+
+    /// Get groups of media items that are likely duplicates of each other, to be
+    /// used with `mergeMetadata`. Only available to admins.
+    async fn find_duplicate_metadata(
+        &self,
+        gql_ctx: &Context<'_>,
+    ) -> Result<Vec<DuplicateMetadataGroup>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        service.admin_account_guard(user_id).await?;
+        service.find_duplicate_metadata().await
+    }
+
+    /// Get all the background jobs that are waiting to be run. Only available to
+    /// admins.
+    async fn pending_jobs(&self, gql_ctx: &Context<'_>) -> Result<Vec<PendingJob>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        service.admin_account_guard(user_id).await?;
+        service.pending_jobs().await
+    }
+
+    /// Get all the users of the service, along with whether their email has
+    /// been verified and when they were created. Only available to admins.
+    async fn users_list(&self, gql_ctx: &Context<'_>) -> Result<Vec<user::Model>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        service.admin_account_guard(user_id).await?;
+        service.users_list().await
+    }
+}
+
+#[derive(Default)]
+pub struct MiscellaneousMutation;
 
 #[Object]
 impl MiscellaneousMutation {
@@ -659,6 +1309,59 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Delete many reviews of the user at once. Reviews of other users are not
+    /// deleted and are returned as rejected, while ids of reviews that do not
+    /// exist are ignored.
+    async fn delete_reviews(
+        &self,
+        gql_ctx: &Context<'_>,
+        review_ids: Vec<i32>,
+    ) -> Result<DeleteReviewsResult> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .delete_reviews(user_id, review_ids)
+            .await
+    }
+
+    /// Pin or unpin a review of the logged in user. Pinning a review unpins any
+    /// other review of the user for the same media.
+    async fn set_review_pinned(
+        &self,
+        gql_ctx: &Context<'_>,
+        review_id: i32,
+        pinned: bool,
+    ) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .set_review_pinned(user_id, review_id, pinned)
+            .await
+    }
+
+    /// React to a review, replacing any earlier reaction of the user to it.
+    async fn react_to_review(
+        &self,
+        gql_ctx: &Context<'_>,
+        review_id: i32,
+        kind: ReviewReactionKind,
+    ) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .react_to_review(user_id, review_id, kind)
+            .await
+    }
+
+    /// Remove the reaction of the user to a review.
+    async fn remove_review_reaction(&self, gql_ctx: &Context<'_>, review_id: i32) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .remove_review_reaction(user_id, review_id)
+            .await
+    }
+
     /// Create a new collection for the logged in user or edit details of an existing one.
     async fn create_or_update_collection(
         &self,
@@ -685,6 +1388,20 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Commit media items using their provider identifiers and add them to a
+    /// collection, which will be created if it does not exist.
+    async fn populate_collection_from_external(
+        &self,
+        gql_ctx: &Context<'_>,
+        input: PopulateCollectionFromExternalInput,
+    ) -> Result<Vec<PopulateCollectionItemResult>> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .populate_collection_from_external(user_id, input)
+            .await
+    }
+
     /// Remove a media item from a collection if it is not there, otherwise do nothing.
     async fn remove_media_from_collection(
         &self,
@@ -712,6 +1429,36 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Rename a collection, keeping the media in it. Fails if the user already
+    /// has another collection with this name.
+    async fn rename_collection(
+        &self,
+        gql_ctx: &Context<'_>,
+        collection_id: i32,
+        name: String,
+    ) -> Result<IdObject> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .rename_collection(user_id, collection_id, name)
+            .await
+    }
+
+    /// Move all the media in a collection into another one and delete the
+    /// former. Returns the ID of the collection that was merged into.
+    async fn merge_collections(
+        &self,
+        gql_ctx: &Context<'_>,
+        from_collection_id: i32,
+        into_collection_id: i32,
+    ) -> Result<IdObject> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .merge_collections(user_id, from_collection_id, into_collection_id)
+            .await
+    }
+
     /// Delete a seen item from a user's history.
     async fn delete_seen_item(&self, gql_ctx: &Context<'_>, seen_id: i32) -> Result<IdObject> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
@@ -721,6 +1468,19 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Change the dates or progress of an existing seen item.
+    async fn update_seen_item(
+        &self,
+        gql_ctx: &Context<'_>,
+        input: UpdateSeenItemInput,
+    ) -> Result<IdObject> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .update_seen_item(input, user_id)
+            .await
+    }
+
     /// Deploy jobs to update all media item's metadata.
     async fn update_all_metadata(&self, gql_ctx: &Context<'_>) -> Result<bool> {
         gql_ctx
@@ -766,7 +1526,17 @@ impl MiscellaneousMutation {
             .await
     }
 
-    /// Update a user's profile details.
+    /// Verify the email of a user using the token that was issued when it was
+    /// set. Returns `false` if the token does not exist.
+    async fn verify_email(&self, gql_ctx: &Context<'_>, token: String) -> Result<bool> {
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .verify_email(&token)
+            .await
+    }
+
+    /// Update a user's profile details. Changing the email issues a new token
+    /// that has to be used to verify it.
     async fn update_user(&self, gql_ctx: &Context<'_>, input: UpdateUserInput) -> Result<IdObject> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
         gql_ctx
@@ -797,12 +1567,13 @@ impl MiscellaneousMutation {
             .await
     }
 
-    /// Mark a user's progress on a specific media item.
+    /// Mark a user's progress on a specific media item and get the `seen` item
+    /// that was created or updated.
     async fn progress_update(
         &self,
         gql_ctx: &Context<'_>,
         input: ProgressUpdateInput,
-    ) -> Result<IdObject> {
+    ) -> Result<seen::Model> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
         gql_ctx
             .data_unchecked::<Arc<MiscellaneousService>>()
@@ -810,6 +1581,16 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Restore the most recently dropped `seen` item of a media item so that it
+    /// is in progress again.
+    async fn undrop_media(&self, gql_ctx: &Context<'_>, metadata_id: i32) -> Result<seen::Model> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .undrop_media(metadata_id, user_id)
+            .await
+    }
+
     /// Deploy a job to update a media item's metadata.
     async fn deploy_update_metadata_job(
         &self,
@@ -836,6 +1617,22 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Add the results of a media search that are not in the database yet to it,
+    /// up to 20 per request. Returns the outcome for every result of the page.
+    async fn commit_media_search_results(
+        &self,
+        gql_ctx: &Context<'_>,
+        lot: MetadataLot,
+        source: MetadataSource,
+        input: SearchInput,
+    ) -> Result<Vec<CommittedMediaSearchItem>> {
+        user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .commit_media_search_results(lot, source, input)
+            .await
+    }
+
     /// Fetch details about a media and create a media item in the database.
     async fn commit_media(
         &self,
@@ -863,6 +1660,49 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Restore the default feature preferences for the currently logged in user
+    /// and return the updated preferences.
+    async fn reset_feature_preferences(&self, gql_ctx: &Context<'_>) -> Result<UserPreferences> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .reset_feature_preferences(user_id)
+            .await
+    }
+
+    /// Change the timezone used to decide what "today" is for the currently
+    /// logged in user. Accepts `UTC` or an offset like `+05:30`.
+    async fn update_user_timezone(&self, gql_ctx: &Context<'_>, timezone: String) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .update_user_timezone(timezone, user_id)
+            .await
+    }
+
+    /// Change the region of the currently logged in user.
+    async fn update_user_region(&self, gql_ctx: &Context<'_>, region: String) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .update_user_region(region, user_id)
+            .await
+    }
+
+    /// Change the ordering used when listing media without an explicit sort for
+    /// the currently logged in user.
+    async fn update_user_media_list_sort(
+        &self,
+        gql_ctx: &Context<'_>,
+        input: MediaSortInput,
+    ) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .update_user_media_list_sort(input, user_id)
+            .await
+    }
+
     /// Generate an auth token without any expiry
     async fn generate_application_token(&self, gql_ctx: &Context<'_>) -> Result<String> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
@@ -885,6 +1725,20 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Check that the details of a yank based integration work, without saving
+    /// them. Returns an error describing the problem if they do not.
+    async fn test_yank_integration(
+        &self,
+        gql_ctx: &Context<'_>,
+        input: CreateUserYankIntegrationInput,
+    ) -> Result<YankIntegrationTestResult> {
+        user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .test_yank_integration(input)
+            .await
+    }
+
     /// Delete a yank based integrations for the currently logged in user.
     async fn delete_user_yank_integration(
         &self,
@@ -898,6 +1752,21 @@ impl MiscellaneousMutation {
             .await
     }
 
+    /// Use a custom poster (URL or uploaded file key) for a media item instead of
+    /// the images provided by the source. Pass `null` to remove the override.
+    async fn set_media_image_override(
+        &self,
+        gql_ctx: &Context<'_>,
+        metadata_id: i32,
+        key: Option<String>,
+    ) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        gql_ctx
+            .data_unchecked::<Arc<MiscellaneousService>>()
+            .set_media_image_override(user_id, metadata_id, key)
+            .await
+    }
+
     /// Yank data from all integrations for the currently logged in user
     async fn yank_integration_data(&self, gql_ctx: &Context<'_>) -> Result<usize> {
         let user_id = user_id_from_ctx(gql_ctx).await?;
@@ -915,6 +1784,50 @@ impl MiscellaneousMutation {
             .delete_user_auth_token(user_id, token)
             .await
     }
+
+    /// Move everything a user has tracked to another user, for merging
+    /// accounts. Collections with the same name are merged and the summaries of
+    /// both users are regenerated. Only available to admins.
+    async fn transfer_user_library(
+        &self,
+        gql_ctx: &Context<'_>,
+        from_user_id: i32,
+        to_user_id: i32,
+    ) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        service.admin_account_guard(user_id).await?;
+        service
+            .transfer_user_library(from_user_id, to_user_id)
+            .await
+    }
+
+    /// Remove media and their associations with users that are no longer needed.
+    /// Only available to admins.
+    async fn run_cleanup(&self, gql_ctx: &Context<'_>) -> Result<CleanupResult> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        service.admin_account_guard(user_id).await?;
+        service.run_cleanup().await
+    }
+
+    /// Recalculate the number of times all media items have been seen. Only
+    /// available to admins.
+    async fn recompute_popularity(&self, gql_ctx: &Context<'_>) -> Result<usize> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        service.admin_account_guard(user_id).await?;
+        service.recompute_popularity().await
+    }
+
+    /// Remove a background job that has not been started yet. Only available to
+    /// admins.
+    async fn cancel_job(&self, gql_ctx: &Context<'_>, job_id: String) -> Result<bool> {
+        let user_id = user_id_from_ctx(gql_ctx).await?;
+        let service = gql_ctx.data_unchecked::<Arc<MiscellaneousService>>();
+        service.admin_account_guard(user_id).await?;
+        service.cancel_job(job_id).await
+    }
 }
 
 pub struct MiscellaneousService {
@@ -937,6 +1850,10 @@ pub struct MiscellaneousService {
     update_metadata: SqliteStorage<UpdateMetadataJob>,
     recalculate_user_summary: SqliteStorage<RecalculateUserSummaryJob>,
     user_created: SqliteStorage<UserCreatedJob>,
+    scheduler_pool: SqlitePool,
+    details_cache: MemoryCache<String, MediaDetails>,
+    search_cache: MemoryCache<String, SearchResults<MediaSearchItem>>,
+    watch_providers_miss_cache: MemoryCache<i32, Option<Vec<WatchProvider>>>,
 }
 
 impl MiscellaneousService {
@@ -950,6 +1867,7 @@ impl MiscellaneousService {
         update_metadata: &SqliteStorage<UpdateMetadataJob>,
         recalculate_user_summary: &SqliteStorage<RecalculateUserSummaryJob>,
         user_created: &SqliteStorage<UserCreatedJob>,
+        scheduler_pool: &SqlitePool,
     ) -> Self {
         let openlibrary_service = OpenlibraryService::new(&config.books.openlibrary).await;
         let google_books_service = GoogleBooksService::new(&config.books.google_books).await;
@@ -962,6 +1880,8 @@ impl MiscellaneousService {
         let anilist_anime_service = AnilistAnimeService::new(&config.anime.anilist).await;
         let anilist_manga_service = AnilistMangaService::new(&config.manga.anilist).await;
         let integration_service = IntegrationService::new().await;
+        let cache_ttl = StdDuration::from_secs(config.media.provider_cache_ttl);
+        let cache_size = config.media.provider_cache_size;
 
         Self {
             db: db.clone(),
@@ -983,14 +1903,20 @@ impl MiscellaneousService {
             update_metadata: update_metadata.clone(),
             recalculate_user_summary: recalculate_user_summary.clone(),
             user_created: user_created.clone(),
+            scheduler_pool: scheduler_pool.clone(),
+            details_cache: MemoryCache::new(cache_ttl, cache_size),
+            search_cache: MemoryCache::new(cache_ttl, cache_size),
+            watch_providers_miss_cache: MemoryCache::new(
+                StdDuration::from_secs(WATCH_PROVIDERS_MISS_TTL_SECONDS),
+                WATCH_PROVIDERS_MISS_CACHE_SIZE,
+            ),
         }
     }
 }
 
 impl MiscellaneousService {
-    async fn metadata_images(&self, meta: &metadata::Model) -> Result<(Vec<String>, Vec<String>)> {
-        let mut poster_images = vec![];
-        let mut backdrop_images = vec![];
+    async fn metadata_images(&self, meta: &metadata::Model) -> Result<MetadataImageUrls> {
+        let mut urls = MetadataImageUrls::default();
         async fn get_image(m: MetadataImageUrl, storage: Arc<FileStorageService>) -> String {
             match m {
                 MetadataImageUrl::Url(u) => u,
@@ -998,16 +1924,84 @@ impl MiscellaneousService {
             }
         }
         for i in meta.images.0.clone() {
+            let image = get_image(i.url, self.file_storage.clone()).await;
             match i.lot {
-                MetadataImageLot::Backdrop => {
-                    backdrop_images.push(get_image(i.url, self.file_storage.clone()).await);
-                }
-                MetadataImageLot::Poster => {
-                    poster_images.push(get_image(i.url, self.file_storage.clone()).await);
-                }
+                MetadataImageLot::Backdrop | MetadataImageLot::Still => urls.backdrops.push(image),
+                MetadataImageLot::Logo => urls.logos.push(image),
+                MetadataImageLot::Poster => urls.posters.push(image),
             };
         }
-        Ok((poster_images, backdrop_images))
+        Ok(urls)
+    }
+
+    async fn user_image_override(&self, user_id: i32, metadata_id: i32) -> Result<Option<String>> {
+        let utm = UserToMetadata::find()
+            .filter(user_to_metadata::Column::UserId.eq(user_id))
+            .filter(user_to_metadata::Column::MetadataId.eq(metadata_id))
+            .one(&self.db)
+            .await?;
+        let image = match utm.and_then(|u| u.image_override) {
+            None => None,
+            Some(i) => Some(self.image_override_url(i).await),
+        };
+        Ok(image)
+    }
+
+    /// Get the watch providers of a TMDb media item in all regions, fetching and
+    /// caching them on the row if they have not been fetched yet. Lookups that
+    /// fail or find nothing are only remembered for a short while, so that they
+    /// are retried later without calling TMDb on every request.
+    async fn metadata_watch_providers(
+        &self,
+        model: &metadata::Model,
+    ) -> Option<Vec<WatchProvider>> {
+        if model.source != MetadataSource::Tmdb {
+            return None;
+        }
+        if let Some(w) = &model.watch_providers {
+            return Some(w.0.clone());
+        }
+        if let Some(w) = self.watch_providers_miss_cache.get(&model.id) {
+            return w;
+        }
+        let providers = match model.lot {
+            MetadataLot::Movie => {
+                self.tmdb_movies_service
+                    .watch_providers(&model.identifier)
+                    .await
+            }
+            MetadataLot::Show => {
+                self.tmdb_shows_service
+                    .watch_providers(&model.identifier)
+                    .await
+            }
+            _ => return None,
+        };
+        let providers = match providers {
+            Ok(providers) if !providers.is_empty() => {
+                let mut meta: metadata::ActiveModel = model.clone().into();
+                meta.watch_providers =
+                    ActiveValue::Set(Some(MetadataWatchProviders(providers.clone())));
+                meta.update(&self.db).await.ok();
+                return Some(providers);
+            }
+            Ok(providers) => Some(providers),
+            Err(e) => {
+                tracing::error!("Error while fetching watch providers: {:?}", e);
+                None
+            }
+        };
+        self.watch_providers_miss_cache
+            .insert(model.id, providers.clone());
+        providers
+    }
+
+    async fn image_override_url(&self, image: String) -> String {
+        if image.starts_with("http://") || image.starts_with("https://") {
+            image
+        } else {
+            self.file_storage.get_presigned_url(image).await
+        }
     }
 
     async fn generic_metadata(&self, metadata_id: i32) -> Result<MediaBaseData> {
@@ -1017,7 +2011,12 @@ impl MiscellaneousService {
             .unwrap()
         {
             Some(m) => m,
-            None => return Err(Error::new("The record does not exit".to_owned())),
+            None => {
+                return Err(coded_error(
+                    ErrorCode::NotFound,
+                    "The record does not exit".to_owned(),
+                ))
+            }
         };
         let genres = meta
             .find_related(Genre)
@@ -1028,166 +2027,292 @@ impl MiscellaneousService {
             .map(|g| g.name)
             .collect();
         let creators = meta.creators.clone().0;
-        let (poster_images, backdrop_images) = self.metadata_images(&meta).await.unwrap();
+        let images = self.metadata_images(&meta).await.unwrap();
         if let Some(ref mut d) = meta.description {
-            *d = markdown_to_html_opts(
-                d,
-                &Options {
-                    compile: CompileOptions {
-                        allow_dangerous_html: true,
-                        allow_dangerous_protocol: true,
-                        ..CompileOptions::default()
-                    },
-                    ..Options::default()
-                },
-            )
-            .unwrap();
+            *d = description_to_html(d);
         }
         Ok(MediaBaseData {
             model: meta,
             creators,
-            poster_images,
-            backdrop_images,
+            poster_images: images.posters,
+            backdrop_images: images.backdrops,
             genres,
         })
     }
 
-    async fn media_details(&self, metadata_id: i32) -> Result<GraphqlMediaDetails> {
-        let MediaBaseData {
-            model,
-            creators,
-            poster_images,
-            backdrop_images,
-            genres,
-        } = self.generic_metadata(metadata_id).await?;
-        let slug = slug::slugify(&model.title);
-        let identifier = &model.identifier;
-        let source_url = match model.source {
-            MetadataSource::Custom => None,
-            MetadataSource::Itunes => Some(format!(
-                "https://podcasts.apple.com/us/podcast/{slug}/id{identifier}"
-            )),
-            MetadataSource::GoogleBooks => Some(format!(
-                "https://www.google.co.in/books/edition/{slug}/{identifier}"
+    async fn media_details(
+        &self,
+        metadata_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<GraphqlMediaDetails> {
+        match self
+            .media_details_batch(vec![metadata_id], user_id)
+            .await?
+            .pop()
+        {
+            Some(details) => Ok(details),
+            None => Err(coded_error(
+                ErrorCode::NotFound,
+                "The record does not exit".to_owned(),
             )),
-            MetadataSource::Audible => {
-                Some(format!("https://www.audible.com/pd/{slug}/{identifier}"))
-            }
-            MetadataSource::Openlibrary => {
-                Some(format!("https://openlibrary.org/works/{identifier}/{slug}"))
-            }
-            MetadataSource::Tmdb => {
-                let bw = match model.lot {
-                    MetadataLot::Movie => "movie",
-                    MetadataLot::Show => "tv",
-                    _ => unreachable!(),
-                };
-                Some(format!(
-                    "https://www.themoviedb.org/{bw}/{identifier}-{slug}"
+        }
+    }
+
+    async fn media_details_recommendations(
+        &self,
+        metadata_id: i32,
+    ) -> Result<Vec<MediaSearchItem>> {
+        let metadata = match Metadata::find_by_id(metadata_id).one(&self.db).await? {
+            Some(m) => m,
+            None => {
+                return Err(coded_error(
+                    ErrorCode::NotFound,
+                    "The record does not exit".to_owned(),
                 ))
             }
-            MetadataSource::Listennotes => Some(format!(
-                "https://www.listennotes.com/podcasts/{slug}-{identifier}"
-            )),
-            MetadataSource::Igdb => Some(format!("https://www.igdb.com/games/{slug}")),
-            MetadataSource::Anilist => {
-                let bw = match model.lot {
-                    MetadataLot::Anime => "anime",
-                    MetadataLot::Manga => "manga",
-                    _ => unreachable!(),
-                };
-                Some(format!("https://anilist.co/{bw}/{identifier}/{slug}"))
-            }
         };
+        if metadata.source == MetadataSource::Custom {
+            return Ok(vec![]);
+        }
+        let recommendations = self
+            .get_provider(metadata.lot, metadata.source)?
+            .recommendations(&metadata.identifier)
+            .await?;
+        let mut items = vec![];
+        for item in recommendations
+            .into_iter()
+            .take(MEDIA_RECOMMENDATIONS_LIMIT)
+        {
+            // recommendations that can not be fetched from the provider are skipped
+            let Ok(id) = self
+                .commit_media(item.lot, metadata.source, &item.identifier)
+                .await
+            else {
+                continue;
+            };
+            items.push(MediaSearchItem {
+                identifier: id.id.to_string(),
+                ..item
+            });
+        }
+        Ok(items)
+    }
 
-        let metadata_alias = Alias::new("m");
-        let seen_alias = Alias::new("s");
-
-        let seen_select = Query::select()
-            .expr_as(
-                Expr::col((metadata_alias.clone(), TempMetadata::Id)),
-                Alias::new("metadata_id"),
-            )
-            .expr_as(
-                Func::count(Expr::col((seen_alias.clone(), TempSeen::MetadataId))),
-                Alias::new("num_times_seen"),
-            )
-            .from_as(TempMetadata::Table, metadata_alias.clone())
-            .join_as(
-                JoinType::LeftJoin,
-                TempSeen::Table,
-                seen_alias.clone(),
-                Expr::col((metadata_alias.clone(), TempMetadata::Id))
-                    .equals((seen_alias.clone(), TempSeen::MetadataId)),
-            )
-            .and_where(Expr::col((metadata_alias.clone(), TempMetadata::Id)).eq(metadata_id))
-            .group_by_col((metadata_alias.clone(), TempMetadata::Id))
-            .to_owned();
-
-        let stmt = self.get_db_stmt(seen_select);
-        let seen_by = self
-            .db
-            .query_one(stmt)
+    async fn franchise_media(&self, metadata_id: i32) -> Result<Vec<MediaSearchItemResponse>> {
+        let metadata = Metadata::find_by_id(metadata_id)
+            .one(&self.db)
             .await?
-            .map(|qr| qr.try_get_by_index::<i64>(1).unwrap())
-            .unwrap();
-        let seen_by: i32 = seen_by.try_into().unwrap();
-
-        let mut resp = GraphqlMediaDetails {
-            id: model.id,
-            title: model.title,
-            identifier: model.identifier,
-            description: model.description,
-            publish_year: model.publish_year,
-            publish_date: model.publish_date,
-            source: model.source,
-            lot: model.lot,
-            creators,
-            genres,
-            poster_images,
-            backdrop_images,
-            book_specifics: None,
-            movie_specifics: None,
-            show_specifics: None,
-            video_game_specifics: None,
-            audio_book_specifics: None,
-            podcast_specifics: None,
-            manga_specifics: None,
-            anime_specifics: None,
-            source_url,
-            seen_by,
+            .ok_or_else(|| {
+                coded_error(ErrorCode::NotFound, "The record does not exit".to_owned())
+            })?;
+        let Some(franchise) = metadata.franchise else {
+            return Ok(vec![]);
         };
-        match model.specifics {
-            MediaSpecifics::AudioBook(a) => {
-                resp.audio_book_specifics = Some(a);
-            }
-            MediaSpecifics::Book(a) => {
-                resp.book_specifics = Some(a);
-            }
-            MediaSpecifics::Movie(a) => {
-                resp.movie_specifics = Some(a);
+        let parts = match (metadata.source, metadata.lot) {
+            (MetadataSource::Tmdb, MetadataLot::Movie) => {
+                self.tmdb_movies_service
+                    .franchise_parts(&franchise.identifier)
+                    .await?
             }
-            MediaSpecifics::Podcast(a) => {
-                resp.podcast_specifics = Some(a);
-            }
-            MediaSpecifics::Show(a) => {
-                resp.show_specifics = Some(a);
-            }
-            MediaSpecifics::VideoGame(a) => {
-                resp.video_game_specifics = Some(a);
-            }
-            MediaSpecifics::Anime(a) => {
-                resp.anime_specifics = Some(a);
-            }
-            MediaSpecifics::Manga(a) => {
-                resp.manga_specifics = Some(a);
-            }
-            MediaSpecifics::Unknown => {}
+            _ => vec![],
         };
-        Ok(resp)
+        franchise_media_items(&self.db, &metadata, parts).await
     }
 
-    async fn seen_history(&self, metadata_id: i32, user_id: i32) -> Result<Vec<seen::Model>> {
+    /// Get the details of many media items using a fixed number of queries. The
+    /// results are in the same order as `metadata_ids` and ids that do not exist
+    /// are skipped.
+    async fn media_details_batch(
+        &self,
+        metadata_ids: Vec<i32>,
+        user_id: Option<i32>,
+    ) -> Result<Vec<GraphqlMediaDetails>> {
+        if metadata_ids.len() > MEDIA_DETAILS_BATCH_LIMIT {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                format!(
+                    "At most {} media can be fetched at once",
+                    MEDIA_DETAILS_BATCH_LIMIT
+                ),
+            ));
+        }
+        let mut models: HashMap<i32, metadata::Model> = Metadata::find()
+            .filter(metadata::Column::Id.is_in(metadata_ids.clone()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+        let mut genres: HashMap<i32, Vec<String>> = HashMap::new();
+        for (link, genre) in MetadataToGenre::find()
+            .filter(metadata_to_genre::Column::MetadataId.is_in(metadata_ids.clone()))
+            .find_also_related(Genre)
+            .all(&self.db)
+            .await?
+        {
+            if let Some(g) = genre {
+                genres.entry(link.metadata_id).or_default().push(g.name);
+            }
+        }
+        let collection_counts: HashMap<i32, i64> = match user_id {
+            Some(u) => MetadataToCollection::find()
+                .select_only()
+                .column(metadata_to_collection::Column::MetadataId)
+                .column_as(
+                    Expr::col(metadata_to_collection::Column::CollectionId).count(),
+                    "num_collections",
+                )
+                .inner_join(Collection)
+                .filter(collection::Column::UserId.eq(u))
+                .filter(metadata_to_collection::Column::MetadataId.is_in(metadata_ids.clone()))
+                .group_by(metadata_to_collection::Column::MetadataId)
+                .into_tuple::<(i32, i64)>()
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .collect(),
+            None => HashMap::new(),
+        };
+        let (is_admin, region, mut image_overrides) = match user_id {
+            Some(u) => {
+                let user = self.user_by_id(u).await?;
+                let is_admin = user.lot == UserLot::Admin;
+                let image_overrides: HashMap<i32, String> = UserToMetadata::find()
+                    .filter(user_to_metadata::Column::UserId.eq(u))
+                    .filter(user_to_metadata::Column::MetadataId.is_in(metadata_ids.clone()))
+                    .filter(user_to_metadata::Column::ImageOverride.is_not_null())
+                    .all(&self.db)
+                    .await?
+                    .into_iter()
+                    .filter_map(|utm| utm.image_override.map(|i| (utm.metadata_id, i)))
+                    .collect();
+                (is_admin, user.preferences.region, image_overrides)
+            }
+            None => (false, UserPreferences::default().region, HashMap::new()),
+        };
+        // the providers of media that do not have them yet are fetched from TMDb,
+        // so a few of them are requested at a time
+        let mut all_watch_providers: HashMap<i32, Option<Vec<WatchProvider>>> =
+            stream::iter(models.values())
+                .map(|m| async move { (m.id, self.metadata_watch_providers(m).await) })
+                .buffer_unordered(WATCH_PROVIDERS_CONCURRENCY)
+                .collect()
+                .await;
+
+        let mut all_details = vec![];
+        for metadata_id in metadata_ids {
+            let mut model = match models.remove(&metadata_id) {
+                Some(m) => m,
+                None => continue,
+            };
+            let mut images = self.metadata_images(&model).await?;
+            if let Some(image) = image_overrides.remove(&metadata_id) {
+                images
+                    .posters
+                    .insert(0, self.image_override_url(image).await);
+            }
+            if let Some(ref mut d) = model.description {
+                *d = description_to_html(d);
+            }
+            let source_url = metadata_source_url(&model);
+            let collection_count: i32 = collection_counts
+                .get(&metadata_id)
+                .copied()
+                .unwrap_or_default()
+                .try_into()
+                .unwrap();
+            let is_editable_by_me = match (model.source, user_id) {
+                (MetadataSource::Custom, Some(u)) => model.created_by == Some(u) || is_admin,
+                _ => false,
+            };
+            let watch_providers = all_watch_providers.remove(&metadata_id).flatten().map(|w| {
+                w.into_iter()
+                    .filter(|p| p.region == region)
+                    .collect::<Vec<_>>()
+            });
+            let creators = model.creators.clone().0;
+            let genres = genres.remove(&metadata_id).unwrap_or_default();
+            let mut resp = GraphqlMediaDetails {
+                id: model.id,
+                title: model.title,
+                identifier: model.identifier,
+                description: model.description,
+                publish_year: model.publish_year,
+                publish_date: model.publish_date,
+                source: model.source,
+                lot: model.lot,
+                creators,
+                genres,
+                poster_images: images.posters,
+                backdrop_images: images.backdrops,
+                logo_images: images.logos,
+                book_specifics: None,
+                movie_specifics: None,
+                show_specifics: None,
+                video_game_specifics: None,
+                audio_book_specifics: None,
+                podcast_specifics: None,
+                manga_specifics: None,
+                anime_specifics: None,
+                source_url,
+                seen_by: model.popularity,
+                collection_count,
+                is_editable_by_me,
+                seasons: vec![],
+                watch_providers,
+                provider_rating: model.provider_rating,
+                original_language: model.original_language,
+                franchise: model.franchise,
+                unavailable: is_unavailable(
+                    model.failed_refreshes,
+                    self.config.media.unavailable_after_failed_refreshes,
+                ),
+            };
+            match model.specifics {
+                MediaSpecifics::AudioBook(a) => {
+                    resp.audio_book_specifics = Some(a);
+                }
+                MediaSpecifics::Book(a) => {
+                    resp.book_specifics = Some(a);
+                }
+                MediaSpecifics::Movie(a) => {
+                    resp.movie_specifics = Some(a);
+                }
+                MediaSpecifics::Podcast(a) => {
+                    resp.podcast_specifics = Some(a);
+                }
+                MediaSpecifics::Show(a) => {
+                    resp.seasons = a
+                        .seasons
+                        .iter()
+                        .map(|s| GraphqlShowSeasonSummary {
+                            season_number: s.season_number,
+                            name: s.name.clone(),
+                            overview: s.overview.clone(),
+                            poster: s.poster_images.first().cloned(),
+                            publish_date: s.publish_date,
+                            episodes_count: s.episodes.len(),
+                        })
+                        .collect();
+                    resp.show_specifics = Some(a);
+                }
+                MediaSpecifics::VideoGame(a) => {
+                    resp.video_game_specifics = Some(a);
+                }
+                MediaSpecifics::Anime(a) => {
+                    resp.anime_specifics = Some(a);
+                }
+                MediaSpecifics::Manga(a) => {
+                    resp.manga_specifics = Some(a);
+                }
+                MediaSpecifics::Unknown => {}
+            };
+            all_details.push(resp);
+        }
+        Ok(all_details)
+    }
+
+    async fn seen_history(&self, metadata_id: i32, user_id: i32) -> Result<Vec<seen::Model>> {
         let mut seen = Seen::find()
             .filter(seen::Column::UserId.eq(user_id))
             .filter(seen::Column::MetadataId.eq(metadata_id))
@@ -1199,10 +2324,390 @@ impl MiscellaneousService {
         Ok(seen)
     }
 
+    async fn users_with_similar_taste(
+        &self,
+        user_id: i32,
+        limit: Option<usize>,
+    ) -> Result<Vec<SimilarUser>> {
+        let limit = limit
+            .unwrap_or(SIMILAR_USERS_DEFAULT_LIMIT)
+            .min(SIMILAR_USERS_MAX_LIMIT);
+        let mut libraries: HashMap<i32, HashSet<i32>> = HashMap::new();
+        for (u, m) in Seen::find()
+            .select_only()
+            .column(seen::Column::UserId)
+            .column(seen::Column::MetadataId)
+            .filter(seen::Column::Progress.eq(100))
+            .distinct()
+            .into_tuple::<(i32, i32)>()
+            .all(&self.db)
+            .await?
+        {
+            libraries.entry(u).or_default().insert(m);
+        }
+        let own_library = libraries.remove(&user_id).unwrap_or_default();
+        let similar = libraries
+            .into_iter()
+            .map(|(u, library)| {
+                let shared = own_library.intersection(&library).count();
+                (u, jaccard_similarity(&own_library, &library), shared)
+            })
+            .filter(|(_, _, shared)| *shared > 0)
+            .sorted_by(|a, b| b.1.total_cmp(&a.1))
+            .take(limit)
+            .collect::<Vec<_>>();
+        let mut users: HashMap<i32, user::Model> = User::find()
+            .filter(user::Column::Id.is_in(similar.iter().map(|s| s.0).collect::<Vec<_>>()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|u| (u.id, u))
+            .collect();
+        let mut resp = vec![];
+        for (u, similarity, shared_media) in similar {
+            let Some(user) = users.remove(&u) else {
+                continue;
+            };
+            let public_collections = Collection::find()
+                .filter(collection::Column::UserId.eq(u))
+                .filter(collection::Column::Visibility.eq(Visibility::Public))
+                .all(&self.db)
+                .await?;
+            resp.push(SimilarUser {
+                id: user.id,
+                name: user.name,
+                similarity,
+                shared_media,
+                public_collections,
+            });
+        }
+        Ok(resp)
+    }
+
+    async fn user_streaks(&self, user_id: i32) -> Result<UserStreaks> {
+        let today = self.user_by_id(user_id).await?.preferences.today();
+        let dates: Vec<NaiveDate> = Seen::find()
+            .select_only()
+            .column(seen::Column::FinishedOn)
+            .distinct()
+            .filter(seen::Column::UserId.eq(user_id))
+            .filter(seen::Column::Progress.eq(100))
+            .filter(seen::Column::FinishedOn.is_not_null())
+            .order_by_asc(seen::Column::FinishedOn)
+            .into_tuple()
+            .all(&self.db)
+            .await?;
+        Ok(calculate_streaks(&dates, today))
+    }
+
+    async fn user_diary(&self, user_id: i32, month: u32, year: i32) -> Result<Vec<UserDiaryDay>> {
+        let Some(start) = NaiveDate::from_ymd_opt(year, month, 1) else {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "The month must be between 1 and 12".to_owned(),
+            ));
+        };
+        let end = start + Months::new(1);
+        let offset = self.user_by_id(user_id).await?.preferences.offset();
+        let as_utc = |d: NaiveDate| Utc.from_utc_datetime(&d.and_hms_opt(0, 0, 0).unwrap());
+        // a timezone can shift the logged date by at most a day either way
+        let (logged_from, logged_to) = (
+            as_utc(start - Duration::days(1)),
+            as_utc(end + Duration::days(1)),
+        );
+        let all_seen = Seen::find()
+            .filter(seen::Column::UserId.eq(user_id))
+            .filter(seen::Column::Progress.eq(100))
+            .filter(
+                Cond::any()
+                    .add(
+                        seen::Column::FinishedOn
+                            .gte(start)
+                            .and(seen::Column::FinishedOn.lt(end)),
+                    )
+                    .add(
+                        seen::Column::FinishedOn
+                            .is_null()
+                            .and(seen::Column::LastUpdatedOn.gte(logged_from))
+                            .and(seen::Column::LastUpdatedOn.lt(logged_to)),
+                    ),
+            )
+            .order_by_asc(seen::Column::LastUpdatedOn)
+            .find_also_related(Metadata)
+            .all(&self.db)
+            .await?;
+        let mut days: BTreeMap<NaiveDate, Vec<UserDiaryEntry>> = BTreeMap::new();
+        for (seen, meta) in all_seen {
+            let Some(meta) = meta else {
+                continue;
+            };
+            let date = diary_date(&seen, &offset);
+            if date < start || date >= end {
+                continue;
+            }
+            let image = match self.user_image_override(user_id, meta.id).await? {
+                Some(i) => Some(i),
+                None => self.metadata_images(&meta).await?.posters.get(0).cloned(),
+            };
+            days.entry(date).or_default().push(UserDiaryEntry {
+                seen_id: seen.id,
+                metadata_id: meta.id,
+                lot: meta.lot,
+                title: meta.title,
+                image,
+                note: seen.note,
+            });
+        }
+        Ok(days
+            .into_iter()
+            .map(|(date, entries)| UserDiaryDay { date, entries })
+            .collect())
+    }
+
+    async fn library_publish_years(
+        &self,
+        user_id: i32,
+        lot: Option<MetadataLot>,
+    ) -> Result<Vec<i32>> {
+        let years: Vec<i32> = Metadata::find()
+            .select_only()
+            .column(metadata::Column::PublishYear)
+            .distinct()
+            .filter(
+                metadata::Column::Id.in_subquery(
+                    Query::select()
+                        .column(user_to_metadata::Column::MetadataId)
+                        .from(UserToMetadata)
+                        .and_where(user_to_metadata::Column::UserId.eq(user_id))
+                        .to_owned(),
+                ),
+            )
+            .filter(metadata::Column::PublishYear.is_not_null())
+            .apply_if(lot, |query, v| query.filter(metadata::Column::Lot.eq(v)))
+            .order_by_desc(metadata::Column::PublishYear)
+            .into_tuple()
+            .all(&self.db)
+            .await?;
+        Ok(years)
+    }
+
+    async fn trending_media(
+        &self,
+        user_id: i32,
+        lot: Option<MetadataLot>,
+        window_days: Option<i64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<MediaSearchItem>> {
+        let window_days = window_days
+            .unwrap_or(TRENDING_MEDIA_DEFAULT_WINDOW_DAYS)
+            .clamp(1, TRENDING_MEDIA_MAX_WINDOW_DAYS);
+        let limit = limit
+            .unwrap_or(TRENDING_MEDIA_DEFAULT_LIMIT)
+            .min(TRENDING_MEDIA_MAX_LIMIT);
+        let seen_counts = Seen::find()
+            .select_only()
+            .column(seen::Column::MetadataId)
+            .column_as(
+                Expr::col((TempSeen::Table, TempSeen::Id)).count(),
+                "num_times_seen",
+            )
+            .inner_join(Metadata)
+            .filter(seen::Column::LastUpdatedOn.gte(Utc::now() - Duration::days(window_days)))
+            .apply_if(lot, |query, v| query.filter(metadata::Column::Lot.eq(v)))
+            .group_by(seen::Column::MetadataId)
+            .order_by_desc(Expr::col((TempSeen::Table, TempSeen::Id)).count())
+            .limit(limit)
+            .into_tuple::<(i32, i64)>()
+            .all(&self.db)
+            .await?;
+        let mut metas: HashMap<i32, metadata::Model> = Metadata::find()
+            .filter(metadata::Column::Id.is_in(seen_counts.iter().map(|(id, _)| *id)))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+        let mut items = vec![];
+        for (metadata_id, _) in seen_counts {
+            let Some(meta) = metas.remove(&metadata_id) else {
+                continue;
+            };
+            let poster_images = self.metadata_images(&meta).await?.posters;
+            let image = match self.user_image_override(user_id, meta.id).await? {
+                Some(i) => Some(i),
+                None => poster_images.get(0).cloned(),
+            };
+            items.push(MediaSearchItem {
+                identifier: meta.id.to_string(),
+                lot: meta.lot,
+                title: meta.title,
+                image,
+                publish_year: meta.publish_year,
+            });
+        }
+        Ok(items)
+    }
+
+    async fn resume_media(
+        &self,
+        user_id: i32,
+        limit: Option<usize>,
+    ) -> Result<Vec<ResumeMediaItem>> {
+        let limit = limit
+            .unwrap_or(RESUME_MEDIA_DEFAULT_LIMIT)
+            .min(RESUME_MEDIA_MAX_LIMIT);
+        let all_seen = Seen::find()
+            .filter(seen::Column::UserId.eq(user_id))
+            .filter(seen::Column::Progress.lt(100))
+            .filter(seen::Column::Dropped.eq(false))
+            .order_by_desc(seen::Column::LastUpdatedOn)
+            .find_also_related(Metadata)
+            .all(&self.db)
+            .await?;
+        let mut resp = vec![];
+        // only the most recent entry of each media is used
+        for (seen, meta) in all_seen.into_iter().unique_by(|(s, _)| s.metadata_id) {
+            let Some(meta) = meta else {
+                continue;
+            };
+            if resp.len() == limit {
+                break;
+            }
+            let mut position = resume_position(&meta.specifics, seen.progress);
+            match seen.extra_information {
+                Some(SeenExtraInformation::Show(s)) => {
+                    position.season = Some(s.season);
+                    position.episode = Some(s.episode);
+                }
+                Some(SeenExtraInformation::Podcast(p)) => {
+                    position.episode = Some(p.episode);
+                }
+                None => {}
+            }
+            let poster_images = self.metadata_images(&meta).await?.posters;
+            let image = match self.user_image_override(user_id, meta.id).await? {
+                Some(i) => Some(i),
+                None => poster_images.get(0).cloned(),
+            };
+            resp.push(ResumeMediaItem {
+                media: MediaSearchItem {
+                    identifier: meta.id.to_string(),
+                    lot: meta.lot,
+                    title: meta.title,
+                    image,
+                    publish_year: meta.publish_year,
+                },
+                progress: seen.progress,
+                last_updated_on: seen.last_updated_on,
+                position,
+            });
+        }
+        Ok(resp)
+    }
+
+    async fn library_counts(&self, user_id: i32) -> Result<Vec<LibraryLotCount>> {
+        let counts: HashMap<String, i64> = Metadata::find()
+            .select_only()
+            .column(metadata::Column::Lot)
+            .column_as(Expr::col(metadata::Column::Id).count(), "num_media")
+            .filter(
+                metadata::Column::Id.in_subquery(
+                    Query::select()
+                        .column(user_to_metadata::Column::MetadataId)
+                        .from(UserToMetadata)
+                        .and_where(user_to_metadata::Column::UserId.eq(user_id))
+                        .to_owned(),
+                ),
+            )
+            .group_by(metadata::Column::Lot)
+            .into_tuple::<(String, i64)>()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .collect();
+        Ok(MetadataLot::iter()
+            .map(|lot| LibraryLotCount {
+                lot,
+                count: counts.get(&lot.to_value()).copied().unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    async fn uncollected_media(
+        &self,
+        user_id: i32,
+        lot: Option<MetadataLot>,
+        page: i32,
+    ) -> Result<SearchResults<MediaSearchItem>> {
+        let query = Metadata::find()
+            .filter(
+                metadata::Column::Id.in_subquery(
+                    Query::select()
+                        .column(user_to_metadata::Column::MetadataId)
+                        .from(UserToMetadata)
+                        .and_where(user_to_metadata::Column::UserId.eq(user_id))
+                        .to_owned(),
+                ),
+            )
+            .filter(
+                metadata::Column::Id.not_in_subquery(
+                    Query::select()
+                        .column((
+                            MetadataToCollection,
+                            metadata_to_collection::Column::MetadataId,
+                        ))
+                        .from(MetadataToCollection)
+                        .inner_join(
+                            Collection,
+                            Expr::col((Collection, collection::Column::Id)).equals((
+                                MetadataToCollection,
+                                metadata_to_collection::Column::CollectionId,
+                            )),
+                        )
+                        .and_where(Expr::col((Collection, collection::Column::UserId)).eq(user_id))
+                        .to_owned(),
+                ),
+            )
+            .apply_if(lot, |query, v| query.filter(metadata::Column::Lot.eq(v)));
+        let total: i32 = query.clone().count(&self.db).await?.try_into().unwrap();
+        let metas = query
+            .order_by_asc(metadata::Column::Title)
+            .limit(PAGE_LIMIT as u64)
+            .offset(((page - 1) * PAGE_LIMIT) as u64)
+            .all(&self.db)
+            .await?;
+        let mut items = vec![];
+        for m in metas {
+            let poster_images = self.metadata_images(&m).await?.posters;
+            let image = match self.user_image_override(user_id, m.id).await? {
+                Some(i) => Some(i),
+                None => poster_images.get(0).cloned(),
+            };
+            items.push(MediaSearchItem {
+                identifier: m.id.to_string(),
+                lot: m.lot,
+                title: m.title,
+                image,
+                publish_year: m.publish_year,
+            });
+        }
+        let next_page = if total - (page * PAGE_LIMIT) > 0 {
+            Some(page + 1)
+        } else {
+            None
+        };
+        Ok(SearchResults {
+            total,
+            items,
+            next_page,
+        })
+    }
+
     async fn media_list(
         &self,
         user_id: i32,
         input: MediaListInput,
+        include_backdrop: bool,
     ) -> Result<SearchResults<MediaListItem>> {
         let meta = UserToMetadata::find()
             .filter(user_to_metadata::Column::UserId.eq(user_id))
@@ -1244,139 +2749,174 @@ impl MiscellaneousService {
                         .add(get_contains_expr(metadata::Column::Creators)),
                 )
                 .to_owned();
+            for (col, field) in SEARCH_MATCH_FIELDS {
+                main_select = main_select
+                    .expr_as(
+                        Expr::case(get_contains_expr(col), 1).finally(0),
+                        Alias::new(&format!("matched_{}", field)),
+                    )
+                    .to_owned();
+            }
         };
 
-        let order_by = input
-            .sort
-            .as_ref()
-            .map(|a| Order::from(a.order))
-            .unwrap_or(Order::Asc);
-
-        match input.sort {
+        let sort = match input.sort {
+            Some(s) => s,
             None => {
+                let preferences = self.user_by_id(user_id).await?.preferences;
+                MediaSortInput {
+                    by: preferences.media_list_sort.by,
+                    order: preferences.media_list_sort.order,
+                }
+            }
+        };
+        let order_by = Order::from(sort.order);
+
+        match sort.by {
+            MediaSortBy::Title => {
                 main_select = main_select
                     .order_by((metadata_alias.clone(), metadata::Column::Title), order_by)
                     .to_owned();
             }
-            Some(s) => {
-                match s.by {
-                    MediaSortBy::Title => {
-                        main_select = main_select
-                            .order_by((metadata_alias.clone(), metadata::Column::Title), order_by)
-                            .to_owned();
-                    }
-                    MediaSortBy::ReleaseDate => {
-                        main_select = main_select
-                            .order_by_with_nulls(
-                                (metadata_alias.clone(), metadata::Column::PublishYear),
-                                order_by,
-                                NullOrdering::Last,
-                            )
-                            .to_owned();
-                    }
-                    MediaSortBy::LastSeen => {
-                        let last_seen = Alias::new("last_seen");
-                        let sub_select = Query::select()
-                            .column(TempSeen::MetadataId)
-                            .expr_as(
-                                Func::max(Expr::col(TempSeen::FinishedOn)),
-                                last_seen.clone(),
-                            )
-                            .from(TempSeen::Table)
-                            .and_where(Expr::col(TempSeen::UserId).eq(user_id))
-                            .group_by_col(TempSeen::MetadataId)
-                            .to_owned();
-                        main_select = main_select
-                            .join_subquery(
-                                JoinType::LeftJoin,
-                                sub_select,
-                                seen_alias.clone(),
-                                Expr::col((metadata_alias.clone(), TempMetadata::Id))
-                                    .equals((seen_alias.clone(), TempSeen::MetadataId)),
-                            )
-                            .order_by_with_nulls(
-                                (seen_alias.clone(), last_seen),
-                                order_by,
-                                NullOrdering::Last,
-                            )
-                            .to_owned();
-                    }
-                    MediaSortBy::LastUpdated => {
-                        main_select = main_select
-                            .join_as(
-                                JoinType::LeftJoin,
-                                TempUserToMetadata::Table,
-                                mtu_alias.clone(),
-                                Expr::col((metadata_alias.clone(), TempMetadata::Id))
-                                    .equals((mtu_alias.clone(), TempUserToMetadata::MetadataId))
-                                    .and(
-                                        Expr::col((mtu_alias.clone(), TempUserToMetadata::UserId))
-                                            .eq(user_id),
-                                    ),
-                            )
-                            .order_by(
-                                (mtu_alias.clone(), TempUserToMetadata::LastUpdatedOn),
-                                order_by,
-                            )
-                            .to_owned();
-                    }
-                    MediaSortBy::Rating => {
-                        let alias_name = "average_rating";
-                        main_select = main_select
-                            .expr_as(
-                                Func::avg(Expr::col((review_alias.clone(), TempReview::Rating))),
-                                Alias::new(alias_name),
-                            )
-                            .join_as(
-                                JoinType::LeftJoin,
-                                TempReview::Table,
-                                review_alias.clone(),
-                                Expr::col((metadata_alias.clone(), TempMetadata::Id))
-                                    .equals((review_alias.clone(), TempReview::MetadataId))
-                                    .and(
-                                        Expr::col((review_alias.clone(), TempReview::UserId))
-                                            .eq(user_id),
-                                    ),
-                            )
-                            .group_by_col((metadata_alias.clone(), TempMetadata::Id))
-                            .order_by_expr_with_nulls(
-                                Expr::cust(alias_name),
-                                order_by,
-                                NullOrdering::Last,
-                            )
-                            .to_owned();
-                    }
-                };
+            MediaSortBy::ReleaseDate => {
+                main_select = main_select
+                    .order_by_with_nulls(
+                        (metadata_alias.clone(), metadata::Column::PublishYear),
+                        order_by,
+                        NullOrdering::Last,
+                    )
+                    .to_owned();
             }
-        };
-
-        if let Some(f) = input.filter {
-            if let Some(s) = f.collection {
-                let all_media = MetadataToCollection::find()
-                    .filter(metadata_to_collection::Column::CollectionId.eq(s))
-                    .all(&self.db)
-                    .await?;
-                let collections = all_media
-                    .into_iter()
-                    .map(|m| m.metadata_id)
-                    .collect::<Vec<_>>();
+            MediaSortBy::LastSeen => {
+                let last_seen = Alias::new("last_seen");
+                let sub_select = Query::select()
+                    .column(TempSeen::MetadataId)
+                    .expr_as(
+                        Func::max(Expr::col(TempSeen::FinishedOn)),
+                        last_seen.clone(),
+                    )
+                    .from(TempSeen::Table)
+                    .and_where(Expr::col(TempSeen::UserId).eq(user_id))
+                    .group_by_col(TempSeen::MetadataId)
+                    .to_owned();
                 main_select = main_select
-                    .and_where(
-                        Expr::col((metadata_alias.clone(), TempMetadata::Id)).is_in(collections),
+                    .join_subquery(
+                        JoinType::LeftJoin,
+                        sub_select,
+                        seen_alias.clone(),
+                        Expr::col((metadata_alias.clone(), TempMetadata::Id))
+                            .equals((seen_alias.clone(), TempSeen::MetadataId)),
+                    )
+                    .order_by_with_nulls(
+                        (seen_alias.clone(), last_seen),
+                        order_by,
+                        NullOrdering::Last,
                     )
                     .to_owned();
             }
-            if let Some(s) = f.general {
-                let reviews = if matches!(s, MediaGeneralFilter::All) {
-                    vec![]
-                } else {
-                    Review::find()
-                        .filter(review::Column::UserId.eq(user_id))
-                        .all(&self.db)
-                        .await?
-                        .into_iter()
-                        .map(|r| r.metadata_id)
-                        .collect::<Vec<_>>()
+            MediaSortBy::LastUpdated => {
+                main_select = main_select
+                    .join_as(
+                        JoinType::LeftJoin,
+                        TempUserToMetadata::Table,
+                        mtu_alias.clone(),
+                        Expr::col((metadata_alias.clone(), TempMetadata::Id))
+                            .equals((mtu_alias.clone(), TempUserToMetadata::MetadataId))
+                            .and(
+                                Expr::col((mtu_alias.clone(), TempUserToMetadata::UserId))
+                                    .eq(user_id),
+                            ),
+                    )
+                    .order_by(
+                        (mtu_alias.clone(), TempUserToMetadata::LastUpdatedOn),
+                        order_by,
+                    )
+                    .to_owned();
+            }
+            MediaSortBy::Rating => {
+                let alias_name = "average_rating";
+                main_select = main_select
+                    .expr_as(
+                        Func::avg(Expr::col((review_alias.clone(), TempReview::Rating))),
+                        Alias::new(alias_name),
+                    )
+                    .join_as(
+                        JoinType::LeftJoin,
+                        TempReview::Table,
+                        review_alias.clone(),
+                        Expr::col((metadata_alias.clone(), TempMetadata::Id))
+                            .equals((review_alias.clone(), TempReview::MetadataId))
+                            .and(Expr::col((review_alias.clone(), TempReview::UserId)).eq(user_id)),
+                    )
+                    .group_by_col((metadata_alias.clone(), TempMetadata::Id))
+                    .order_by_expr_with_nulls(Expr::cust(alias_name), order_by, NullOrdering::Last)
+                    .to_owned();
+            }
+            MediaSortBy::TimesSeen => {
+                sort_by_times_seen(
+                    &mut main_select,
+                    &metadata_alias,
+                    &seen_alias,
+                    user_id,
+                    order_by,
+                );
+            }
+            MediaSortBy::Runtime => {
+                sort_by_runtime(
+                    &mut main_select,
+                    &metadata_alias,
+                    self.db.get_database_backend(),
+                    order_by,
+                );
+            }
+        };
+
+        // ties are broken by the id so that the order is the same across pages
+        main_select = main_select
+            .order_by((metadata_alias.clone(), TempMetadata::Id), Order::Asc)
+            .to_owned();
+        if sort.by == MediaSortBy::LastUpdated {
+            main_select = main_select
+                .expr_as(
+                    Expr::col((mtu_alias.clone(), TempUserToMetadata::LastUpdatedOn)),
+                    Alias::new("cursor_last_updated_on"),
+                )
+                .to_owned();
+        }
+
+        if let Some(f) = input.filter {
+            if let Some(s) = f.collection {
+                let all_media = MetadataToCollection::find()
+                    .filter(metadata_to_collection::Column::CollectionId.eq(s))
+                    .all(&self.db)
+                    .await?;
+                let collections = all_media
+                    .into_iter()
+                    .map(|m| m.metadata_id)
+                    .collect::<Vec<_>>();
+                main_select = main_select
+                    .and_where(
+                        Expr::col((metadata_alias.clone(), TempMetadata::Id)).is_in(collections),
+                    )
+                    .to_owned();
+            }
+            if let Some(l) = f.original_language {
+                main_select = main_select
+                    .and_where(
+                        Expr::col((metadata_alias.clone(), TempMetadata::OriginalLanguage)).eq(l),
+                    )
+                    .to_owned();
+            }
+            if let Some(s) = f.general {
+                let reviews = if matches!(s, MediaGeneralFilter::All) {
+                    vec![]
+                } else {
+                    Review::find()
+                        .filter(review::Column::UserId.eq(user_id))
+                        .all(&self.db)
+                        .await?
+                        .into_iter()
+                        .map(|r| r.metadata_id)
+                        .collect::<Vec<_>>()
                 };
                 match s {
                     MediaGeneralFilter::All => {}
@@ -1412,7 +2952,7 @@ impl MiscellaneousService {
                             )
                             .to_owned();
                     }
-                    MediaGeneralFilter::Finished => {
+                    MediaGeneralFilter::Finished | MediaGeneralFilter::NotFinished => {
                         let finished_ids = Seen::find()
                             .filter(seen::Column::UserId.eq(user_id))
                             .filter(seen::Column::Progress.eq(100))
@@ -1421,26 +2961,23 @@ impl MiscellaneousService {
                             .into_iter()
                             .map(|r| r.metadata_id)
                             .collect::<Vec<_>>();
-                        main_select = main_select
-                            .and_where(
-                                Expr::col((metadata_alias.clone(), TempMetadata::Id))
-                                    .is_in(finished_ids),
-                            )
-                            .to_owned();
+                        let id_col = Expr::col((metadata_alias.clone(), TempMetadata::Id));
+                        let condition = if s == MediaGeneralFilter::Finished {
+                            id_col.is_in(finished_ids)
+                        } else {
+                            id_col.is_not_in(finished_ids)
+                        };
+                        main_select = main_select.and_where(condition).to_owned();
                     }
                     MediaGeneralFilter::Unseen => {
-                        main_select = main_select
-                            .join_as(
-                                JoinType::LeftJoin,
-                                TempReview::Table,
-                                review_alias.clone(),
-                                Expr::col((metadata_alias.clone(), TempMetadata::Id))
-                                    .equals((seen_alias.clone(), TempSeen::MetadataId)),
-                            )
-                            .and_where(
-                                Expr::col((seen_alias.clone(), TempSeen::MetadataId)).is_null(),
-                            )
-                            .to_owned();
+                        // `seen_alias` might already be joined when sorting by last seen
+                        let unseen_alias = Alias::new("us");
+                        filter_unseen_media(
+                            &mut main_select,
+                            &metadata_alias,
+                            &unseen_alias,
+                            user_id,
+                        );
                     }
                 };
             }
@@ -1468,20 +3005,60 @@ impl MiscellaneousService {
             .unwrap();
         let total: i32 = total.try_into().unwrap();
 
-        let main_select = main_select
-            .limit(PAGE_LIMIT as u64)
-            .offset(((input.page - 1) * PAGE_LIMIT) as u64)
-            .to_owned();
+        let mut main_select = main_select.limit(PAGE_LIMIT as u64).to_owned();
+        if let Some(after) = &input.after {
+            let cursor = MediaListCursor::decode(after)?;
+            // one more item is fetched to know whether there is a next page
+            main_select = main_select
+                .cond_where(media_list_keyset_condition(
+                    &cursor,
+                    sort.by,
+                    sort.order,
+                    &metadata_alias,
+                    &mtu_alias,
+                )?)
+                .limit(PAGE_LIMIT as u64 + 1)
+                .to_owned();
+        } else {
+            main_select = main_select
+                .offset(((input.page - 1) * PAGE_LIMIT) as u64)
+                .to_owned();
+        }
         let stmt = self.get_db_stmt(main_select);
-        let metas: Vec<InnerMediaSearchItem> = self
+        let metas = self
             .db
             .query_all(stmt)
             .await?
             .into_iter()
-            .map(|qr| InnerMediaSearchItem::from_query_result(&qr, "").unwrap())
-            .collect();
+            .map(|qr| {
+                let matched_fields = SEARCH_MATCH_FIELDS
+                    .into_iter()
+                    .filter(|(_, field)| {
+                        qr.try_get::<i32>("", &format!("matched_{}", field))
+                            .unwrap_or_default()
+                            == 1
+                    })
+                    .map(|(_, field)| field.to_owned())
+                    .collect::<Vec<_>>();
+                let m = InnerMediaSearchItem::from_query_result(&qr, "").unwrap();
+                let key = match sort.by {
+                    MediaSortBy::Title => Some(MediaListCursorKey::Title(m.title.clone())),
+                    MediaSortBy::ReleaseDate => {
+                        Some(MediaListCursorKey::ReleaseDate(m.publish_year))
+                    }
+                    MediaSortBy::LastUpdated => qr
+                        .try_get::<DateTimeUtc>("", "cursor_last_updated_on")
+                        .ok()
+                        .map(MediaListCursorKey::LastUpdated),
+                    _ => None,
+                };
+                let cursor = key.map(|key| MediaListCursor { key, id: m.id }.encode());
+                (m, matched_fields, cursor)
+            })
+            .collect::<Vec<_>>();
+        let has_more = metas.len() > PAGE_LIMIT as usize;
         let mut items = vec![];
-        for m in metas {
+        for (m, matched_fields, cursor) in metas.into_iter().take(PAGE_LIMIT as usize) {
             let avg_select = Query::select()
                 .expr(Func::avg(Expr::col((
                     TempReview::Table,
@@ -1501,26 +3078,40 @@ impl MiscellaneousService {
                 .await?
                 .map(|qr| qr.try_get_by_index::<Decimal>(0).ok())
                 .unwrap();
-            let images = serde_json::from_value(m.images).unwrap();
-            let (poster_images, _) = self
+            let mut images: MetadataImages = serde_json::from_value(m.images).unwrap();
+            if !include_backdrop {
+                images.0.retain(|i| i.lot == MetadataImageLot::Poster);
+            }
+            let images = self
                 .metadata_images(&metadata::Model {
                     images,
                     ..Default::default()
                 })
                 .await?;
+            let image = match self.user_image_override(user_id, m.id).await? {
+                Some(i) => Some(i),
+                None => images.posters.get(0).cloned(),
+            };
             let m_small = MediaListItem {
                 data: MediaSearchItem {
                     identifier: m.id.to_string(),
                     lot: m.lot,
                     title: m.title,
-                    image: poster_images.get(0).cloned(),
+                    image,
                     publish_year: m.publish_year,
                 },
                 average_rating: avg,
+                backdrop_image: images.backdrops.first().cloned(),
+                matched_fields,
+                cursor,
             };
             items.push(m_small);
         }
-        let next_page = if total - ((input.page) * PAGE_LIMIT) > 0 {
+        let next_page = if input.after.is_some() {
+            // the position of a cursor in the list is not known, so this only
+            // tells whether there are more items
+            has_more.then_some(input.page + 1)
+        } else if total - ((input.page) * PAGE_LIMIT) > 0 {
             Some(input.page + 1)
         } else {
             None
@@ -1536,149 +3127,44 @@ impl MiscellaneousService {
         &self,
         input: ProgressUpdateInput,
         user_id: i32,
-    ) -> Result<IdObject> {
-        let prev_seen = Seen::find()
-            .filter(seen::Column::Progress.lt(100))
-            .filter(seen::Column::UserId.eq(user_id))
-            .filter(seen::Column::Dropped.ne(true))
-            .filter(seen::Column::MetadataId.eq(i32::from(input.metadata_id)))
-            .order_by_desc(seen::Column::LastUpdatedOn)
-            .all(&self.db)
-            .await
-            .unwrap();
-        #[derive(Debug, Serialize, Deserialize, Enum, Clone, PartialEq, Eq, Copy)]
-        pub enum ProgressUpdateAction {
-            Update,
-            Now,
-            InThePast,
-            JustStarted,
-            Drop,
-        }
-        let action = match input.progress {
-            None => ProgressUpdateAction::Drop,
-            Some(p) => {
-                if p == 100 {
-                    match input.date {
-                        None => ProgressUpdateAction::InThePast,
-                        Some(u) => {
-                            if Utc::now().date_naive() == u {
-                                if prev_seen.is_empty() {
-                                    ProgressUpdateAction::Now
-                                } else {
-                                    ProgressUpdateAction::Update
-                                }
-                            } else {
-                                ProgressUpdateAction::InThePast
-                            }
-                        }
-                    }
-                } else if prev_seen.is_empty() {
-                    ProgressUpdateAction::JustStarted
-                } else {
-                    ProgressUpdateAction::Update
-                }
+    ) -> Result<seen::Model> {
+        let today = self.user_by_id(user_id).await?.preferences.today();
+        let (seen_item, updated) = apply_progress_update(&self.db, input, user_id, today).await?;
+        if updated {
+            self.deploy_after_media_seen_job(seen_item.clone()).await?;
+        }
+        let mut seen_items = vec![seen_item];
+        modify_seen_elements(&mut seen_items);
+        Ok(seen_items.remove(0))
+    }
+
+    async fn undrop_media(&self, metadata_id: i32, user_id: i32) -> Result<seen::Model> {
+        let seen_item = match undrop_latest_seen(&self.db, user_id, metadata_id).await? {
+            Some(s) => s,
+            None => {
+                return Err(coded_error(
+                    ErrorCode::NotFound,
+                    "There is no dropped `seen` item for this media".to_owned(),
+                ))
             }
         };
-        let meta = Seen::find()
-            .filter(seen::Column::Identifier.eq(input.identifier.clone()))
-            .one(&self.db)
-            .await
-            .unwrap();
-        if let Some(m) = meta {
-            Ok(IdObject { id: m.metadata_id })
-        } else {
-            let err = || Err(Error::new("There is no `seen` item underway".to_owned()));
-            let seen_item = match action {
-                ProgressUpdateAction::Update => {
-                    let progress = input.progress.unwrap();
-                    let mut last_seen: seen::ActiveModel = prev_seen[0].clone().into();
-                    last_seen.progress = ActiveValue::Set(progress);
-                    last_seen.last_updated_on = ActiveValue::Set(Utc::now());
-                    if progress == 100 {
-                        last_seen.finished_on = ActiveValue::Set(Some(Utc::now().date_naive()));
-                    }
-                    last_seen.update(&self.db).await.unwrap()
-                }
-                ProgressUpdateAction::Drop => {
-                    let last_seen = Seen::find()
-                        .filter(seen::Column::UserId.eq(user_id))
-                        .filter(seen::Column::Dropped.ne(true))
-                        .filter(seen::Column::MetadataId.eq(i32::from(input.metadata_id)))
-                        .order_by_desc(seen::Column::LastUpdatedOn)
-                        .one(&self.db)
-                        .await
-                        .unwrap();
-                    match last_seen {
-                        Some(ls) => {
-                            let mut last_seen: seen::ActiveModel = ls.into();
-                            last_seen.dropped = ActiveValue::Set(true);
-                            last_seen.last_updated_on = ActiveValue::Set(Utc::now());
-                            last_seen.update(&self.db).await.unwrap()
-                        }
-                        None => {
-                            return err();
-                        }
-                    }
-                }
-                ProgressUpdateAction::Now
-                | ProgressUpdateAction::InThePast
-                | ProgressUpdateAction::JustStarted => {
-                    let meta = Metadata::find_by_id(input.metadata_id)
-                        .one(&self.db)
-                        .await
-                        .unwrap()
-                        .unwrap();
-                    let finished_on = if action == ProgressUpdateAction::JustStarted {
-                        None
-                    } else {
-                        input.date
-                    };
-                    let (progress, started_on) =
-                        if matches!(action, ProgressUpdateAction::JustStarted) {
-                            (0, Some(Utc::now().date_naive()))
-                        } else {
-                            (100, None)
-                        };
-                    let mut seen_insert = seen::ActiveModel {
-                        progress: ActiveValue::Set(progress),
-                        user_id: ActiveValue::Set(user_id),
-                        metadata_id: ActiveValue::Set(i32::from(input.metadata_id)),
-                        started_on: ActiveValue::Set(started_on),
-                        finished_on: ActiveValue::Set(finished_on),
-                        last_updated_on: ActiveValue::Set(Utc::now()),
-                        identifier: ActiveValue::Set(input.identifier),
-                        ..Default::default()
-                    };
-                    if meta.lot == MetadataLot::Show {
-                        seen_insert.extra_information = ActiveValue::Set(Some(
-                            SeenExtraInformation::Show(SeenShowExtraInformation {
-                                season: input.show_season_number.unwrap(),
-                                episode: input.show_episode_number.unwrap(),
-                            }),
-                        ));
-                    } else if meta.lot == MetadataLot::Podcast {
-                        seen_insert.extra_information = ActiveValue::Set(Some(
-                            SeenExtraInformation::Podcast(SeenPodcastExtraInformation {
-                                episode: input.podcast_episode_number.unwrap(),
-                            }),
-                        ))
-                    }
+        self.deploy_after_media_seen_job(seen_item.clone()).await?;
+        let mut seen_items = vec![seen_item];
+        modify_seen_elements(&mut seen_items);
+        Ok(seen_items.remove(0))
+    }
 
-                    seen_insert.insert(&self.db).await.unwrap()
-                }
-            };
-            let id = seen_item.id;
-            let metadata = self.generic_metadata(input.metadata_id).await?;
-            let mut storage = self.after_media_seen.clone();
-            storage
-                .push(AfterMediaSeenJob {
-                    seen: seen_item,
-                    metadata_lot: metadata.model.lot,
-                })
-                .await
-                .ok();
-            Ok(IdObject { id })
-        }
+    async fn deploy_after_media_seen_job(&self, seen: seen::Model) -> Result<()> {
+        let metadata = self.generic_metadata(seen.metadata_id).await?;
+        let mut storage = self.after_media_seen.clone();
+        storage
+            .push(AfterMediaSeenJob {
+                seen,
+                metadata_lot: metadata.model.lot,
+            })
+            .await
+            .ok();
+        Ok(())
     }
 
     pub async fn deploy_recalculate_summary_job(&self, user_id: i32) -> Result<()> {
@@ -1687,8 +3173,11 @@ impl MiscellaneousService {
         Ok(())
     }
 
-    pub async fn cleanup_user_and_metadata_association(&self) -> Result<()> {
+    /// Remove the associations between users and media that are no longer
+    /// needed, returning the number of rows removed.
+    pub async fn cleanup_user_and_metadata_association(&self) -> Result<usize> {
         let user_to_metadatas = UserToMetadata::find().all(&self.db).await.unwrap();
+        let mut removed = 0;
         for u in user_to_metadatas {
             // check if there is any seen item
             let seen_count = Seen::find()
@@ -1722,15 +3211,17 @@ impl MiscellaneousService {
                 .await
                 .unwrap();
             let is_in_collection = meta_ids.contains(&u.metadata_id);
-            if seen_count + reviewed_count == 0 && !is_in_collection {
+            if seen_count + reviewed_count == 0 && !is_in_collection && u.image_override.is_none() {
                 tracing::debug!(
                     "Removing user_to_metadata = {id:?}",
                     id = (u.user_id, u.metadata_id)
                 );
-                u.delete(&self.db).await.ok();
+                if u.delete(&self.db).await.is_ok() {
+                    removed += 1;
+                }
             }
         }
-        Ok(())
+        Ok(removed)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -1789,6 +3280,7 @@ impl MiscellaneousService {
     }
 
     pub async fn commit_media_internal(&self, details: MediaDetails) -> Result<IdObject> {
+        let content_hash = media_details_hash(&details);
         let metadata = metadata::ActiveModel {
             lot: ActiveValue::Set(details.lot),
             source: ActiveValue::Set(details.source),
@@ -1800,6 +3292,11 @@ impl MiscellaneousService {
             identifier: ActiveValue::Set(details.identifier),
             creators: ActiveValue::Set(MetadataCreators(details.creators)),
             specifics: ActiveValue::Set(details.specifics),
+            content_hash: ActiveValue::Set(Some(content_hash)),
+            provider_rating: ActiveValue::Set(details.provider_rating),
+            original_language: ActiveValue::Set(details.original_language),
+            franchise: ActiveValue::Set(details.franchise),
+            slug: ActiveValue::Set(details.slug),
             ..Default::default()
         };
         let metadata = metadata.insert(&self.db).await.unwrap();
@@ -1811,19 +3308,23 @@ impl MiscellaneousService {
         Ok(IdObject { id: metadata.id })
     }
 
-    pub async fn cleanup_metadata_with_associated_user_activities(&self) -> Result<()> {
+    /// Remove the media that are not associated with any user, returning the
+    /// number of rows removed.
+    pub async fn cleanup_metadata_with_associated_user_activities(&self) -> Result<usize> {
         let all_metadata = Metadata::find().all(&self.db).await.unwrap();
+        let mut removed = 0;
         for metadata in all_metadata {
             let num_associations = UserToMetadata::find()
                 .filter(user_to_metadata::Column::MetadataId.eq(metadata.id))
                 .count(&self.db)
                 .await
                 .unwrap();
-            if num_associations == 0 {
-                metadata.delete(&self.db).await.ok();
+            if num_associations == 0 && delete_metadata(&self.db, metadata.id).await.is_ok() {
+                self.delete_metadata_images(&metadata).await;
+                removed += 1;
             }
         }
-        Ok(())
+        Ok(removed)
     }
 
     pub async fn deploy_update_metadata_job(&self, metadata_id: i32) -> Result<String> {
@@ -1853,6 +3354,7 @@ impl MiscellaneousService {
             new_seen.insert(&self.db).await?;
             old_seen.delete(&self.db).await?;
         }
+        self.recalculate_popularity(merge_into).await?;
         for old_review in Review::find()
             .filter(review::Column::MetadataId.eq(merge_from))
             .all(&self.db)
@@ -1863,15 +3365,75 @@ impl MiscellaneousService {
             let new_review = review::ActiveModel {
                 id: ActiveValue::NotSet,
                 metadata_id: ActiveValue::Set(merge_into),
+                // the target might already have a pinned review by this user
+                pinned: ActiveValue::Set(false),
                 ..old_review_active
             };
             new_review.insert(&self.db).await?;
             old_review.delete(&self.db).await?;
         }
-        Metadata::delete_by_id(merge_from).exec(&self.db).await?;
+        let old_metadata = Metadata::find_by_id(merge_from).one(&self.db).await?;
+        delete_metadata(&self.db, merge_from).await?;
+        if let Some(m) = old_metadata {
+            self.delete_metadata_images(&m).await;
+        }
         Ok(true)
     }
 
+    /// Store the number of `seen` items of a media item on it.
+    pub async fn recalculate_popularity(&self, metadata_id: i32) -> Result<()> {
+        let popularity: i32 = Seen::find()
+            .filter(seen::Column::MetadataId.eq(metadata_id))
+            .count(&self.db)
+            .await?
+            .try_into()
+            .unwrap();
+        Metadata::update_many()
+            .col_expr(metadata::Column::Popularity, Expr::value(popularity))
+            .filter(metadata::Column::Id.eq(metadata_id))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// Recalculate the popularity of all media items, returning the number of
+    /// media items that have been seen at least once.
+    async fn recompute_popularity(&self) -> Result<usize> {
+        let seen_counts = Seen::find()
+            .select_only()
+            .column(seen::Column::MetadataId)
+            .column_as(Expr::col(seen::Column::Id).count(), "num_times_seen")
+            .group_by(seen::Column::MetadataId)
+            .into_tuple::<(i32, i64)>()
+            .all(&self.db)
+            .await?;
+        let txn = self.db.begin().await?;
+        Metadata::update_many()
+            .col_expr(metadata::Column::Popularity, Expr::value(0))
+            .exec(&txn)
+            .await?;
+        for (metadata_id, count) in seen_counts.iter() {
+            Metadata::update_many()
+                .col_expr(metadata::Column::Popularity, Expr::value(*count))
+                .filter(metadata::Column::Id.eq(*metadata_id))
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+        Ok(seen_counts.len())
+    }
+
+    /// Remove the images of a media item that were uploaded to file storage.
+    async fn delete_metadata_images(&self, meta: &metadata::Model) {
+        for image in meta.images.0.iter() {
+            if let MetadataImageUrl::S3(key) = &image.url {
+                if !self.file_storage.delete(key).await {
+                    tracing::warn!("Could not delete image with key = {key}");
+                }
+            }
+        }
+    }
+
     async fn user_preferences(&self, user_id: i32) -> Result<UserPreferences> {
         let mut prefs = self.user_by_id(user_id).await?.preferences;
         prefs.features_enabled.anime =
@@ -1893,6 +3455,31 @@ impl MiscellaneousService {
         Ok(prefs)
     }
 
+    async fn server_health(&self) -> ServerHealth {
+        let started_on = Utc::now();
+        let database = self
+            .db
+            .execute(Statement::from_string(
+                self.db.get_database_backend(),
+                "SELECT 1".to_owned(),
+            ))
+            .await
+            .is_ok();
+        let database_latency = database.then(|| (Utc::now() - started_on).num_milliseconds());
+        let file_storage_enabled = self.config.file_storage.is_enabled();
+        let file_storage_reachable = file_storage_enabled
+            && tokio::time::timeout(StdDuration::from_secs(5), self.file_storage.is_enabled())
+                .await
+                .unwrap_or_default();
+        ServerHealth {
+            database,
+            database_latency,
+            file_storage_enabled,
+            file_storage_reachable,
+            healthy: database && (!file_storage_enabled || file_storage_reachable),
+        }
+    }
+
     async fn core_enabled_features(&self) -> Result<GeneralFeatures> {
         let mut files_enabled = self.config.file_storage.is_enabled();
         if files_enabled && !self.file_storage.is_enabled().await {
@@ -1911,8 +3498,32 @@ impl MiscellaneousService {
         source: MetadataSource,
         input: SearchInput,
     ) -> Result<DetailedMediaSearchResults> {
-        let provider = self.get_provider(lot, source)?;
-        let results = provider.search(&input.query, input.page).await?;
+        if let Some(language) = &input.language {
+            validate_search_language(source, language)?;
+        }
+        let key = format!(
+            "{lot:?}-{source:?}-{query}-{page:?}-{year:?}-{language:?}",
+            query = input.query,
+            page = input.page,
+            year = input.year,
+            language = input.language
+        );
+        let results = match self.search_cache.get(&key) {
+            Some(results) => results,
+            None => {
+                let provider = self.get_provider(lot, source)?;
+                let results = provider
+                    .search(
+                        &input.query,
+                        input.page,
+                        input.year,
+                        input.language.as_deref(),
+                    )
+                    .await?;
+                self.search_cache.insert(key, results.clone());
+                results
+            }
+        };
         let mut all_idens = results
             .items
             .iter()
@@ -2005,6 +3616,46 @@ impl MiscellaneousService {
         Ok(results)
     }
 
+    async fn media_search_all_sources(
+        &self,
+        lot: MetadataLot,
+        input: SearchInput,
+    ) -> Result<AllSourcesMediaSearchResults> {
+        let sources = self.enabled_sources_for_lot(lot).await;
+        let results = join_all(
+            sources
+                .iter()
+                .map(|source| self.media_search(lot, *source, input.clone())),
+        )
+        .await;
+        let mut items = vec![];
+        let mut failed_sources = vec![];
+        let mut has_next_page = false;
+        for (source, result) in sources.into_iter().zip(results) {
+            match result {
+                Ok(r) => {
+                    has_next_page = has_next_page || r.next_page.is_some();
+                    items.extend(r.items.into_iter().map(|i| SourcedMediaSearchItem {
+                        source,
+                        item: i.item,
+                        database_id: i.database_id,
+                    }));
+                }
+                Err(e) => {
+                    tracing::error!("Unable to search {source:?}: {:?}", e.message);
+                    failed_sources.push(source);
+                }
+            }
+        }
+        Ok(AllSourcesMediaSearchResults {
+            items: dedup_search_results(items),
+            failed_sources,
+            next_page: has_next_page.then(|| input.page.unwrap_or(1) + 1),
+        })
+    }
+
+    /// Get the latest details of a media item from its provider, bypassing the
+    /// cache.
     async fn details_from_provider_for_existing_media(
         &self,
         metadata_id: i32,
@@ -2015,7 +3666,7 @@ impl MiscellaneousService {
             .unwrap()
             .unwrap();
         let results = self
-            .details_from_provider(metadata.lot, metadata.source, &metadata.identifier)
+            .fetch_details_from_provider(metadata.lot, metadata.source, &metadata.identifier)
             .await?;
         Ok(results)
     }
@@ -2039,7 +3690,10 @@ impl MiscellaneousService {
             },
             MetadataSource::Igdb => Box::new(self.igdb_service.clone()),
             MetadataSource::Custom => {
-                return Err(Error::new("This source is not supported".to_owned()));
+                return Err(coded_error(
+                    ErrorCode::Validation,
+                    "This source is not supported".to_owned(),
+                ));
             }
         };
         Ok(service)
@@ -2050,9 +3704,31 @@ impl MiscellaneousService {
         lot: MetadataLot,
         source: MetadataSource,
         identifier: &str,
+    ) -> Result<MediaDetails> {
+        let key = format!("{lot:?}-{source:?}-{identifier}");
+        if let Some(details) = self.details_cache.get(&key) {
+            return Ok(details);
+        }
+        self.fetch_details_from_provider(lot, source, identifier)
+            .await
+    }
+
+    async fn fetch_details_from_provider(
+        &self,
+        lot: MetadataLot,
+        source: MetadataSource,
+        identifier: &str,
     ) -> Result<MediaDetails> {
         let provider = self.get_provider(lot, source)?;
-        let results = provider.details(identifier).await?;
+        let results = provider.details(identifier).await.map_err(|e| {
+            if e.is::<MediaNotFoundError>() {
+                coded_error(ErrorCode::NotFound, e.to_string())
+            } else {
+                Error::new(e.to_string())
+            }
+        })?;
+        self.details_cache
+            .insert(format!("{lot:?}-{source:?}-{identifier}"), results.clone());
         Ok(results)
     }
 
@@ -2068,17 +3744,88 @@ impl MiscellaneousService {
         {
             Ok(m)
         } else {
-            let details = self.details_from_provider(lot, source, identifier).await?;
-            let media_id = self.commit_media_internal(details).await?;
-            Ok(media_id)
+            self.commit_media_from_provider(lot, source, identifier)
+                .await
+        }
+    }
+
+    /// Create a media item that is not in the database yet from its provider.
+    async fn commit_media_from_provider(
+        &self,
+        lot: MetadataLot,
+        source: MetadataSource,
+        identifier: &str,
+    ) -> Result<IdObject> {
+        self.get_provider(lot, source)?
+            .validate_identifier(identifier)?;
+        let details = self.details_from_provider(lot, source, identifier).await?;
+        self.commit_media_internal(details).await
+    }
+
+    async fn commit_media_search_results(
+        &self,
+        lot: MetadataLot,
+        source: MetadataSource,
+        input: SearchInput,
+    ) -> Result<Vec<CommittedMediaSearchItem>> {
+        // the search results are cached, and already carry the ids of the media
+        // that are in the database
+        let results = self.media_search(lot, source, input).await?;
+        let mut committed = vec![];
+        let mut remaining = MEDIA_SEARCH_COMMIT_LIMIT;
+        for result in results.items {
+            let identifier = result.item.identifier;
+            let outcome = match result.database_id {
+                Some(id) => Ok(id),
+                None if remaining == 0 => Err(format!(
+                    "Only {} media are committed per request",
+                    MEDIA_SEARCH_COMMIT_LIMIT
+                )),
+                None => {
+                    remaining -= 1;
+                    self.commit_media_from_provider(lot, source, &identifier)
+                        .await
+                        .map(|m| m.id)
+                        .map_err(|e| e.message)
+                }
+            };
+            committed.push(CommittedMediaSearchItem {
+                identifier,
+                database_id: outcome.as_ref().ok().copied(),
+                error: outcome.err(),
+            });
+        }
+        Ok(committed)
+    }
+
+    /// Check that a media item can be committed without writing anything to the
+    /// database.
+    pub async fn resolve_media(
+        &self,
+        lot: MetadataLot,
+        source: MetadataSource,
+        identifier: &str,
+    ) -> Result<()> {
+        if self
+            .media_exists_in_database(lot, source, identifier)
+            .await?
+            .is_none()
+        {
+            self.get_provider(lot, source)?
+                .validate_identifier(identifier)?;
+            self.details_from_provider(lot, source, identifier).await?;
         }
+        Ok(())
     }
 
     async fn review_by_id(&self, review_id: i32) -> Result<review::Model> {
         let review = Review::find_by_id(review_id).one(&self.db).await?;
         match review {
             Some(r) => Ok(r),
-            None => Err(Error::new("Unable to find review".to_owned())),
+            None => Err(coded_error(
+                ErrorCode::NotFound,
+                "Unable to find review".to_owned(),
+            )),
         }
     }
 
@@ -2086,54 +3833,101 @@ impl MiscellaneousService {
         &self,
         user_id: &i32,
         metadata_id: &i32,
-    ) -> Result<Vec<ReviewItem>> {
-        let all_reviews = Review::find()
-            .order_by_desc(review::Column::PostedOn)
+        page: i32,
+        filter: ReviewFilterInput,
+    ) -> Result<SearchResults<ReviewItem>> {
+        let query = Review::find()
             .filter(review::Column::MetadataId.eq(metadata_id.to_owned()))
+            .filter(
+                Cond::any()
+                    .add(review::Column::Visibility.ne(Visibility::Private))
+                    .add(review::Column::UserId.eq(*user_id)),
+            )
+            .apply_if(filter.min_rating, |query, v| {
+                query.filter(review::Column::Rating.gte(v))
+            })
+            .apply_if(filter.hide_spoilers.then_some(false), |query, v| {
+                query.filter(review::Column::Spoiler.eq(v))
+            });
+        let total: i32 = query.clone().count(&self.db).await?.try_into().unwrap();
+        let mut items = query
+            .order_by_desc(review::Column::Pinned)
+            .order_by_desc(review::Column::PostedOn)
             .find_also_related(User)
+            .limit(PAGE_LIMIT as u64)
+            .offset(((page - 1) * PAGE_LIMIT) as u64)
             .all(&self.db)
-            .await
-            .unwrap()
-            .into_iter()
-            .map(|(r, u)| {
-                let (show_se, show_ep, podcast_ep) = match r.extra_information {
-                    Some(s) => match s {
-                        SeenExtraInformation::Show(d) => (Some(d.season), Some(d.episode), None),
-                        SeenExtraInformation::Podcast(d) => (None, None, Some(d.episode)),
-                    },
-                    None => (None, None, None),
-                };
-                let user = u.unwrap();
-                ReviewItem {
-                    id: r.id,
-                    posted_on: r.posted_on,
-                    rating: r.rating,
-                    spoiler: r.spoiler,
-                    text: r.text,
-                    visibility: r.visibility,
-                    season_number: show_se,
-                    episode_number: show_ep,
-                    podcast_episode_id: podcast_ep,
-                    posted_by: ReviewPostedBy {
-                        id: user.id,
-                        name: user.name,
-                    },
-                }
-            })
-            .collect::<Vec<_>>();
-        let all_reviews = all_reviews
+            .await?
             .into_iter()
-            .filter(|r| match r.visibility {
-                Visibility::Private => i32::from(r.posted_by.id) == *user_id,
-                _ => true,
-            })
+            .map(|(r, u)| review_item_from_model(r, u.unwrap()))
             .map(|r| ReviewItem {
                 text: r.text.map(|t| markdown_to_html(&t)),
                 ..r
             })
-            .collect();
-        Ok(all_reviews)
-    }
+            .collect::<Vec<_>>();
+        let mut reaction_counts = self
+            .review_reaction_counts(items.iter().map(|r| r.id).collect())
+            .await?;
+        for item in items.iter_mut() {
+            item.reaction_counts = reaction_counts.remove(&item.id).unwrap_or_default();
+        }
+        let next_page = if total - (page * PAGE_LIMIT) > 0 {
+            Some(page + 1)
+        } else {
+            None
+        };
+        Ok(SearchResults {
+            total,
+            items,
+            next_page,
+        })
+    }
+
+    async fn my_reviews(&self, user_id: i32, page: i32) -> Result<SearchResults<UserReviewItem>> {
+        let user = self.user_by_id(user_id).await?;
+        let query = Review::find().filter(review::Column::UserId.eq(user_id));
+        let total: i32 = query.clone().count(&self.db).await?.try_into().unwrap();
+        let reviews = query
+            .order_by_desc(review::Column::PostedOn)
+            .find_also_related(Metadata)
+            .limit(PAGE_LIMIT as u64)
+            .offset(((page - 1) * PAGE_LIMIT) as u64)
+            .all(&self.db)
+            .await?;
+        let mut reaction_counts = self
+            .review_reaction_counts(reviews.iter().map(|(r, _)| r.id).collect())
+            .await?;
+        let mut items = vec![];
+        for (r, m) in reviews {
+            let meta = m.unwrap();
+            let poster_images = self.metadata_images(&meta).await?.posters;
+            let review = review_item_from_model(r, user.clone());
+            items.push(UserReviewItem {
+                review: ReviewItem {
+                    text: review.text.map(|t| markdown_to_html(&t)),
+                    reaction_counts: reaction_counts.remove(&review.id).unwrap_or_default(),
+                    ..review
+                },
+                media: MediaSearchItem {
+                    identifier: meta.id.to_string(),
+                    lot: meta.lot,
+                    title: meta.title,
+                    image: poster_images.get(0).cloned(),
+                    publish_year: meta.publish_year,
+                },
+            });
+        }
+        let next_page = if total - (page * PAGE_LIMIT) > 0 {
+            Some(page + 1)
+        } else {
+            None
+        };
+        Ok(SearchResults {
+            total,
+            items,
+            next_page,
+        })
+    }
 
     async fn collections(
         &self,
@@ -2209,13 +4003,17 @@ impl MiscellaneousService {
         if collection.visibility != Visibility::Public {
             match user_id {
                 None => {
-                    return Err(Error::new(
+                    return Err(coded_error(
+                        ErrorCode::Forbidden,
                         "Need to be logged in to view a private collection".to_owned(),
                     ));
                 }
                 Some(u) => {
                     if u != collection.user_id {
-                        return Err(Error::new("This collection is not public".to_owned()));
+                        return Err(coded_error(
+                            ErrorCode::Forbidden,
+                            "This collection is not public".to_owned(),
+                        ));
                     }
                 }
             }
@@ -2256,6 +4054,7 @@ impl MiscellaneousService {
     }
 
     pub async fn post_review(&self, user_id: &i32, input: PostReviewInput) -> Result<IdObject> {
+        validate_review(&input)?;
         let meta = Review::find()
             .filter(review::Column::Identifier.eq(input.identifier.clone()))
             .one(&self.db)
@@ -2294,6 +4093,12 @@ impl MiscellaneousService {
                         episode: e,
                     })));
             }
+            if let Some(season) = input.spoils_up_to_season {
+                review_obj.spoils_up_to = ActiveValue::Set(Some(ReviewSpoilerScope {
+                    season,
+                    episode: input.spoils_up_to_episode,
+                }));
+            }
             let insert = review_obj.save(&self.db).await.unwrap();
             Ok(IdObject {
                 id: insert.id.unwrap(),
@@ -2313,54 +4118,126 @@ impl MiscellaneousService {
                     r.delete(&self.db).await?;
                     Ok(true)
                 } else {
-                    Err(Error::new("This review does not belong to you".to_owned()))
+                    Err(coded_error(
+                        ErrorCode::Forbidden,
+                        "This review does not belong to you".to_owned(),
+                    ))
                 }
             }
             None => Ok(false),
         }
     }
 
-    pub async fn create_or_update_collection(
+    async fn delete_reviews(
         &self,
-        user_id: &i32,
-        input: CreateOrUpdateCollectionInput,
-    ) -> Result<IdObject> {
-        let meta = Collection::find()
-            .filter(collection::Column::Name.eq(input.name.clone()))
-            .filter(collection::Column::UserId.eq(user_id.to_owned()))
+        user_id: i32,
+        review_ids: Vec<i32>,
+    ) -> Result<DeleteReviewsResult> {
+        delete_reviews(&self.db, user_id, review_ids).await
+    }
+
+    async fn set_review_pinned(&self, user_id: i32, review_id: i32, pinned: bool) -> Result<bool> {
+        let review = self.review_by_id(review_id).await?;
+        if review.user_id != user_id {
+            return Err(coded_error(
+                ErrorCode::Forbidden,
+                "This review does not belong to you".to_owned(),
+            ));
+        }
+        let txn = self.db.begin().await?;
+        if pinned {
+            Review::update_many()
+                .col_expr(review::Column::Pinned, Expr::value(false))
+                .filter(review::Column::UserId.eq(user_id))
+                .filter(review::Column::MetadataId.eq(review.metadata_id))
+                .exec(&txn)
+                .await?;
+        }
+        let mut review: review::ActiveModel = review.into();
+        review.pinned = ActiveValue::Set(pinned);
+        review.update(&txn).await?;
+        txn.commit().await?;
+        Ok(true)
+    }
+
+    async fn react_to_review(
+        &self,
+        user_id: i32,
+        review_id: i32,
+        kind: ReviewReactionKind,
+    ) -> Result<bool> {
+        let review = self.review_by_id(review_id).await?;
+        if review.visibility == Visibility::Private && review.user_id != user_id {
+            return Err(coded_error(
+                ErrorCode::NotFound,
+                "Unable to find review".to_owned(),
+            ));
+        }
+        let existing = ReviewReaction::find_by_id((user_id, review_id))
             .one(&self.db)
-            .await
-            .unwrap();
-        match meta {
-            Some(m) if input.update_id.is_none() => Ok(IdObject { id: m.id }),
-            _ => {
-                let col = collection::ActiveModel {
-                    id: match input.update_id {
-                        Some(i) => ActiveValue::Unchanged(i),
-                        None => ActiveValue::NotSet,
-                    },
-                    name: ActiveValue::Set(input.name),
-                    user_id: ActiveValue::Set(user_id.to_owned()),
-                    description: ActiveValue::Set(input.description),
-                    visibility: match input.visibility {
-                        None => ActiveValue::NotSet,
-                        Some(v) => ActiveValue::Set(v),
-                    },
-                    ..Default::default()
+            .await?;
+        match existing {
+            Some(r) => {
+                let mut reaction: review_reaction::ActiveModel = r.into();
+                reaction.kind = ActiveValue::Set(kind);
+                reaction.update(&self.db).await?;
+            }
+            None => {
+                let reaction = review_reaction::ActiveModel {
+                    user_id: ActiveValue::Set(user_id),
+                    review_id: ActiveValue::Set(review_id),
+                    kind: ActiveValue::Set(kind),
+                    created_on: ActiveValue::Set(Utc::now()),
                 };
-                let inserted = col.save(&self.db).await.map_err(|_| {
-                    Error::new("There was an error creating the collection".to_owned())
-                })?;
-                Ok(IdObject {
-                    id: inserted.id.unwrap(),
-                })
+                reaction.insert(&self.db).await?;
             }
         }
+        Ok(true)
+    }
+
+    async fn remove_review_reaction(&self, user_id: i32, review_id: i32) -> Result<bool> {
+        let deleted = ReviewReaction::delete_by_id((user_id, review_id))
+            .exec(&self.db)
+            .await?;
+        Ok(deleted.rows_affected > 0)
+    }
+
+    async fn review_reaction_counts(
+        &self,
+        review_ids: Vec<i32>,
+    ) -> Result<HashMap<i32, Vec<ReviewReactionCount>>> {
+        let reactions = ReviewReaction::find()
+            .filter(review_reaction::Column::ReviewId.is_in(review_ids))
+            .all(&self.db)
+            .await?;
+        let mut counts: HashMap<i32, Vec<ReviewReactionCount>> = HashMap::new();
+        for ((review_id, kind), count) in reactions
+            .into_iter()
+            .map(|r| (r.review_id, r.kind))
+            .counts()
+        {
+            counts
+                .entry(review_id)
+                .or_default()
+                .push(ReviewReactionCount { kind, count });
+        }
+        Ok(counts)
+    }
+
+    pub async fn create_or_update_collection(
+        &self,
+        user_id: &i32,
+        input: CreateOrUpdateCollectionInput,
+    ) -> Result<IdObject> {
+        create_or_update_collection(&self.db, *user_id, input).await
     }
 
     pub async fn delete_collection(&self, user_id: &i32, name: &str) -> Result<bool> {
-        if DefaultCollection::iter().any(|col_name| col_name.to_string() == name) {
-            return Err(Error::new("Can not delete a default collection".to_owned()));
+        if self.config.users.is_default_collection(name) {
+            return Err(coded_error(
+                ErrorCode::Forbidden,
+                "Can not delete a default collection".to_owned(),
+            ));
         }
         let collection = Collection::find()
             .filter(collection::Column::Name.eq(name))
@@ -2375,26 +4252,106 @@ impl MiscellaneousService {
         Ok(resp)
     }
 
+    async fn rename_collection(
+        &self,
+        user_id: i32,
+        collection_id: i32,
+        name: String,
+    ) -> Result<IdObject> {
+        let collection = Collection::find_by_id(collection_id)
+            .filter(collection::Column::UserId.eq(user_id))
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| coded_error(ErrorCode::NotFound, "Collection not found".to_owned()))?;
+        if self.config.users.is_default_collection(&collection.name) {
+            return Err(coded_error(
+                ErrorCode::Forbidden,
+                "Can not rename a default collection".to_owned(),
+            ));
+        }
+        rename_collection(&self.db, collection, name).await
+    }
+
+    async fn merge_collections(
+        &self,
+        user_id: i32,
+        from_collection_id: i32,
+        into_collection_id: i32,
+    ) -> Result<IdObject> {
+        if from_collection_id == into_collection_id {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "Can not merge a collection into itself".to_owned(),
+            ));
+        }
+        let mut collections = Collection::find()
+            .filter(collection::Column::Id.is_in([from_collection_id, into_collection_id]))
+            .filter(collection::Column::UserId.eq(user_id))
+            .all(&self.db)
+            .await?;
+        if collections.len() != 2 {
+            return Err(coded_error(
+                ErrorCode::NotFound,
+                "Collection not found".to_owned(),
+            ));
+        }
+        collections.retain(|c| c.id == from_collection_id);
+        let from_collection = collections.remove(0);
+        if self
+            .config
+            .users
+            .is_default_collection(&from_collection.name)
+        {
+            return Err(coded_error(
+                ErrorCode::Forbidden,
+                "Can not merge a default collection".to_owned(),
+            ));
+        }
+        let txn = self.db.begin().await?;
+        let items = MetadataToCollection::find()
+            .filter(metadata_to_collection::Column::CollectionId.eq(from_collection_id))
+            .all(&txn)
+            .await?;
+        if !items.is_empty() {
+            // items already present in the target, even if they were added while
+            // merging, are removed along with the source
+            MetadataToCollection::insert_many(items.into_iter().map(|item| {
+                metadata_to_collection::ActiveModel {
+                    metadata_id: ActiveValue::Set(item.metadata_id),
+                    collection_id: ActiveValue::Set(into_collection_id),
+                }
+            }))
+            .on_conflict(
+                OnConflict::columns([
+                    metadata_to_collection::Column::MetadataId,
+                    metadata_to_collection::Column::CollectionId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+        }
+        MetadataToCollection::delete_many()
+            .filter(metadata_to_collection::Column::CollectionId.eq(from_collection_id))
+            .exec(&txn)
+            .await?;
+        Collection::delete_by_id(from_collection_id)
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(IdObject {
+            id: into_collection_id,
+        })
+    }
+
     pub async fn remove_media_item_from_collection(
         &self,
         user_id: &i32,
         metadata_id: &i32,
         collection_name: &str,
     ) -> Result<IdObject> {
-        let collect = Collection::find()
-            .filter(collection::Column::Name.eq(collection_name.to_owned()))
-            .filter(collection::Column::UserId.eq(user_id.to_owned()))
-            .one(&self.db)
-            .await
-            .unwrap()
-            .unwrap();
-        let col = metadata_to_collection::ActiveModel {
-            metadata_id: ActiveValue::Set(metadata_id.to_owned()),
-            collection_id: ActiveValue::Set(collect.id),
-        };
-        let id = col.collection_id.clone().unwrap();
-        col.delete(&self.db).await.ok();
-        Ok(IdObject { id })
+        remove_media_item_from_collection(&self.db, *user_id, *metadata_id, collection_name).await
     }
 
     pub async fn add_media_to_collection(
@@ -2402,28 +4359,83 @@ impl MiscellaneousService {
         user_id: &i32,
         input: AddMediaToCollection,
     ) -> Result<bool> {
-        let collection = Collection::find()
-            .filter(collection::Column::UserId.eq(user_id.to_owned()))
-            .filter(collection::Column::Name.eq(input.collection_name))
-            .one(&self.db)
-            .await
-            .unwrap()
-            .unwrap();
-        let col = metadata_to_collection::ActiveModel {
-            metadata_id: ActiveValue::Set(i32::from(input.media_id)),
-            collection_id: ActiveValue::Set(collection.id),
-        };
-        Ok(col.clone().insert(&self.db).await.is_ok())
+        add_media_to_collection(&self.db, *user_id, input).await
+    }
+
+    /// Add a finished media item to the collection configured for completed
+    /// media, if there is one.
+    pub async fn add_media_to_completed_collection(
+        &self,
+        user_id: i32,
+        metadata_id: i32,
+        metadata_lot: MetadataLot,
+    ) -> Result<()> {
+        add_media_to_completed_collection(
+            &self.db,
+            self.config.users.completed_collection.as_deref(),
+            user_id,
+            metadata_id,
+            metadata_lot,
+        )
+        .await
+    }
+
+    async fn populate_collection_from_external(
+        &self,
+        user_id: i32,
+        input: PopulateCollectionFromExternalInput,
+    ) -> Result<Vec<PopulateCollectionItemResult>> {
+        self.create_or_update_collection(
+            &user_id,
+            CreateOrUpdateCollectionInput {
+                name: input.collection_name.clone(),
+                description: None,
+                visibility: None,
+                update_id: None,
+            },
+        )
+        .await?;
+        let mut results = vec![];
+        for identifier in input.identifiers {
+            let resp = match self
+                .commit_media(input.lot, input.source, &identifier)
+                .await
+            {
+                Ok(IdObject { id }) => {
+                    self.add_media_to_collection(
+                        &user_id,
+                        AddMediaToCollection {
+                            collection_name: input.collection_name.clone(),
+                            media_id: id,
+                        },
+                    )
+                    .await?;
+                    PopulateCollectionItemResult {
+                        identifier,
+                        metadata_id: Some(id),
+                        error: None,
+                    }
+                }
+                Err(e) => PopulateCollectionItemResult {
+                    identifier,
+                    metadata_id: None,
+                    error: Some(e.message),
+                },
+            };
+            results.push(resp);
+        }
+        Ok(results)
     }
 
     pub async fn start_import_job(
         &self,
         user_id: i32,
-        source: MediaImportSource,
+        input: &DeployImportInput,
     ) -> Result<media_import_report::Model> {
         let model = media_import_report::ActiveModel {
             user_id: ActiveValue::Set(user_id),
-            source: ActiveValue::Set(source),
+            source: ActiveValue::Set(input.source),
+            input: ActiveValue::Set(Some(input.clone())),
             ..Default::default()
         };
         let model = model.insert(&self.db).await.unwrap();
@@ -2463,11 +4475,13 @@ impl MiscellaneousService {
             let progress = si.progress;
             let metadata_id = si.metadata_id;
             if si.user_id != user_id {
-                return Err(Error::new(
+                return Err(coded_error(
+                    ErrorCode::Forbidden,
                     "This seen item does not belong to this user".to_owned(),
                 ));
             }
             si.delete(&self.db).await.ok();
+            self.recalculate_popularity(metadata_id).await.ok();
             if progress < 100 {
                 self.remove_media_item_from_collection(
                     &user_id,
@@ -2479,8 +4493,70 @@ impl MiscellaneousService {
             }
             Ok(IdObject { id: seen_id })
         } else {
-            Err(Error::new("This seen item does not exist".to_owned()))
+            Err(coded_error(
+                ErrorCode::NotFound,
+                "This seen item does not exist".to_owned(),
+            ))
+        }
+    }
+
+    async fn update_seen_item(&self, input: UpdateSeenItemInput, user_id: i32) -> Result<IdObject> {
+        let si = match Seen::find_by_id(input.seen_id).one(&self.db).await? {
+            Some(si) => si,
+            None => {
+                return Err(coded_error(
+                    ErrorCode::NotFound,
+                    "This seen item does not exist".to_owned(),
+                ))
+            }
+        };
+        if si.user_id != user_id {
+            return Err(coded_error(
+                ErrorCode::Forbidden,
+                "This seen item does not belong to this user".to_owned(),
+            ));
+        }
+        if let Some(progress) = input.progress {
+            if !(0..=100).contains(&progress) {
+                return Err(coded_error(
+                    ErrorCode::Validation,
+                    "Progress must be between 0 and 100".to_owned(),
+                ));
+            }
+        }
+        let today = self.user_by_id(user_id).await?.preferences.today();
+        let progress = input.progress.unwrap_or(si.progress);
+        let started_on = updated_date(input.started_on, si.started_on);
+        let finished_on = match input.finished_on {
+            MaybeUndefined::Undefined if progress != si.progress => {
+                (progress == 100).then(|| si.finished_on.unwrap_or(today))
+            }
+            finished_on => updated_date(finished_on, si.finished_on),
+        };
+        if let (Some(s), Some(f)) = (started_on, finished_on) {
+            if s > f {
+                return Err(coded_error(
+                    ErrorCode::Validation,
+                    "The start date can not be after the finish date".to_owned(),
+                ));
+            }
+        }
+        let progress_changed = progress != si.progress;
+        let mut seen: seen::ActiveModel = si.into();
+        seen.progress = ActiveValue::Set(progress);
+        seen.started_on = ActiveValue::Set(started_on);
+        seen.finished_on = ActiveValue::Set(finished_on);
+        if let Some(note) = input.note {
+            seen.note = ActiveValue::Set(seen_note(note));
         }
+        seen.last_updated_on = ActiveValue::Set(Utc::now());
+        let seen = seen.update(&self.db).await?;
+        let id = seen.id;
+        if progress_changed {
+            self.deploy_after_media_seen_job(seen).await?;
+        }
+        self.deploy_recalculate_summary_job(user_id).await?;
+        Ok(IdObject { id })
     }
 
     pub async fn cleanup_summaries_for_user(&self, user_id: &i32) -> Result<()> {
@@ -2503,19 +4579,44 @@ impl MiscellaneousService {
         let maybe_details = self
             .details_from_provider_for_existing_media(metadata_id)
             .await;
+        record_refresh_result(&self.db, metadata_id, RefreshResult::of(&maybe_details)).await?;
         match maybe_details {
             Ok(details) => {
-                self.update_media(
-                    metadata_id,
-                    details.title,
-                    details.description,
-                    details.images,
-                    details.creators,
-                    details.specifics,
-                    details.genres,
-                )
-                .await
-                .ok();
+                let content_hash = media_details_hash(&details);
+                let provider_rating = details.provider_rating;
+                let original_language = details.original_language.clone();
+                let franchise = details.franchise.clone();
+                let slug = details.slug.clone();
+                let unchanged = metadata.content_hash.as_ref() == Some(&content_hash);
+                let mut metadata: metadata::ActiveModel = metadata.into();
+                if unchanged {
+                    tracing::info!("Skipped updating unchanged metadata for {:?}", metadata_id);
+                } else {
+                    let updated = self
+                        .update_media(
+                            metadata_id,
+                            details.title,
+                            details.description,
+                            details.images,
+                            details.creators,
+                            details.specifics,
+                            details.genres,
+                        )
+                        .await;
+                    // only remember the details once they are stored, so that a
+                    // failed update is retried the next time
+                    match updated {
+                        Ok(_) => metadata.content_hash = ActiveValue::Set(Some(content_hash)),
+                        Err(e) => tracing::error!("Error while updating: {:?}", e),
+                    }
+                }
+                metadata.last_updated_on = ActiveValue::Set(Utc::now());
+                metadata.watch_providers = ActiveValue::Set(None);
+                metadata.provider_rating = ActiveValue::Set(provider_rating);
+                metadata.original_language = ActiveValue::Set(original_language);
+                metadata.franchise = ActiveValue::Set(franchise);
+                metadata.slug = ActiveValue::Set(slug);
+                metadata.update(&self.db).await.ok();
             }
             Err(e) => {
                 tracing::error!("Error while updating: {:?}", e);
@@ -2525,6 +4626,32 @@ impl MiscellaneousService {
         Ok(())
     }
 
+    /// Update a media item from its provider right away, unless it was already
+    /// updated recently.
+    async fn refresh_metadata(&self, metadata_id: i32) -> Result<()> {
+        let metadata = match Metadata::find_by_id(metadata_id).one(&self.db).await? {
+            Some(m) => m,
+            None => {
+                return Err(coded_error(
+                    ErrorCode::NotFound,
+                    "The record does not exit".to_owned(),
+                ))
+            }
+        };
+        if metadata.source == MetadataSource::Custom
+            || Utc::now() - metadata.last_updated_on
+                < Duration::minutes(MEDIA_REFRESH_COOLDOWN_MINUTES)
+        {
+            return Ok(());
+        }
+        // the attempt is recorded up front, so that refreshes which fail or find
+        // nothing new are also held back by the cooldown
+        let mut metadata: metadata::ActiveModel = metadata.into();
+        metadata.last_updated_on = ActiveValue::Set(Utc::now());
+        let metadata = metadata.update(&self.db).await?;
+        self.update_metadata(metadata).await
+    }
+
     pub async fn update_all_metadata(&self) -> Result<bool> {
         let metadatas = Metadata::find()
             .order_by_asc(metadata::Column::Id)
@@ -2549,12 +4676,164 @@ impl MiscellaneousService {
         }
     }
 
-    async fn user_by_id(&self, user_id: i32) -> Result<user::Model> {
+    pub async fn user_by_id(&self, user_id: i32) -> Result<user::Model> {
         User::find_by_id(user_id)
             .one(&self.db)
             .await
             .unwrap()
-            .ok_or_else(|| Error::new("No user found"))
+            .ok_or_else(|| coded_error(ErrorCode::NotFound, "No user found"))
+    }
+
+    async fn admin_account_guard(&self, user_id: i32) -> Result<()> {
+        if self.user_by_id(user_id).await?.lot != UserLot::Admin {
+            return Err(coded_error(
+                ErrorCode::Forbidden,
+                "Only admins can perform this operation",
+            ));
+        }
+        Ok(())
+    }
+
+    async fn run_cleanup(&self) -> Result<CleanupResult> {
+        // associations need to be removed first so that the media they pointed
+        // to can be cleaned up in the same run
+        let user_to_metadata_removed = self.cleanup_user_and_metadata_association().await?;
+        let metadata_removed = self
+            .cleanup_metadata_with_associated_user_activities()
+            .await?;
+        Ok(CleanupResult {
+            user_to_metadata_removed,
+            metadata_removed,
+        })
+    }
+
+    async fn audit_images(&self, limit: Option<usize>) -> Result<ImageAudit> {
+        if !self.file_storage.is_enabled().await {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "File storage must be enabled for this feature.".to_owned(),
+            ));
+        }
+        let mut references = vec![];
+        for (id, images) in Metadata::find()
+            .select_only()
+            .column(metadata::Column::Id)
+            .column(metadata::Column::Images)
+            .into_tuple::<(i32, MetadataImages)>()
+            .all(&self.db)
+            .await?
+        {
+            for image in images.0 {
+                if let MetadataImageUrl::S3(key) = image.url {
+                    references.push((ImageReferenceLot::Metadata, id, key));
+                }
+            }
+        }
+        for ex in Exercise::find().all(&self.db).await? {
+            for key in ex.attributes.images {
+                references.push((ImageReferenceLot::Exercise, ex.id, key));
+            }
+        }
+        for utm in UserToMetadata::find()
+            .filter(user_to_metadata::Column::ImageOverride.is_not_null())
+            .all(&self.db)
+            .await?
+        {
+            if let Some(key) = utm
+                .image_override
+                .filter(|i| !i.starts_with("http://") && !i.starts_with("https://"))
+            {
+                references.push((ImageReferenceLot::UserImageOverride, utm.metadata_id, key));
+            }
+        }
+        if let Some(limit) = limit {
+            references.truncate(limit);
+        }
+        let mut broken = vec![];
+        let mut errored = vec![];
+        for (lot, entity_id, key) in references.iter() {
+            let reference = BrokenImageReference {
+                key: key.clone(),
+                lot: *lot,
+                entity_id: *entity_id,
+            };
+            match self.file_storage.exists(key).await {
+                Ok(true) => {}
+                Ok(false) => broken.push(reference),
+                Err(e) => {
+                    tracing::warn!("Could not check image with key = {key}: {e:?}");
+                    errored.push(reference);
+                }
+            }
+        }
+        Ok(ImageAudit {
+            checked: references.len(),
+            broken,
+            errored,
+        })
+    }
+
+    async fn find_duplicate_metadata(&self) -> Result<Vec<DuplicateMetadataGroup>> {
+        let mut groups: HashMap<(MetadataLot, Option<i32>, String), Vec<metadata::Model>> =
+            HashMap::new();
+        for meta in Metadata::find()
+            .order_by_asc(metadata::Column::Id)
+            .all(&self.db)
+            .await?
+        {
+            groups
+                .entry((meta.lot, meta.publish_year, normalize_title(&meta.title)))
+                .or_default()
+                .push(meta);
+        }
+        Ok(groups
+            .into_values()
+            .filter(|metas| metas.len() > 1)
+            .map(|metas| DuplicateMetadataGroup {
+                title: metas[0].title.clone(),
+                lot: metas[0].lot,
+                publish_year: metas[0].publish_year,
+                items: metas
+                    .into_iter()
+                    .map(|m| DuplicateMetadataItem {
+                        id: m.id,
+                        source: m.source,
+                        identifier: m.identifier,
+                    })
+                    .collect(),
+            })
+            .sorted_by_key(|g| g.items[0].id)
+            .collect())
+    }
+
+    async fn pending_jobs(&self) -> Result<Vec<PendingJob>> {
+        let jobs: Vec<(String, String, i64)> = sqlx::query_as(
+            "SELECT id, job_type, run_at FROM Jobs WHERE status = 'Pending' ORDER BY run_at",
+        )
+        .fetch_all(&self.scheduler_pool)
+        .await?;
+        Ok(jobs
+            .into_iter()
+            .map(|(id, name, run_at)| PendingJob {
+                id,
+                name,
+                run_at: Utc.timestamp_opt(run_at, 0).single(),
+            })
+            .collect())
+    }
+
+    async fn cancel_job(&self, job_id: String) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM Jobs WHERE id = ? AND status = 'Pending'")
+            .bind(job_id)
+            .execute(&self.scheduler_pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(coded_error(
+                ErrorCode::NotFound,
+                "This job does not exist or has already been started",
+            ));
+        }
+        Ok(true)
     }
 
     async fn latest_user_summary(&self, user_id: &i32) -> Result<summary::Model> {
@@ -2568,113 +4847,99 @@ impl MiscellaneousService {
         Ok(ls)
     }
 
-    async fn user_summary(&self, user_id: &i32) -> Result<UserSummary> {
+    async fn user_summary(
+        &self,
+        user_id: &i32,
+        recently_finished_limit: Option<u64>,
+    ) -> Result<UserSummary> {
         let ls = self.latest_user_summary(user_id).await?;
-        Ok(UserSummary {
-            media: ls.data,
-            calculated_on: ls.created_on,
-        })
-    }
-
-    pub async fn calculate_user_summary(&self, user_id: &i32) -> Result<IdObject> {
-        let mut ls = summary::Model::default();
+        let limit = recently_finished_limit
+            .unwrap_or(RECENTLY_FINISHED_DEFAULT_LIMIT)
+            .min(RECENTLY_FINISHED_MAX_LIMIT);
         let mut seen_items = Seen::find()
-            .filter(seen::Column::UserId.eq(user_id.to_owned()))
             .filter(seen::Column::UserId.eq(user_id.to_owned()))
             .filter(seen::Column::Progress.eq(100))
+            .filter(seen::Column::FinishedOn.is_not_null())
+            .order_by_desc(seen::Column::FinishedOn)
+            .order_by_desc(seen::Column::LastUpdatedOn)
             .find_also_related(Metadata)
+            .limit(limit)
             .stream(&self.db)
             .await?;
-
-        let mut unique_shows = HashSet::new();
-        let mut unique_show_seasons = HashSet::new();
-        let mut unique_podcasts = HashSet::new();
-        let mut unique_podcast_episodes = HashSet::new();
-        while let Some((seen, metadata)) = seen_items.try_next().await.unwrap() {
-            let meta = metadata.to_owned().unwrap();
-            match meta.specifics {
-                MediaSpecifics::AudioBook(item) => {
-                    ls.data.audio_books.played += 1;
-                    if let Some(r) = item.runtime {
-                        ls.data.audio_books.runtime += r;
-                    }
-                }
-                MediaSpecifics::Anime(item) => {
-                    ls.data.anime.watched += 1;
-                    if let Some(r) = item.episodes {
-                        ls.data.anime.episodes += r;
-                    }
-                }
-                MediaSpecifics::Manga(item) => {
-                    ls.data.manga.read += 1;
-                    if let Some(r) = item.chapters {
-                        ls.data.manga.chapters += r;
-                    }
-                }
-                MediaSpecifics::Book(item) => {
-                    ls.data.books.read += 1;
-                    if let Some(pg) = item.pages {
-                        ls.data.books.pages += pg;
-                    }
-                }
-                MediaSpecifics::Podcast(item) => {
-                    unique_podcasts.insert(seen.metadata_id);
-                    for episode in item.episodes {
-                        match seen.extra_information.to_owned() {
-                            None => continue,
-                            Some(sei) => match sei {
-                                SeenExtraInformation::Show(_) => unreachable!(),
-                                SeenExtraInformation::Podcast(s) => {
-                                    if s.episode == episode.number {
-                                        if let Some(r) = episode.runtime {
-                                            ls.data.podcasts.runtime += r;
-                                        }
-                                        unique_podcast_episodes.insert((s.episode, episode.id));
-                                    }
-                                }
-                            },
-                        }
-                    }
-                }
-                MediaSpecifics::Movie(item) => {
-                    ls.data.movies.watched += 1;
-                    if let Some(r) = item.runtime {
-                        ls.data.movies.runtime += r;
-                    }
-                }
-                MediaSpecifics::Show(item) => {
-                    unique_shows.insert(seen.metadata_id);
-                    for season in item.seasons {
-                        for episode in season.episodes {
-                            match seen.extra_information.to_owned().unwrap() {
-                                SeenExtraInformation::Podcast(_) => unreachable!(),
-                                SeenExtraInformation::Show(s) => {
-                                    if s.season == season.season_number
-                                        && s.episode == episode.episode_number
-                                    {
-                                        if let Some(r) = episode.runtime {
-                                            ls.data.shows.runtime += r;
-                                        }
-                                        ls.data.shows.watched_episodes += 1;
-                                        unique_show_seasons.insert((s.season, season.id));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                MediaSpecifics::VideoGame(_item) => {
-                    ls.data.video_games.played += 1;
-                }
-                MediaSpecifics::Unknown => {}
+        let mut recently_finished = vec![];
+        while let Some((seen, metadata)) = seen_items.try_next().await? {
+            if let Some(meta) = metadata {
+                recently_finished.push(RecentlyFinishedItem {
+                    metadata_id: meta.id,
+                    title: meta.title,
+                    lot: meta.lot,
+                    finished_on: seen.finished_on,
+                });
             }
         }
+        Ok(UserSummary {
+            runtime: UserRuntimeSummary::from(&ls.data),
+            media: ls.data,
+            calculated_on: ls.created_on,
+            recently_finished,
+        })
+    }
 
-        ls.data.podcasts.played += i32::try_from(unique_podcasts.len()).unwrap();
-        ls.data.podcasts.played_episodes += i32::try_from(unique_podcast_episodes.len()).unwrap();
+    pub async fn calculate_user_summary(&self, user_id: &i32) -> Result<IdObject> {
+        let mut ls = summary::Model::default();
+        ls.data = summarize_completed_media(
+            &self.db,
+            *user_id,
+            self.config.summary.use_average_episode_runtime,
+        )
+        .await?;
 
-        ls.data.shows.watched = i32::try_from(unique_shows.len()).unwrap();
-        ls.data.shows.watched_seasons += i32::try_from(unique_show_seasons.len()).unwrap();
+        let seen_progress = Seen::find()
+            .select_only()
+            .column(seen::Column::MetadataId)
+            .column(seen::Column::Progress)
+            .column(seen::Column::Dropped)
+            .filter(seen::Column::UserId.eq(user_id.to_owned()))
+            .into_tuple::<(i32, i32, bool)>()
+            .all(&self.db)
+            .await?;
+        let mut started = HashSet::new();
+        let mut in_progress = HashSet::new();
+        let mut completed = HashSet::new();
+        for (metadata_id, progress, dropped) in seen_progress {
+            started.insert(metadata_id);
+            if progress == 100 {
+                completed.insert(metadata_id);
+            } else if !dropped {
+                in_progress.insert(metadata_id);
+            }
+        }
+        let collection_items = MetadataToCollection::find()
+            .select_only()
+            .column(metadata_to_collection::Column::MetadataId)
+            .distinct()
+            .filter(
+                metadata_to_collection::Column::CollectionId.in_subquery(
+                    Query::select()
+                        .column(collection::Column::Id)
+                        .from(Collection)
+                        .and_where(collection::Column::UserId.eq(user_id.to_owned()))
+                        .to_owned(),
+                ),
+            )
+            .into_tuple::<i32>()
+            .all(&self.db)
+            .await?;
+        ls.data.backlog.in_progress = i32::try_from(in_progress.len()).unwrap();
+        ls.data.backlog.completed =
+            i32::try_from(completed.difference(&in_progress).count()).unwrap();
+        ls.data.backlog.unstarted = i32::try_from(
+            collection_items
+                .iter()
+                .filter(|m| !started.contains(m))
+                .count(),
+        )
+        .unwrap();
 
         let summary_obj = summary::ActiveModel {
             id: ActiveValue::NotSet,
@@ -2692,6 +4957,16 @@ impl MiscellaneousService {
                 error: RegisterErrorVariant::Disabled,
             }));
         }
+        if !self.config.users.is_valid_username(username) {
+            return Ok(RegisterResult::Error(RegisterError {
+                error: RegisterErrorVariant::InvalidUsername,
+            }));
+        }
+        if !self.config.users.is_strong_password(password) {
+            return Ok(RegisterResult::Error(RegisterError {
+                error: RegisterErrorVariant::WeakPassword,
+            }));
+        }
         let mut storage = self.user_created.clone();
         if User::find()
             .filter(user::Column::Name.eq(username))
@@ -2714,6 +4989,7 @@ impl MiscellaneousService {
             password: ActiveValue::Set(password.to_owned()),
             lot: ActiveValue::Set(lot),
             preferences: ActiveValue::Set(UserPreferences::default()),
+            created_on: ActiveValue::Set(Some(Utc::now())),
             ..Default::default()
         };
         let user = user.insert(&self.db).await.unwrap();
@@ -2764,12 +5040,12 @@ impl MiscellaneousService {
 
     // this job is run when a user is created for the first time
     pub async fn user_created_job(&self, user_id: &i32) -> Result<()> {
-        for col in DefaultCollection::iter() {
+        for col in self.config.users.default_collections.iter() {
             self.create_or_update_collection(
                 user_id,
                 CreateOrUpdateCollectionInput {
-                    name: col.to_string(),
-                    description: Some(col.meta().to_owned()),
+                    name: col.name.clone(),
+                    description: col.description.clone(),
                     ..Default::default()
                 },
             )
@@ -2780,27 +5056,74 @@ impl MiscellaneousService {
     }
 
     async fn update_user(&self, user_id: &i32, input: UpdateUserInput) -> Result<IdObject> {
-        let mut user_obj: user::ActiveModel = User::find_by_id(user_id.to_owned())
-            .one(&self.db)
-            .await
-            .unwrap()
-            .unwrap()
-            .into();
+        let user_model = self.user_by_id(*user_id).await?;
+        let previous_email = user_model.email.clone();
+        let mut user_obj: user::ActiveModel = user_model.into();
         if let Some(n) = input.username {
             if self.config.users.allow_changing_username {
+                if !self.config.users.is_valid_username(&n) {
+                    return Err(coded_error(
+                        ErrorCode::Validation,
+                        "This username is not allowed".to_owned(),
+                    ));
+                }
                 user_obj.name = ActiveValue::Set(n);
             }
         }
         if let Some(e) = input.email {
+            if previous_email.as_ref() != Some(&e) {
+                // there is no way to send emails, so the token can only be
+                // made available to the admin in the logs
+                let token = Uuid::new_v4().to_string();
+                if self.config.users.log_email_verification_tokens {
+                    tracing::debug!("Email verification token for user {user_id}: {token}");
+                }
+                user_obj.email_verified = ActiveValue::Set(false);
+                user_obj.email_verification_token = ActiveValue::Set(Some(token));
+            }
             user_obj.email = ActiveValue::Set(Some(e));
         }
         if let Some(p) = input.password {
+            if !self.config.users.is_strong_password(&p) {
+                return Err(coded_error(
+                    ErrorCode::Validation,
+                    "This password is too weak".to_owned(),
+                ));
+            }
             user_obj.password = ActiveValue::Set(p);
         }
         let user_obj = user_obj.update(&self.db).await.unwrap();
         Ok(IdObject { id: user_obj.id })
     }
 
+    async fn transfer_user_library(&self, from_user_id: i32, to_user_id: i32) -> Result<bool> {
+        if from_user_id == to_user_id {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "A library can not be transferred to the same user".to_owned(),
+            ));
+        }
+        self.user_by_id(from_user_id).await?;
+        self.user_by_id(to_user_id).await?;
+        transfer_user_library(&self.db, from_user_id, to_user_id).await?;
+        for user_id in [from_user_id, to_user_id] {
+            self.cleanup_summaries_for_user(&user_id).await?;
+            self.calculate_user_summary(&user_id).await?;
+        }
+        Ok(true)
+    }
+
+    async fn verify_email(&self, token: &str) -> Result<bool> {
+        verify_email(&self.db, token).await
+    }
+
+    async fn users_list(&self) -> Result<Vec<user::Model>> {
+        Ok(User::find()
+            .order_by_asc(user::Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
     pub async fn regenerate_user_summaries(&self) -> Result<()> {
         let all_users = User::find().all(&self.db).await.unwrap();
         for user in all_users {
@@ -2893,8 +5216,19 @@ impl MiscellaneousService {
             publish_year: input.publish_year,
             publish_date: None,
             specifics,
+            provider_rating: None,
+            original_language: None,
+            franchise: None,
+            slug: None,
         };
         let media = self.commit_media_internal(details).await?;
+        let metadata = metadata::ActiveModel {
+            id: ActiveValue::Unchanged(media.id),
+            created_by: ActiveValue::Set(Some(user_id.to_owned())),
+            ..Default::default()
+        };
+        metadata.update(&self.db).await?;
+        // the collection might have been removed from the configured defaults
         self.add_media_to_collection(
             user_id,
             AddMediaToCollection {
@@ -2902,11 +5236,16 @@ impl MiscellaneousService {
                 media_id: media.id,
             },
         )
-        .await?;
+        .await
+        .ok();
         Ok(CreateCustomMediaResult::Ok(media))
     }
 
-    pub async fn json_export(&self, user_id: i32) -> Result<Vec<ExportMedia>> {
+    pub async fn json_export(
+        &self,
+        user_id: i32,
+        lots: Option<Vec<MetadataLot>>,
+    ) -> Result<Vec<ExportMedia>> {
         let related_metadata = UserToMetadata::find()
             .filter(user_to_metadata::Column::UserId.eq(user_id))
             .all(&self.db)
@@ -2918,57 +5257,54 @@ impl MiscellaneousService {
             .collect::<Vec<_>>();
         let metas = Metadata::find()
             .filter(metadata::Column::Id.is_in(distinct_meta_ids))
+            .apply_if(lots, |query, v| {
+                query.filter(metadata::Column::Lot.is_in(v))
+            })
             .order_by(metadata::Column::Id, Order::Asc)
             .all(&self.db)
             .await?;
+        export_media(&self.db, user_id, metas).await
+    }
 
-        let mut resp = vec![];
+    /// Export one page of the media of the user, for libraries that are too
+    /// large to export at once.
+    pub async fn json_export_page(
+        &self,
+        user_id: i32,
+        page: u64,
+        lots: Option<Vec<MetadataLot>>,
+    ) -> Result<ExportMediaPage> {
+        export_media_page(&self.db, user_id, page, lots).await
+    }
 
-        for m in metas {
-            let mut seens = m
-                .find_related(Seen)
-                .filter(seen::Column::UserId.eq(user_id))
-                .all(&self.db)
-                .await
-                .unwrap();
-            modify_seen_elements(&mut seens);
-            let reviews = m
-                .find_related(Review)
-                .filter(review::Column::UserId.eq(user_id))
-                .all(&self.db)
-                .await
-                .unwrap();
-            let mut exp = ExportMedia {
-                ryot_id: m.id,
-                title: m.title,
-                lot: m.lot,
-                audible_id: None,
-                custom_id: None,
-                igdb_id: None,
-                listennotes_id: None,
-                google_books_id: None,
-                openlibrary_id: None,
-                tmdb_id: None,
-                itunes_id: None,
-                anilist_id: None,
-                seen_history: seens,
-                user_reviews: reviews,
-            };
-            match m.source {
-                MetadataSource::Audible => exp.audible_id = Some(m.identifier),
-                MetadataSource::Custom => exp.custom_id = Some(m.identifier),
-                MetadataSource::GoogleBooks => exp.google_books_id = Some(m.identifier),
-                MetadataSource::Igdb => exp.igdb_id = Some(m.identifier),
-                MetadataSource::Listennotes => exp.listennotes_id = Some(m.identifier),
-                MetadataSource::Openlibrary => exp.openlibrary_id = Some(m.identifier),
-                MetadataSource::Tmdb => exp.tmdb_id = Some(m.identifier),
-                MetadataSource::Anilist => exp.anilist_id = Some(m.identifier),
-                MetadataSource::Itunes => exp.itunes_id = Some(m.identifier),
-            };
-            resp.push(exp);
+    /// Export the media in a collection along with the seen history and reviews
+    /// of the user exporting it.
+    pub async fn export_collection(
+        &self,
+        user_id: i32,
+        collection_id: i32,
+    ) -> Result<Vec<ExportMedia>> {
+        let collection = match Collection::find_by_id(collection_id).one(&self.db).await? {
+            Some(c) => c,
+            None => {
+                return Err(coded_error(
+                    ErrorCode::NotFound,
+                    "Collection not found".to_owned(),
+                ))
+            }
+        };
+        if collection.visibility != Visibility::Public && collection.user_id != user_id {
+            return Err(coded_error(
+                ErrorCode::Forbidden,
+                "This collection is not public".to_owned(),
+            ));
         }
-
-        Ok(resp)
+        let metas = collection
+            .find_related(Metadata)
+            .order_by(metadata::Column::Id, Order::Asc)
+            .all(&self.db)
+            .await?;
+        export_media(&self.db, user_id, metas).await
     }
 
     fn get_sql_and_values(&self, stmt: SelectStatement) -> (String, Values) {
@@ -3008,6 +5344,65 @@ impl MiscellaneousService {
         Ok(true)
     }
 
+    async fn reset_feature_preferences(&self, user_id: i32) -> Result<UserPreferences> {
+        let user_model = self.user_by_id(user_id).await?;
+        let mut preferences = user_model.preferences.clone();
+        preferences.features_enabled = UserPreferences::default().features_enabled;
+        let mut user_model: user::ActiveModel = user_model.into();
+        user_model.preferences = ActiveValue::Set(preferences.clone());
+        user_model.update(&self.db).await?;
+        Ok(preferences)
+    }
+
+    async fn update_user_timezone(&self, timezone: String, user_id: i32) -> Result<bool> {
+        if parse_timezone(&timezone).is_none() {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "This timezone is not valid".to_owned(),
+            ));
+        }
+        let user_model = self.user_by_id(user_id).await?;
+        let mut preferences = user_model.preferences.clone();
+        preferences.timezone = timezone;
+        let mut user_model: user::ActiveModel = user_model.into();
+        user_model.preferences = ActiveValue::Set(preferences);
+        user_model.update(&self.db).await?;
+        Ok(true)
+    }
+
+    async fn update_user_region(&self, region: String, user_id: i32) -> Result<bool> {
+        if region.len() != 2 || !region.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "This region is not valid".to_owned(),
+            ));
+        }
+        let user_model = self.user_by_id(user_id).await?;
+        let mut preferences = user_model.preferences.clone();
+        preferences.region = region.to_uppercase();
+        let mut user_model: user::ActiveModel = user_model.into();
+        user_model.preferences = ActiveValue::Set(preferences);
+        user_model.update(&self.db).await?;
+        Ok(true)
+    }
+
+    async fn update_user_media_list_sort(
+        &self,
+        input: MediaSortInput,
+        user_id: i32,
+    ) -> Result<bool> {
+        let user_model = self.user_by_id(user_id).await?;
+        let mut preferences = user_model.preferences.clone();
+        preferences.media_list_sort = UserMediaListSortPreferences {
+            by: input.by,
+            order: input.order,
+        };
+        let mut user_model: user::ActiveModel = user_model.into();
+        user_model.preferences = ActiveValue::Set(preferences);
+        user_model.update(&self.db).await?;
+        Ok(true)
+    }
+
     async fn generate_application_token(&self, user_id: i32) -> Result<String> {
         let api_token = Uuid::new_v4().to_string();
         self.set_auth_token(&api_token, &user_id)
@@ -3029,41 +5424,91 @@ impl MiscellaneousService {
         Ok(integrations
             .into_iter()
             .map(|i| {
-                let (lot, description) = match i.settings {
-                    UserYankIntegrationSetting::Audiobookshelf { base_url, .. } => {
-                        (UserYankIntegrationLot::Audiobookshelf, base_url)
-                    }
+                let (lot, description, min_progress, max_progress, library_id) = match i.settings {
+                    UserYankIntegrationSetting::Audiobookshelf {
+                        base_url,
+                        min_progress,
+                        max_progress,
+                        library_id,
+                        ..
+                    } => (
+                        UserYankIntegrationLot::Audiobookshelf,
+                        base_url,
+                        min_progress,
+                        max_progress,
+                        library_id,
+                    ),
                 };
                 GraphqlUserYankIntegration {
                     id: i.id,
                     lot,
                     description,
                     timestamp: i.timestamp,
+                    min_progress,
+                    max_progress,
+                    library_id,
                 }
             })
             .collect())
     }
 
+    async fn test_yank_integration(
+        &self,
+        input: CreateUserYankIntegrationInput,
+    ) -> Result<YankIntegrationTestResult> {
+        let items = match input.lot {
+            UserYankIntegrationLot::Audiobookshelf => {
+                self.integration_service
+                    .audiobookshelf_progress(
+                        &input.base_url,
+                        &input.token,
+                        input.library_id.as_deref(),
+                    )
+                    .await
+            }
+        }
+        .map_err(|e| Error::new(e.to_string()))?;
+        Ok(YankIntegrationTestResult {
+            progress_items: items.len(),
+        })
+    }
+
     async fn create_user_yank_integration(
         &self,
         user_id: i32,
         input: CreateUserYankIntegrationInput,
     ) -> Result<usize> {
+        let min_progress = input.min_progress.unwrap_or_else(default_yank_min_progress);
+        let max_progress = input.max_progress.unwrap_or_else(default_yank_max_progress);
+        if !(0..=100).contains(&min_progress)
+            || !(0..=100).contains(&max_progress)
+            || min_progress > max_progress
+        {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "Invalid progress thresholds".to_owned(),
+            ));
+        }
         let user = self.user_by_id(user_id).await?;
         let mut integrations = if let Some(i) = user.yank_integrations.clone() {
             i.0
         } else {
             vec![]
         };
-        let new_integration_id = integrations.len() + 1;
+        let new_integration_id =
+            next_yank_integration_id(user.last_yank_integration_id, &integrations);
         let new_integration = UserYankIntegration {
             id: new_integration_id,
             timestamp: Utc::now(),
+            last_synced_progress: HashMap::new(),
             settings: match input.lot {
                 UserYankIntegrationLot::Audiobookshelf => {
                     UserYankIntegrationSetting::Audiobookshelf {
                         base_url: input.base_url,
                         token: input.token,
+                        min_progress,
+                        max_progress,
+                        library_id: input.library_id,
                     }
                 }
             },
@@ -3071,6 +5516,7 @@ impl MiscellaneousService {
         integrations.push(new_integration);
         let mut user: user::ActiveModel = user.into();
         user.yank_integrations = ActiveValue::Set(Some(UserYankIntegrations(integrations)));
+        user.last_yank_integration_id = ActiveValue::Set(new_integration_id.try_into().unwrap());
         user.update(&self.db).await?;
         Ok(new_integration_id)
     }
@@ -3101,6 +5547,35 @@ impl MiscellaneousService {
         Ok(true)
     }
 
+    async fn set_media_image_override(
+        &self,
+        user_id: i32,
+        metadata_id: i32,
+        key: Option<String>,
+    ) -> Result<bool> {
+        if Metadata::find_by_id(metadata_id)
+            .one(&self.db)
+            .await?
+            .is_none()
+        {
+            return Err(coded_error(
+                ErrorCode::NotFound,
+                "The record does not exit".to_owned(),
+            ));
+        }
+        associate_user_with_metadata(&user_id, &metadata_id, &self.db).await?;
+        let utm = UserToMetadata::find()
+            .filter(user_to_metadata::Column::UserId.eq(user_id))
+            .filter(user_to_metadata::Column::MetadataId.eq(metadata_id))
+            .one(&self.db)
+            .await?
+            .unwrap();
+        let mut utm: user_to_metadata::ActiveModel = utm.into();
+        utm.image_override = ActiveValue::Set(key);
+        utm.update(&self.db).await?;
+        Ok(true)
+    }
+
     async fn set_auth_token(&self, api_key: &str, user_id: &i32) -> anyhow::Result<()> {
         self.auth_db
             .insert(
@@ -3130,6 +5605,39 @@ impl MiscellaneousService {
         Ok(media.map(|m| IdObject { id: m.id }))
     }
 
+    async fn enabled_sources_for_lot(&self, lot: MetadataLot) -> Vec<MetadataSource> {
+        self.media_sources_for_lot(lot)
+            .await
+            .into_iter()
+            .filter(|s| self.is_source_enabled(lot, *s))
+            .collect()
+    }
+
+    fn is_source_enabled(&self, lot: MetadataLot, source: MetadataSource) -> bool {
+        match source {
+            MetadataSource::Audible => self.config.audio_books.is_enabled(),
+            MetadataSource::Openlibrary | MetadataSource::GoogleBooks => {
+                self.config.books.is_enabled()
+            }
+            MetadataSource::Itunes => self.config.podcasts.is_enabled(),
+            MetadataSource::Listennotes => {
+                self.config.podcasts.is_enabled() && self.config.podcasts.listennotes.is_enabled()
+            }
+            MetadataSource::Igdb => self.config.video_games.is_enabled(),
+            MetadataSource::Anilist => match lot {
+                MetadataLot::Manga => self.config.manga.is_enabled(),
+                _ => self.config.anime.is_enabled(),
+            },
+            MetadataSource::Tmdb => match lot {
+                MetadataLot::Show => {
+                    self.config.shows.is_enabled() && self.config.shows.tmdb.is_enabled()
+                }
+                _ => self.config.movies.is_enabled() && self.config.movies.tmdb.is_enabled(),
+            },
+            MetadataSource::Custom => false,
+        }
+    }
+
     async fn media_sources_for_lot(&self, lot: MetadataLot) -> Vec<MetadataSource> {
         match lot {
             MetadataLot::AudioBook => vec![MetadataSource::Audible],
@@ -3144,44 +5652,7 @@ impl MiscellaneousService {
     fn providers_language_information(&self) -> Vec<ProviderLanguageInformation> {
         MetadataSource::iter()
             .map(|source| {
-                let (supported, default) = match source {
-                    MetadataSource::Itunes => (
-                        ITunesService::supported_languages(),
-                        ITunesService::default_language(),
-                    ),
-                    MetadataSource::Audible => (
-                        AudibleService::supported_languages(),
-                        AudibleService::default_language(),
-                    ),
-                    MetadataSource::Openlibrary => (
-                        OpenlibraryService::supported_languages(),
-                        OpenlibraryService::default_language(),
-                    ),
-                    MetadataSource::Tmdb => (
-                        TmdbService::supported_languages(),
-                        TmdbService::default_language(),
-                    ),
-                    MetadataSource::Listennotes => (
-                        ListennotesService::supported_languages(),
-                        ListennotesService::default_language(),
-                    ),
-                    MetadataSource::GoogleBooks => (
-                        GoogleBooksService::supported_languages(),
-                        GoogleBooksService::default_language(),
-                    ),
-                    MetadataSource::Igdb => (
-                        IgdbService::supported_languages(),
-                        IgdbService::default_language(),
-                    ),
-                    MetadataSource::Anilist => (
-                        AnilistService::supported_languages(),
-                        AnilistService::default_language(),
-                    ),
-                    MetadataSource::Custom => (
-                        CustomService::supported_languages(),
-                        CustomService::default_language(),
-                    ),
-                };
+                let (supported, default) = provider_languages(source);
                 ProviderLanguageInformation {
                     supported,
                     default,
@@ -3192,42 +5663,70 @@ impl MiscellaneousService {
     }
 
     pub async fn yank_integrations_data_for_user(&self, user_id: i32) -> Result<usize> {
-        if let Some(integrations) = self.user_by_id(user_id).await?.yank_integrations {
-            let mut progress_updates = vec![];
-            for integration in integrations.0.iter() {
-                let response = match &integration.settings {
-                    UserYankIntegrationSetting::Audiobookshelf { base_url, token } => {
+        let user = self.user_by_id(user_id).await?;
+        if let Some(integrations) = user.yank_integrations.clone() {
+            let mut integrations = integrations.0;
+            let mut updated_count = 0;
+            for integration in integrations.iter_mut() {
+                let (response, min_progress, max_progress) = match &integration.settings {
+                    UserYankIntegrationSetting::Audiobookshelf {
+                        base_url,
+                        token,
+                        min_progress,
+                        max_progress,
+                        library_id,
+                    } => (
                         self.integration_service
-                            .audiobookshelf_progress(base_url, token)
-                            .await
-                    }
+                            .audiobookshelf_progress(base_url, token, library_id.as_deref())
+                            .await,
+                        *min_progress,
+                        *max_progress,
+                    ),
                 };
-                if let Ok(data) = response {
-                    progress_updates.extend(data);
-                }
-            }
-            let mut updated_count = 0;
-            for pu in progress_updates.iter() {
-                if !(1..=95).contains(&pu.progress) {
-                    continue;
-                } else {
+                let progress_updates = match response {
+                    Ok(data) => data,
+                    Err(_) => continue,
+                };
+                for pu in progress_updates.iter() {
+                    let progress =
+                        match yank_progress_to_commit(pu.progress, min_progress, max_progress) {
+                            Some(p) => p,
+                            None => continue,
+                        };
+                    // nothing has changed since the last time this item was synced
+                    if integration.last_synced_progress.get(&pu.identifier) == Some(&progress) {
+                        continue;
+                    }
                     updated_count += 1;
+                    let IdObject { id } =
+                        self.commit_media(pu.lot, pu.source, &pu.identifier).await?;
+                    let resp = self
+                        .progress_update(
+                            ProgressUpdateInput {
+                                metadata_id: id,
+                                progress: Some(progress),
+                                date: Some(user.preferences.today()),
+                                show_season_number: None,
+                                show_episode_number: None,
+                                podcast_episode_number: None,
+                                identifier: None,
+                                rewatch: false,
+                                note: None,
+                            },
+                            user_id,
+                        )
+                        .await;
+                    if resp.is_ok() {
+                        integration
+                            .last_synced_progress
+                            .insert(pu.identifier.clone(), progress);
+                    }
                 }
-                let IdObject { id } = self.commit_media(pu.lot, pu.source, &pu.identifier).await?;
-                self.progress_update(
-                    ProgressUpdateInput {
-                        metadata_id: id,
-                        progress: Some(pu.progress),
-                        date: Some(Utc::now().date_naive()),
-                        show_season_number: None,
-                        show_episode_number: None,
-                        podcast_episode_number: None,
-                        identifier: None,
-                    },
-                    user_id,
-                )
-                .await
-                .ok();
+            }
+            if updated_count > 0 {
+                let mut user: user::ActiveModel = user.into();
+                user.yank_integrations = ActiveValue::Set(Some(UserYankIntegrations(integrations)));
+                user.update(&self.db).await?;
             }
             Ok(updated_count)
         } else {
@@ -3285,17 +5784,2704 @@ impl MiscellaneousService {
     }
 }
 
-fn modify_seen_elements(all_seen: &mut Vec<seen::Model>) {
-    all_seen.iter_mut().for_each(|s| {
-        if let Some(i) = s.extra_information.as_ref() {
-            match i {
-                SeenExtraInformation::Show(sea) => {
-                    s.show_information = Some(sea.clone());
-                }
-                SeenExtraInformation::Podcast(sea) => {
-                    s.podcast_information = Some(sea.clone());
-                }
-            };
+/// The day on which a `seen` item appears in the diary of a user.
+fn diary_date(seen: &seen::Model, offset: &FixedOffset) -> NaiveDate {
+    seen.finished_on
+        .unwrap_or_else(|| seen.last_updated_on.with_timezone(offset).date_naive())
+}
+
+/// Calculate streaks from distinct dates sorted in ascending order.
+fn calculate_streaks(dates: &[NaiveDate], today: NaiveDate) -> UserStreaks {
+    let mut streaks = UserStreaks::default();
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in dates.iter().copied() {
+        run = match previous {
+            Some(p) if date - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        streaks.longest = streaks.longest.max(run);
+        previous = Some(date);
+    }
+    if let Some(last) = previous {
+        // a finish date after today (for example after changing timezones) still
+        // keeps the streak going
+        let gap = today - last;
+        if gap <= Duration::days(1) {
+            streaks.current = run;
         }
-    });
+    }
+    streaks
+}
+
+/// Estimate where a media item should be resumed from, given the percentage of
+/// it that has been consumed.
+fn resume_position(specifics: &MediaSpecifics, progress: i32) -> ResumePosition {
+    let at = |total: i32| total * progress / 100;
+    let mut position = ResumePosition::default();
+    match specifics {
+        MediaSpecifics::Movie(MovieSpecifics { runtime })
+        | MediaSpecifics::AudioBook(AudioBookSpecifics { runtime }) => {
+            position.seconds = runtime.map(|r| at(r * 60));
+        }
+        MediaSpecifics::Book(b) => {
+            position.page = b.pages.map(at);
+        }
+        MediaSpecifics::Manga(m) => {
+            position.chapter = m.chapters.map(at);
+        }
+        MediaSpecifics::Anime(a) => {
+            position.episode = a.episodes.map(at);
+        }
+        _ => {}
+    }
+    position
+}
+
+/// The id for a new yank integration. Ids are never reused, even after the
+/// integration they belonged to has been deleted.
+fn next_yank_integration_id(last_id: i32, integrations: &[UserYankIntegration]) -> usize {
+    let last_id = usize::try_from(last_id).unwrap_or_default();
+    integrations.iter().map(|i| i.id).fold(last_id, usize::max) + 1
+}
+
+/// Lowercase a title and strip everything except letters and digits, so that
+/// titles differing only in case, spacing or punctuation compare equal.
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Only keep the first of the search results that have the same title and
+/// release year.
+fn dedup_search_results(items: Vec<SourcedMediaSearchItem>) -> Vec<SourcedMediaSearchItem> {
+    items
+        .into_iter()
+        .unique_by(|i| (normalize_title(&i.item.title), i.item.publish_year))
+        .collect()
+}
+
+/// The size of the intersection of two sets divided by the size of their union.
+fn jaccard_similarity(a: &HashSet<i32>, b: &HashSet<i32>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+fn enum_variant_references<T: std::fmt::Debug>(
+    variants: impl Iterator<Item = T>,
+) -> Vec<EnumVariantReference> {
+    variants
+        .map(|v| {
+            let name = format!("{:?}", v);
+            EnumVariantReference {
+                value: name.to_case(Case::UpperSnake),
+                display: name.to_case(Case::Title),
+            }
+        })
+        .collect()
+}
+
+fn validate_review(input: &PostReviewInput) -> Result<()> {
+    if !input.has_content() {
+        return Err(coded_error(
+            ErrorCode::Validation,
+            "A review must have a rating or some text".to_owned(),
+        ));
+    }
+    if input.spoils_up_to_episode.is_some() && input.spoils_up_to_season.is_none() {
+        return Err(coded_error(
+            ErrorCode::Validation,
+            "The season must be set along with the episode up to which a review has spoilers"
+                .to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// Decide which progress (if any) should be recorded for a value reported by a
+/// yank integration, given its configured thresholds.
+fn yank_progress_to_commit(progress: i32, min_progress: i32, max_progress: i32) -> Option<i32> {
+    if progress >= max_progress {
+        Some(100)
+    } else if progress >= min_progress {
+        Some(progress)
+    } else {
+        None
+    }
+}
+
+/// A fingerprint of the details returned by a provider, used to detect whether
+/// anything changed since the last update. It is stored in the database, so it
+/// must stay the same across builds and Rust versions.
+fn media_details_hash(details: &MediaDetails) -> String {
+    let serialized = serde_json::to_string(details).unwrap_or_default();
+    format!("{:016x}", fnv1a_hash(serialized.as_bytes()))
+}
+
+/// The 64 bit FNV-1a hash of the bytes.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+fn metadata_source_url(model: &metadata::Model) -> Option<String> {
+    let slug = model
+        .slug
+        .clone()
+        .unwrap_or_else(|| slug::slugify(&model.title));
+    let identifier = &model.identifier;
+    match model.source {
+        MetadataSource::Custom => None,
+        MetadataSource::Itunes => Some(format!(
+            "https://podcasts.apple.com/us/podcast/{slug}/id{identifier}"
+        )),
+        MetadataSource::GoogleBooks => Some(format!(
+            "https://www.google.co.in/books/edition/{slug}/{identifier}"
+        )),
+        MetadataSource::Audible => Some(format!("https://www.audible.com/pd/{slug}/{identifier}")),
+        MetadataSource::Openlibrary => {
+            Some(format!("https://openlibrary.org/works/{identifier}/{slug}"))
+        }
+        MetadataSource::Tmdb => {
+            let bw = match model.lot {
+                MetadataLot::Movie => "movie",
+                MetadataLot::Show => "tv",
+                _ => unreachable!(),
+            };
+            Some(format!(
+                "https://www.themoviedb.org/{bw}/{identifier}-{slug}"
+            ))
+        }
+        MetadataSource::Listennotes => Some(format!(
+            "https://www.listennotes.com/podcasts/{slug}-{identifier}"
+        )),
+        MetadataSource::Igdb => Some(format!("https://www.igdb.com/games/{slug}")),
+        MetadataSource::Anilist => {
+            let bw = match model.lot {
+                MetadataLot::Anime => "anime",
+                MetadataLot::Manga => "manga",
+                _ => unreachable!(),
+            };
+            Some(format!("https://anilist.co/{bw}/{identifier}/{slug}"))
+        }
+    }
+}
+
+/// Render the markdown description of a media item.
+fn description_to_html(description: &str) -> String {
+    markdown_to_html_opts(
+        description,
+        &Options {
+            compile: CompileOptions {
+                allow_dangerous_html: true,
+                allow_dangerous_protocol: true,
+                ..CompileOptions::default()
+            },
+            ..Options::default()
+        },
+    )
+    .unwrap()
+}
+
+/// The new value of an optional date, where `null` removes it.
+fn updated_date(input: MaybeUndefined<NaiveDate>, current: Option<NaiveDate>) -> Option<NaiveDate> {
+    match input {
+        MaybeUndefined::Undefined => current,
+        MaybeUndefined::Null => None,
+        MaybeUndefined::Value(date) => Some(date),
+    }
+}
+
+/// Delete a media item along with its genres, collection entries, seen history,
+/// reviews and the library entries of all users.
+async fn delete_metadata(db: &DatabaseConnection, metadata_id: i32) -> Result<()> {
+    MetadataToGenre::delete_many()
+        .filter(metadata_to_genre::Column::MetadataId.eq(metadata_id))
+        .exec(db)
+        .await?;
+    MetadataToCollection::delete_many()
+        .filter(metadata_to_collection::Column::MetadataId.eq(metadata_id))
+        .exec(db)
+        .await?;
+    Seen::delete_many()
+        .filter(seen::Column::MetadataId.eq(metadata_id))
+        .exec(db)
+        .await?;
+    Review::delete_many()
+        .filter(review::Column::MetadataId.eq(metadata_id))
+        .exec(db)
+        .await?;
+    UserToMetadata::delete_many()
+        .filter(user_to_metadata::Column::MetadataId.eq(metadata_id))
+        .exec(db)
+        .await?;
+    Metadata::delete_by_id(metadata_id).exec(db).await?;
+    Ok(())
+}
+
+/// Only keep the media items that do not have any `seen` entry for this user.
+fn filter_unseen_media(
+    select: &mut SelectStatement,
+    metadata_alias: &Alias,
+    seen_alias: &Alias,
+    user_id: i32,
+) {
+    select
+        .join_as(
+            JoinType::LeftJoin,
+            TempSeen::Table,
+            seen_alias.clone(),
+            Expr::col((metadata_alias.clone(), TempMetadata::Id))
+                .equals((seen_alias.clone(), TempSeen::MetadataId))
+                .and(Expr::col((seen_alias.clone(), TempSeen::UserId)).eq(user_id)),
+        )
+        .and_where(Expr::col((seen_alias.clone(), TempSeen::MetadataId)).is_null());
+}
+
+/// Order the media items by the number of times this user has completed them.
+fn sort_by_times_seen(
+    select: &mut SelectStatement,
+    metadata_alias: &Alias,
+    seen_alias: &Alias,
+    user_id: i32,
+    order_by: Order,
+) {
+    let times_seen = Alias::new("times_seen");
+    let sub_select = Query::select()
+        .column(TempSeen::MetadataId)
+        .expr_as(Func::count(Expr::col(TempSeen::Id)), times_seen.clone())
+        .from(TempSeen::Table)
+        .and_where(Expr::col(TempSeen::UserId).eq(user_id))
+        .and_where(Expr::col(TempSeen::Progress).eq(100))
+        .group_by_col(TempSeen::MetadataId)
+        .to_owned();
+    select
+        .join_subquery(
+            JoinType::LeftJoin,
+            sub_select,
+            seen_alias.clone(),
+            Expr::col((metadata_alias.clone(), TempMetadata::Id))
+                .equals((seen_alias.clone(), TempSeen::MetadataId)),
+        )
+        .order_by_expr(
+            Func::if_null(Expr::col((seen_alias.clone(), times_seen)), 0).into(),
+            order_by,
+        );
+}
+
+/// The languages supported by the provider of a source, and its default one.
+fn provider_languages(source: MetadataSource) -> (Vec<String>, String) {
+    match source {
+        MetadataSource::Itunes => (
+            ITunesService::supported_languages(),
+            ITunesService::default_language(),
+        ),
+        MetadataSource::Audible => (
+            AudibleService::supported_languages(),
+            AudibleService::default_language(),
+        ),
+        MetadataSource::Openlibrary => (
+            OpenlibraryService::supported_languages(),
+            OpenlibraryService::default_language(),
+        ),
+        MetadataSource::Tmdb => (
+            TmdbService::supported_languages(),
+            TmdbService::default_language(),
+        ),
+        MetadataSource::Listennotes => (
+            ListennotesService::supported_languages(),
+            ListennotesService::default_language(),
+        ),
+        MetadataSource::GoogleBooks => (
+            GoogleBooksService::supported_languages(),
+            GoogleBooksService::default_language(),
+        ),
+        MetadataSource::Igdb => (
+            IgdbService::supported_languages(),
+            IgdbService::default_language(),
+        ),
+        MetadataSource::Anilist => (
+            AnilistService::supported_languages(),
+            AnilistService::default_language(),
+        ),
+        MetadataSource::Custom => (
+            CustomService::supported_languages(),
+            CustomService::default_language(),
+        ),
+    }
+}
+
+/// Check that a search can be done in this language. Only some providers take
+/// the language per request, the others always use the configured one.
+fn validate_search_language(source: MetadataSource, language: &str) -> Result<()> {
+    if !matches!(source, MetadataSource::Tmdb | MetadataSource::Itunes) {
+        return Err(coded_error(
+            ErrorCode::Validation,
+            format!("{source:?} can only search in the configured language"),
+        ));
+    }
+    if !provider_languages(source).0.iter().any(|l| l == language) {
+        return Err(coded_error(
+            ErrorCode::Validation,
+            format!("The language {language:?} is not supported by {source:?}"),
+        ));
+    }
+    Ok(())
+}
+
+/// The condition that selects the media list items that come after a cursor,
+/// when the list is sorted by the column of the cursor and then by the id.
+fn media_list_keyset_condition(
+    cursor: &MediaListCursor,
+    sort_by: MediaSortBy,
+    order: MediaSortOrder,
+    metadata_alias: &Alias,
+    mtu_alias: &Alias,
+) -> Result<Cond> {
+    let past = |col: Expr, value: Value| match order {
+        MediaSortOrder::Asc => col.gt(value),
+        MediaSortOrder::Desc => col.lt(value),
+    };
+    let id_after = Expr::col((metadata_alias.clone(), TempMetadata::Id)).gt(cursor.id);
+    let condition = match (&cursor.key, sort_by) {
+        (MediaListCursorKey::Title(title), MediaSortBy::Title) => {
+            let col = || Expr::col((metadata_alias.clone(), TempMetadata::Title));
+            Cond::any()
+                .add(past(col(), title.clone().into()))
+                .add(col().eq(title.clone()).and(id_after))
+        }
+        (MediaListCursorKey::ReleaseDate(year), MediaSortBy::ReleaseDate) => {
+            let col = || Expr::col((metadata_alias.clone(), TempMetadata::PublishYear));
+            // media without a release year are sorted last
+            match year {
+                Some(y) => Cond::any()
+                    .add(past(col(), (*y).into()))
+                    .add(col().eq(*y).and(id_after))
+                    .add(col().is_null()),
+                None => Cond::all().add(col().is_null()).add(id_after),
+            }
+        }
+        (MediaListCursorKey::LastUpdated(on), MediaSortBy::LastUpdated) => {
+            let col = || Expr::col((mtu_alias.clone(), TempUserToMetadata::LastUpdatedOn));
+            Cond::any()
+                .add(past(col(), (*on).into()))
+                .add(col().eq(*on).and(id_after))
+        }
+        _ => {
+            return Err(coded_error(
+                ErrorCode::Validation,
+                "This cursor can not be used with the current sort".to_owned(),
+            ))
+        }
+    };
+    Ok(condition)
+}
+
+/// Order the media items by the runtime stored in their specifics. Items that
+/// do not have one are sorted last.
+fn sort_by_runtime(
+    select: &mut SelectStatement,
+    metadata_alias: &Alias,
+    backend: DatabaseBackend,
+    order_by: Order,
+) {
+    let alias = metadata_alias.to_string();
+    let runtime = match backend {
+        DatabaseBackend::MySql => {
+            format!("CAST(JSON_EXTRACT(`{alias}`.`specifics`, '$.d.runtime') AS SIGNED)")
+        }
+        DatabaseBackend::Postgres => {
+            format!(r#"CAST("{alias}"."specifics" -> 'd' ->> 'runtime' AS INTEGER)"#)
+        }
+        DatabaseBackend::Sqlite => format!(r#"json_extract("{alias}"."specifics", '$.d.runtime')"#),
+    };
+    select.order_by_expr_with_nulls(Expr::cust(&runtime), order_by, NullOrdering::Last);
+}
+
+fn review_item_from_model(review: review::Model, user: user::Model) -> ReviewItem {
+    let (show_se, show_ep, podcast_ep) = match review.extra_information {
+        Some(s) => match s {
+            SeenExtraInformation::Show(d) => (Some(d.season), Some(d.episode), None),
+            SeenExtraInformation::Podcast(d) => (None, None, Some(d.episode)),
+        },
+        None => (None, None, None),
+    };
+    ReviewItem {
+        id: review.id,
+        posted_on: review.posted_on,
+        rating: review.rating,
+        spoiler: review.spoiler,
+        text: review.text,
+        visibility: review.visibility,
+        season_number: show_se,
+        episode_number: show_ep,
+        podcast_episode_id: podcast_ep,
+        posted_by: ReviewPostedBy {
+            id: user.id,
+            name: user.name,
+        },
+        reaction_counts: vec![],
+        pinned: review.pinned,
+        spoils_up_to: review.spoils_up_to,
+    }
+}
+
+async fn delete_reviews(
+    db: &DatabaseConnection,
+    user_id: i32,
+    review_ids: Vec<i32>,
+) -> Result<DeleteReviewsResult> {
+    let txn = db.begin().await?;
+    let (own, others): (Vec<_>, Vec<_>) = Review::find()
+        .filter(review::Column::Id.is_in(review_ids))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .partition(|r| r.user_id == user_id);
+    let deleted = Review::delete_many()
+        .filter(review::Column::Id.is_in(own.into_iter().map(|r| r.id)))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    txn.commit().await?;
+    Ok(DeleteReviewsResult {
+        deleted,
+        rejected_ids: others.into_iter().map(|r| r.id).collect(),
+    })
+}
+
+/// Create or update the `seen` item of the user for a progress update. If the
+/// update comes from a source that already created a `seen` item, that item is
+/// returned instead and the second value is `false`.
+async fn apply_progress_update<C>(
+    db: &C,
+    input: ProgressUpdateInput,
+    user_id: i32,
+    today: NaiveDate,
+) -> Result<(seen::Model, bool)>
+where
+    C: ConnectionTrait,
+{
+    let prev_seen = Seen::find()
+        .filter(seen::Column::Progress.lt(100))
+        .filter(seen::Column::UserId.eq(user_id))
+        .filter(seen::Column::Dropped.ne(true))
+        .filter(seen::Column::MetadataId.eq(i32::from(input.metadata_id)))
+        .order_by_desc(seen::Column::LastUpdatedOn)
+        .all(db)
+        .await
+        .unwrap();
+    #[derive(Debug, Serialize, Deserialize, Enum, Clone, PartialEq, Eq, Copy)]
+    pub enum ProgressUpdateAction {
+        Update,
+        Now,
+        InThePast,
+        JustStarted,
+        Drop,
+    }
+    let action = match input.progress {
+        None => ProgressUpdateAction::Drop,
+        Some(p) => {
+            if p == 100 {
+                match input.date {
+                    None => ProgressUpdateAction::InThePast,
+                    Some(u) => {
+                        if today == u {
+                            if prev_seen.is_empty() || input.rewatch {
+                                ProgressUpdateAction::Now
+                            } else {
+                                ProgressUpdateAction::Update
+                            }
+                        } else {
+                            ProgressUpdateAction::InThePast
+                        }
+                    }
+                }
+            } else if prev_seen.is_empty() || input.rewatch {
+                ProgressUpdateAction::JustStarted
+            } else {
+                ProgressUpdateAction::Update
+            }
+        }
+    };
+    if let Some(s) = imported_seen_item(db, user_id, input.metadata_id, &input.identifier).await? {
+        return Ok((s, false));
+    }
+    let err = || {
+        Err(coded_error(
+            ErrorCode::NotFound,
+            "There is no `seen` item underway".to_owned(),
+        ))
+    };
+    let seen_item = match action {
+        ProgressUpdateAction::Update => {
+            let progress = input.progress.unwrap();
+            let mut last_seen: seen::ActiveModel = prev_seen[0].clone().into();
+            last_seen.progress = ActiveValue::Set(progress);
+            last_seen.last_updated_on = ActiveValue::Set(Utc::now());
+            if progress == 100 {
+                last_seen.finished_on = ActiveValue::Set(Some(today));
+            }
+            if let Some(note) = input.note {
+                last_seen.note = ActiveValue::Set(seen_note(note));
+            }
+            last_seen.update(db).await.unwrap()
+        }
+        ProgressUpdateAction::Drop => {
+            let last_seen = Seen::find()
+                .filter(seen::Column::UserId.eq(user_id))
+                .filter(seen::Column::Dropped.ne(true))
+                .filter(seen::Column::MetadataId.eq(i32::from(input.metadata_id)))
+                .order_by_desc(seen::Column::LastUpdatedOn)
+                .one(db)
+                .await
+                .unwrap();
+            match last_seen {
+                Some(ls) => {
+                    let mut last_seen: seen::ActiveModel = ls.into();
+                    last_seen.dropped = ActiveValue::Set(true);
+                    last_seen.last_updated_on = ActiveValue::Set(Utc::now());
+                    if let Some(note) = input.note {
+                        last_seen.note = ActiveValue::Set(seen_note(note));
+                    }
+                    last_seen.update(db).await.unwrap()
+                }
+                None => {
+                    return err();
+                }
+            }
+        }
+        ProgressUpdateAction::Now
+        | ProgressUpdateAction::InThePast
+        | ProgressUpdateAction::JustStarted => {
+            let meta = Metadata::find_by_id(input.metadata_id)
+                .one(db)
+                .await
+                .unwrap()
+                .unwrap();
+            let finished_on = if action == ProgressUpdateAction::JustStarted {
+                None
+            } else {
+                input.date
+            };
+            let (progress, started_on) = if matches!(action, ProgressUpdateAction::JustStarted) {
+                (0, Some(today))
+            } else {
+                (100, None)
+            };
+            let mut seen_insert = seen::ActiveModel {
+                progress: ActiveValue::Set(progress),
+                user_id: ActiveValue::Set(user_id),
+                metadata_id: ActiveValue::Set(i32::from(input.metadata_id)),
+                started_on: ActiveValue::Set(started_on),
+                finished_on: ActiveValue::Set(finished_on),
+                last_updated_on: ActiveValue::Set(Utc::now()),
+                identifier: ActiveValue::Set(input.identifier),
+                note: ActiveValue::Set(input.note.and_then(seen_note)),
+                ..Default::default()
+            };
+            let extra_information = if meta.lot == MetadataLot::Show {
+                let season = input.show_season_number.unwrap();
+                let episode = input.show_episode_number.unwrap();
+                if progress == 100
+                    && !input.rewatch
+                    && has_completed_episode(db, user_id, meta.id, season, episode).await?
+                {
+                    return Err(coded_error(
+                        ErrorCode::Validation,
+                        "This episode has already been seen, mark it as a rewatch \
+                         to see it again"
+                            .to_owned(),
+                    ));
+                }
+                Some(SeenExtraInformation::Show(SeenShowExtraInformation {
+                    season,
+                    episode,
+                }))
+            } else if meta.lot == MetadataLot::Podcast {
+                Some(SeenExtraInformation::Podcast(SeenPodcastExtraInformation {
+                    episode: input.podcast_episode_number.unwrap(),
+                }))
+            } else {
+                None
+            };
+            if input.rewatch {
+                // the first time something is seen does not have a number, so it
+                // counts as the first playthrough
+                let last_playthrough = Seen::find()
+                    .filter(seen::Column::UserId.eq(user_id))
+                    .filter(seen::Column::MetadataId.eq(input.metadata_id))
+                    .all(db)
+                    .await?
+                    .into_iter()
+                    .filter(|s| s.extra_information == extra_information)
+                    .map(|s| s.playthrough.unwrap_or(1))
+                    .max()
+                    .unwrap_or(0);
+                seen_insert.playthrough = ActiveValue::Set(Some(last_playthrough + 1));
+            }
+            seen_insert.extra_information = ActiveValue::Set(extra_information);
+
+            seen_insert.insert(db).await.unwrap()
+        }
+    };
+    Ok((seen_item, true))
+}
+
+/// The `seen` item of the user for this media that was already created for this
+/// import identifier, if any.
+async fn imported_seen_item<C>(
+    db: &C,
+    user_id: i32,
+    metadata_id: i32,
+    identifier: &Option<String>,
+) -> Result<Option<seen::Model>>
+where
+    C: ConnectionTrait,
+{
+    let identifier = match identifier {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    let seen_item = Seen::find()
+        .filter(seen::Column::UserId.eq(user_id))
+        .filter(seen::Column::MetadataId.eq(metadata_id))
+        .filter(seen::Column::Identifier.eq(identifier.to_owned()))
+        .one(db)
+        .await?;
+    Ok(seen_item)
+}
+
+/// Clear the `dropped` flag on the latest dropped `seen` item of the user for
+/// this media, returning the updated row.
+async fn undrop_latest_seen<C>(
+    db: &C,
+    user_id: i32,
+    metadata_id: i32,
+) -> Result<Option<seen::Model>>
+where
+    C: ConnectionTrait,
+{
+    let last_dropped = Seen::find()
+        .filter(seen::Column::UserId.eq(user_id))
+        .filter(seen::Column::MetadataId.eq(metadata_id))
+        .filter(seen::Column::Dropped.eq(true))
+        .order_by_desc(seen::Column::LastUpdatedOn)
+        .one(db)
+        .await?;
+    let Some(last_dropped) = last_dropped else {
+        return Ok(None);
+    };
+    let mut last_dropped: seen::ActiveModel = last_dropped.into();
+    last_dropped.dropped = ActiveValue::Set(false);
+    last_dropped.last_updated_on = ActiveValue::Set(Utc::now());
+    Ok(Some(last_dropped.update(db).await?))
+}
+
+async fn find_collection_by_name<C>(
+    db: &C,
+    user_id: i32,
+    name: &str,
+) -> Result<Option<collection::Model>>
+where
+    C: ConnectionTrait,
+{
+    let collection = Collection::find()
+        .filter(collection::Column::Name.eq(name))
+        .filter(collection::Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+    Ok(collection)
+}
+
+fn collection_name_taken_error() -> Error {
+    coded_error(
+        ErrorCode::Validation,
+        "A collection with this name already exists".to_owned(),
+    )
+}
+
+/// Change the name of a collection. Its media are kept since they reference the
+/// collection by its id.
+async fn rename_collection<C>(
+    db: &C,
+    collection: collection::Model,
+    name: String,
+) -> Result<IdObject>
+where
+    C: ConnectionTrait,
+{
+    let name = name.trim().to_owned();
+    if name.is_empty() {
+        return Err(coded_error(
+            ErrorCode::Validation,
+            "The name of a collection can not be empty".to_owned(),
+        ));
+    }
+    if let Some(other) = find_collection_by_name(db, collection.user_id, &name).await? {
+        if other.id != collection.id {
+            return Err(collection_name_taken_error());
+        }
+    }
+    let id = collection.id;
+    let mut collection: collection::ActiveModel = collection.into();
+    collection.name = ActiveValue::Set(name);
+    collection.update(db).await?;
+    Ok(IdObject { id })
+}
+
+/// The collection of the user with this name. Collections of other users are
+/// treated as missing.
+async fn user_collection_by_name<C>(db: &C, user_id: i32, name: &str) -> Result<collection::Model>
+where
+    C: ConnectionTrait,
+{
+    find_collection_by_name(db, user_id, name)
+        .await?
+        .ok_or_else(|| coded_error(ErrorCode::NotFound, "Collection not found".to_owned()))
+}
+
+async fn add_media_to_collection<C>(
+    db: &C,
+    user_id: i32,
+    input: AddMediaToCollection,
+) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    let collection = user_collection_by_name(db, user_id, &input.collection_name).await?;
+    let col = metadata_to_collection::ActiveModel {
+        metadata_id: ActiveValue::Set(input.media_id),
+        collection_id: ActiveValue::Set(collection.id),
+    };
+    Ok(col.insert(db).await.is_ok())
+}
+
+/// Add a finished media item to the completed collection of the user, creating
+/// the collection if needed. Nothing happens if no collection is configured, or
+/// for shows and podcasts since they are seen one episode at a time.
+async fn add_media_to_completed_collection<C>(
+    db: &C,
+    collection_name: Option<&str>,
+    user_id: i32,
+    metadata_id: i32,
+    metadata_lot: MetadataLot,
+) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    let Some(name) = collection_name else {
+        return Ok(());
+    };
+    if matches!(metadata_lot, MetadataLot::Show | MetadataLot::Podcast) {
+        return Ok(());
+    }
+    create_or_update_collection(
+        db,
+        user_id,
+        CreateOrUpdateCollectionInput {
+            name: name.to_owned(),
+            description: None,
+            visibility: None,
+            update_id: None,
+        },
+    )
+    .await?;
+    add_media_to_collection(
+        db,
+        user_id,
+        AddMediaToCollection {
+            collection_name: name.to_owned(),
+            media_id: metadata_id,
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+async fn remove_media_item_from_collection<C>(
+    db: &C,
+    user_id: i32,
+    metadata_id: i32,
+    collection_name: &str,
+) -> Result<IdObject>
+where
+    C: ConnectionTrait,
+{
+    let collection = user_collection_by_name(db, user_id, collection_name).await?;
+    let col = metadata_to_collection::ActiveModel {
+        metadata_id: ActiveValue::Set(metadata_id),
+        collection_id: ActiveValue::Set(collection.id),
+    };
+    col.delete(db).await.ok();
+    Ok(IdObject { id: collection.id })
+}
+
+/// Create a collection if the user does not have one with this name, or update
+/// the one with `update_id`. Names are unique per user, so a collection created
+/// by a concurrent request in the meantime is returned instead.
+async fn create_or_update_collection<C>(
+    db: &C,
+    user_id: i32,
+    input: CreateOrUpdateCollectionInput,
+) -> Result<IdObject>
+where
+    C: ConnectionTrait,
+{
+    let existing = find_collection_by_name(db, user_id, &input.name).await?;
+    match (&existing, input.update_id) {
+        (Some(m), None) => return Ok(IdObject { id: m.id }),
+        (Some(m), Some(update_id)) if m.id != update_id => {
+            return Err(collection_name_taken_error());
+        }
+        _ => {}
+    }
+    let col = collection::ActiveModel {
+        id: match input.update_id {
+            Some(i) => ActiveValue::Unchanged(i),
+            None => ActiveValue::NotSet,
+        },
+        name: ActiveValue::Set(input.name.clone()),
+        user_id: ActiveValue::Set(user_id),
+        description: ActiveValue::Set(input.description),
+        visibility: match input.visibility {
+            None => ActiveValue::NotSet,
+            Some(v) => ActiveValue::Set(v),
+        },
+        ..Default::default()
+    };
+    match col.save(db).await {
+        Ok(saved) => Ok(IdObject {
+            id: saved.id.unwrap(),
+        }),
+        Err(_) => match find_collection_by_name(db, user_id, &input.name).await? {
+            Some(m) if input.update_id.is_none() => Ok(IdObject { id: m.id }),
+            Some(m) if Some(m.id) != input.update_id => Err(collection_name_taken_error()),
+            _ => Err(Error::new(
+                "There was an error creating the collection".to_owned(),
+            )),
+        },
+    }
+}
+
+async fn export_media_page<C>(
+    db: &C,
+    user_id: i32,
+    page: u64,
+    lots: Option<Vec<MetadataLot>>,
+) -> Result<ExportMediaPage>
+where
+    C: ConnectionTrait,
+{
+    if page == 0 {
+        return Err(coded_error(
+            ErrorCode::Validation,
+            "Pages start at 1".to_owned(),
+        ));
+    }
+    let paginator = Metadata::find()
+        .filter(
+            metadata::Column::Id.in_subquery(
+                UserToMetadata::find()
+                    .select_only()
+                    .column(user_to_metadata::Column::MetadataId)
+                    .filter(user_to_metadata::Column::UserId.eq(user_id))
+                    .into_query(),
+            ),
+        )
+        .apply_if(lots, |query, v| {
+            query.filter(metadata::Column::Lot.is_in(v))
+        })
+        .order_by(metadata::Column::Id, Order::Asc)
+        .paginate(db, EXPORT_PAGE_SIZE);
+    let total_pages = paginator.num_pages().await?;
+    let metas = paginator.fetch_page(page - 1).await?;
+    let next_page = (page < total_pages).then_some(page + 1);
+    Ok(ExportMediaPage {
+        items: export_media(db, user_id, metas).await?,
+        next_page,
+    })
+}
+
+/// Attach the seen history and reviews of the user to the media. They are
+/// fetched for all the media at once instead of once per media.
+async fn export_media<C>(
+    db: &C,
+    user_id: i32,
+    metas: Vec<metadata::Model>,
+) -> Result<Vec<ExportMedia>>
+where
+    C: ConnectionTrait,
+{
+    let metadata_ids = metas.iter().map(|m| m.id).collect::<Vec<_>>();
+    let mut seens = Seen::find()
+        .filter(seen::Column::UserId.eq(user_id))
+        .filter(seen::Column::MetadataId.is_in(metadata_ids.clone()))
+        .order_by_asc(seen::Column::Id)
+        .all(db)
+        .await?;
+    modify_seen_elements(&mut seens);
+    let mut seens_by_metadata: HashMap<i32, Vec<seen::Model>> = HashMap::new();
+    for s in seens {
+        seens_by_metadata.entry(s.metadata_id).or_default().push(s);
+    }
+    let reviews = Review::find()
+        .filter(review::Column::UserId.eq(user_id))
+        .filter(review::Column::MetadataId.is_in(metadata_ids))
+        .order_by_asc(review::Column::Id)
+        .all(db)
+        .await?;
+    let mut reviews_by_metadata: HashMap<i32, Vec<review::Model>> = HashMap::new();
+    for r in reviews {
+        reviews_by_metadata
+            .entry(r.metadata_id)
+            .or_default()
+            .push(r);
+    }
+
+    let mut resp = vec![];
+    for m in metas {
+        let mut exp = ExportMedia {
+            ryot_id: m.id,
+            title: m.title,
+            lot: m.lot,
+            audible_id: None,
+            custom_id: None,
+            igdb_id: None,
+            listennotes_id: None,
+            google_books_id: None,
+            openlibrary_id: None,
+            tmdb_id: None,
+            itunes_id: None,
+            anilist_id: None,
+            seen_history: seens_by_metadata.remove(&m.id).unwrap_or_default(),
+            user_reviews: reviews_by_metadata.remove(&m.id).unwrap_or_default(),
+        };
+        match m.source {
+            MetadataSource::Audible => exp.audible_id = Some(m.identifier),
+            MetadataSource::Custom => exp.custom_id = Some(m.identifier),
+            MetadataSource::GoogleBooks => exp.google_books_id = Some(m.identifier),
+            MetadataSource::Igdb => exp.igdb_id = Some(m.identifier),
+            MetadataSource::Listennotes => exp.listennotes_id = Some(m.identifier),
+            MetadataSource::Openlibrary => exp.openlibrary_id = Some(m.identifier),
+            MetadataSource::Tmdb => exp.tmdb_id = Some(m.identifier),
+            MetadataSource::Anilist => exp.anilist_id = Some(m.identifier),
+            MetadataSource::Itunes => exp.itunes_id = Some(m.identifier),
+        };
+        resp.push(exp);
+    }
+    Ok(resp)
+}
+
+/// Mark the email of the user that was issued this token as verified. The token
+/// can only be used once.
+async fn verify_email<C>(db: &C, token: &str) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    let Some(user) = User::find()
+        .filter(user::Column::EmailVerificationToken.eq(token))
+        .one(db)
+        .await?
+    else {
+        return Ok(false);
+    };
+    let mut user: user::ActiveModel = user.into();
+    user.email_verified = ActiveValue::Set(true);
+    user.email_verification_token = ActiveValue::Set(None);
+    user.update(db).await?;
+    Ok(true)
+}
+
+/// What the provider answered when a media item was refreshed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RefreshResult {
+    Found,
+    /// The provider says that the media item does not exist anymore.
+    NotFound,
+    /// Network, rate limit and other errors that say nothing about the item.
+    Failed,
+}
+
+impl RefreshResult {
+    fn of<T>(result: &Result<T>) -> Self {
+        match result {
+            Ok(_) => Self::Found,
+            Err(e) if has_error_code(e, ErrorCode::NotFound) => Self::NotFound,
+            Err(_) => Self::Failed,
+        }
+    }
+}
+
+/// Reset the number of failed refreshes of a media item if its provider
+/// returned details for it, and increase it if the provider does not have it
+/// anymore. Custom media are never refreshed from a provider.
+async fn record_refresh_result<C>(db: &C, metadata_id: i32, result: RefreshResult) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    let failed_refreshes = match result {
+        RefreshResult::Found => Expr::value(0),
+        RefreshResult::NotFound => Expr::col(metadata::Column::FailedRefreshes).add(1),
+        RefreshResult::Failed => return Ok(()),
+    };
+    Metadata::update_many()
+        .col_expr(metadata::Column::FailedRefreshes, failed_refreshes)
+        .filter(metadata::Column::Id.eq(metadata_id))
+        .filter(metadata::Column::Source.ne(MetadataSource::Custom))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+fn is_unavailable(failed_refreshes: i32, unavailable_after: i32) -> bool {
+    unavailable_after > 0 && failed_refreshes >= unavailable_after
+}
+
+/// Reassign everything that belongs to a user to another user. Rows the other
+/// user already has an equivalent of are dropped, and collections with the same
+/// name are merged into the collection of the other user.
+async fn transfer_user_library(
+    db: &DatabaseConnection,
+    from_user_id: i32,
+    to_user_id: i32,
+) -> Result<()> {
+    let txn = db.begin().await?;
+
+    let existing_metadata_ids: Vec<i32> = UserToMetadata::find()
+        .select_only()
+        .column(user_to_metadata::Column::MetadataId)
+        .filter(user_to_metadata::Column::UserId.eq(to_user_id))
+        .into_tuple()
+        .all(&txn)
+        .await?;
+    UserToMetadata::delete_many()
+        .filter(user_to_metadata::Column::UserId.eq(from_user_id))
+        .filter(user_to_metadata::Column::MetadataId.is_in(existing_metadata_ids))
+        .exec(&txn)
+        .await?;
+    UserToMetadata::update_many()
+        .col_expr(user_to_metadata::Column::UserId, Expr::value(to_user_id))
+        .filter(user_to_metadata::Column::UserId.eq(from_user_id))
+        .exec(&txn)
+        .await?;
+
+    Seen::update_many()
+        .col_expr(seen::Column::UserId, Expr::value(to_user_id))
+        .filter(seen::Column::UserId.eq(from_user_id))
+        .exec(&txn)
+        .await?;
+
+    // the other user might already have pinned a review of the same media
+    let pinned_metadata_ids: Vec<i32> = Review::find()
+        .select_only()
+        .column(review::Column::MetadataId)
+        .filter(review::Column::UserId.eq(to_user_id))
+        .filter(review::Column::Pinned.eq(true))
+        .into_tuple()
+        .all(&txn)
+        .await?;
+    Review::update_many()
+        .col_expr(review::Column::Pinned, Expr::value(false))
+        .filter(review::Column::UserId.eq(from_user_id))
+        .filter(review::Column::MetadataId.is_in(pinned_metadata_ids))
+        .exec(&txn)
+        .await?;
+    Review::update_many()
+        .col_expr(review::Column::UserId, Expr::value(to_user_id))
+        .filter(review::Column::UserId.eq(from_user_id))
+        .exec(&txn)
+        .await?;
+
+    let existing_review_ids: Vec<i32> = ReviewReaction::find()
+        .select_only()
+        .column(review_reaction::Column::ReviewId)
+        .filter(review_reaction::Column::UserId.eq(to_user_id))
+        .into_tuple()
+        .all(&txn)
+        .await?;
+    ReviewReaction::delete_many()
+        .filter(review_reaction::Column::UserId.eq(from_user_id))
+        .filter(review_reaction::Column::ReviewId.is_in(existing_review_ids))
+        .exec(&txn)
+        .await?;
+    ReviewReaction::update_many()
+        .col_expr(review_reaction::Column::UserId, Expr::value(to_user_id))
+        .filter(review_reaction::Column::UserId.eq(from_user_id))
+        .exec(&txn)
+        .await?;
+
+    let existing_exercise_ids: Vec<i32> = UserExerciseFavorite::find()
+        .select_only()
+        .column(user_exercise_favorite::Column::ExerciseId)
+        .filter(user_exercise_favorite::Column::UserId.eq(to_user_id))
+        .into_tuple()
+        .all(&txn)
+        .await?;
+    UserExerciseFavorite::delete_many()
+        .filter(user_exercise_favorite::Column::UserId.eq(from_user_id))
+        .filter(user_exercise_favorite::Column::ExerciseId.is_in(existing_exercise_ids))
+        .exec(&txn)
+        .await?;
+    UserExerciseFavorite::update_many()
+        .col_expr(
+            user_exercise_favorite::Column::UserId,
+            Expr::value(to_user_id),
+        )
+        .filter(user_exercise_favorite::Column::UserId.eq(from_user_id))
+        .exec(&txn)
+        .await?;
+
+    for col in Collection::find()
+        .filter(collection::Column::UserId.eq(from_user_id))
+        .all(&txn)
+        .await?
+    {
+        let Some(target) = find_collection_by_name(&txn, to_user_id, &col.name).await? else {
+            let mut col: collection::ActiveModel = col.into();
+            col.user_id = ActiveValue::Set(to_user_id);
+            col.update(&txn).await?;
+            continue;
+        };
+        let existing_metadata_ids: Vec<i32> = MetadataToCollection::find()
+            .select_only()
+            .column(metadata_to_collection::Column::MetadataId)
+            .filter(metadata_to_collection::Column::CollectionId.eq(target.id))
+            .into_tuple()
+            .all(&txn)
+            .await?;
+        MetadataToCollection::delete_many()
+            .filter(metadata_to_collection::Column::CollectionId.eq(col.id))
+            .filter(metadata_to_collection::Column::MetadataId.is_in(existing_metadata_ids))
+            .exec(&txn)
+            .await?;
+        MetadataToCollection::update_many()
+            .col_expr(
+                metadata_to_collection::Column::CollectionId,
+                Expr::value(target.id),
+            )
+            .filter(metadata_to_collection::Column::CollectionId.eq(col.id))
+            .exec(&txn)
+            .await?;
+        col.delete(&txn).await?;
+    }
+
+    MediaImportReport::update_many()
+        .col_expr(media_import_report::Column::UserId, Expr::value(to_user_id))
+        .filter(media_import_report::Column::UserId.eq(from_user_id))
+        .exec(&txn)
+        .await?;
+    Metadata::update_many()
+        .col_expr(metadata::Column::CreatedBy, Expr::value(to_user_id))
+        .filter(metadata::Column::CreatedBy.eq(from_user_id))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(())
+}
+
+/// The media of a franchise other than `metadata` itself, along with the ids
+/// of the ones that are already in the database.
+async fn franchise_media_items<C>(
+    db: &C,
+    metadata: &metadata::Model,
+    parts: Vec<MediaSearchItem>,
+) -> Result<Vec<MediaSearchItemResponse>>
+where
+    C: ConnectionTrait,
+{
+    let parts = parts
+        .into_iter()
+        .filter(|p| p.identifier != metadata.identifier)
+        .collect::<Vec<_>>();
+    let database_ids: HashMap<String, i32> = Metadata::find()
+        .select_only()
+        .column(metadata::Column::Identifier)
+        .column(metadata::Column::Id)
+        .filter(metadata::Column::Source.eq(metadata.source))
+        .filter(metadata::Column::Lot.eq(metadata.lot))
+        .filter(metadata::Column::Identifier.is_in(parts.iter().map(|p| p.identifier.clone())))
+        .into_tuple::<(String, i32)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+    Ok(parts
+        .into_iter()
+        .map(|item| MediaSearchItemResponse {
+            database_id: database_ids.get(&item.identifier).copied(),
+            item,
+        })
+        .collect())
+}
+
+/// Add up the media a user has completed. Shows and podcasts are counted once
+/// no matter how many of their episodes have been seen.
+async fn summarize_completed_media(
+    db: &DatabaseConnection,
+    user_id: i32,
+    use_average_runtime: bool,
+) -> Result<UserMediaSummary> {
+    let mut summary = UserMediaSummary::default();
+    let mut seen_items = Seen::find()
+        .filter(seen::Column::UserId.eq(user_id))
+        .filter(seen::Column::Progress.eq(100))
+        .find_also_related(Metadata)
+        .stream(db)
+        .await?;
+
+    let mut unique_shows = HashSet::new();
+    let mut unique_show_seasons = HashSet::new();
+    let mut unique_podcasts = HashSet::new();
+    let mut unique_podcast_episodes = HashSet::new();
+    // the media that have already been counted, so that consuming them
+    // again only adds to the totals
+    let mut unique_completed = HashSet::new();
+    while let Some((seen, metadata)) = seen_items.try_next().await.unwrap() {
+        let meta = metadata.to_owned().unwrap();
+        let first_completion = unique_completed.insert(seen.metadata_id);
+        match meta.specifics {
+            MediaSpecifics::AudioBook(item) => {
+                summary.audio_books.total_played += 1;
+                if first_completion {
+                    summary.audio_books.played += 1;
+                }
+                if let Some(r) = item.runtime {
+                    summary.audio_books.runtime += r;
+                }
+            }
+            MediaSpecifics::Anime(item) => {
+                summary.anime.total_watched += 1;
+                if first_completion {
+                    summary.anime.watched += 1;
+                }
+                if let Some(r) = item.episodes {
+                    summary.anime.episodes += r;
+                }
+            }
+            MediaSpecifics::Manga(item) => {
+                summary.manga.total_read += 1;
+                if first_completion {
+                    summary.manga.read += 1;
+                }
+                if let Some(r) = item.chapters {
+                    summary.manga.chapters += r;
+                }
+            }
+            MediaSpecifics::Book(item) => {
+                summary.books.total_read += 1;
+                if first_completion {
+                    summary.books.read += 1;
+                }
+                if let Some(pg) = item.pages {
+                    summary.books.pages += pg;
+                }
+            }
+            MediaSpecifics::Podcast(item) => {
+                unique_podcasts.insert(seen.metadata_id);
+                let average_runtime = if use_average_runtime {
+                    let runtimes = item
+                        .episodes
+                        .iter()
+                        .filter_map(|e| e.runtime)
+                        .collect::<Vec<_>>();
+                    if runtimes.is_empty() {
+                        None
+                    } else {
+                        Some(runtimes.iter().sum::<i32>() / i32::try_from(runtimes.len()).unwrap())
+                    }
+                } else {
+                    None
+                };
+                for episode in item.episodes {
+                    match seen.extra_information.to_owned() {
+                        None => continue,
+                        Some(sei) => match sei {
+                            SeenExtraInformation::Show(_) => unreachable!(),
+                            SeenExtraInformation::Podcast(s) => {
+                                if s.episode == episode.number {
+                                    if let Some(r) = episode.runtime.or(average_runtime) {
+                                        summary.podcasts.runtime += r;
+                                    }
+                                    unique_podcast_episodes.insert((s.episode, episode.id));
+                                }
+                            }
+                        },
+                    }
+                }
+            }
+            MediaSpecifics::Movie(item) => {
+                summary.movies.total_watched += 1;
+                if first_completion {
+                    summary.movies.watched += 1;
+                }
+                if let Some(r) = item.runtime {
+                    summary.movies.runtime += r;
+                }
+            }
+            MediaSpecifics::Show(item) => {
+                unique_shows.insert(seen.metadata_id);
+                let average_runtime = if use_average_runtime {
+                    item.average_episode_runtime
+                } else {
+                    None
+                };
+                for season in item.seasons {
+                    for episode in season.episodes {
+                        match seen.extra_information.to_owned().unwrap() {
+                            SeenExtraInformation::Podcast(_) => unreachable!(),
+                            SeenExtraInformation::Show(s) => {
+                                if s.season == season.season_number
+                                    && s.episode == episode.episode_number
+                                {
+                                    if let Some(r) = episode.runtime.or(average_runtime) {
+                                        summary.shows.runtime += r;
+                                    }
+                                    summary.shows.watched_episodes += 1;
+                                    unique_show_seasons.insert((s.season, season.id));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            MediaSpecifics::VideoGame(_item) => {
+                summary.video_games.total_played += 1;
+                if first_completion {
+                    summary.video_games.played += 1;
+                }
+            }
+            MediaSpecifics::Unknown => {}
+        }
+    }
+
+    summary.podcasts.played += i32::try_from(unique_podcasts.len()).unwrap();
+    summary.podcasts.played_episodes += i32::try_from(unique_podcast_episodes.len()).unwrap();
+
+    summary.shows.watched = i32::try_from(unique_shows.len()).unwrap();
+    summary.shows.watched_seasons += i32::try_from(unique_show_seasons.len()).unwrap();
+    Ok(summary)
+}
+
+/// Blank notes are not stored.
+fn seen_note(note: String) -> Option<String> {
+    let note = note.trim();
+    (!note.is_empty()).then(|| note.to_owned())
+}
+
+fn modify_seen_elements(all_seen: &mut Vec<seen::Model>) {
+    all_seen.iter_mut().for_each(|s| {
+        if let Some(i) = s.extra_information.as_ref() {
+            match i {
+                SeenExtraInformation::Show(sea) => {
+                    s.show_information = Some(sea.clone());
+                }
+                SeenExtraInformation::Podcast(sea) => {
+                    s.podcast_information = Some(sea.clone());
+                }
+            };
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use apalis::prelude::Job;
+    use schematic::ConfigLoader;
+    use sea_orm::Database;
+    use sea_orm_migration::MigratorTrait;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::{
+        integrations::audiobookshelf,
+        migrator::Migrator,
+        models::media::{RuntimeBreakdown, ShowEpisode, ShowSeason},
+    };
+
+    /// An empty database along with a service that uses it. Jobs deployed by
+    /// the service are queued but never run.
+    async fn setup() -> (DatabaseConnection, MiscellaneousService) {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let config = ConfigLoader::<AppConfig>::new().load().unwrap().config;
+        let auth_db = Arc::new(
+            darkbird::Storage::<String, MemoryAuthData>::open(darkbird::Options::new(
+                std::env::temp_dir().to_str().unwrap(),
+                &Uuid::new_v4().to_string(),
+                1000,
+                darkbird::StorageType::RamCopies,
+                true,
+            ))
+            .await
+            .unwrap(),
+        );
+        let s3_client = aws_sdk_s3::Client::from_conf(
+            aws_sdk_s3::Config::builder()
+                .region(aws_sdk_s3::config::Region::new("us-east-1"))
+                .build(),
+        );
+        let file_storage = Arc::new(FileStorageService::new(s3_client, "ryot"));
+        // every connection opens its own in memory database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let service = MiscellaneousService::new(
+            &db,
+            &auth_db,
+            Arc::new(config),
+            file_storage,
+            &job_storage(&pool).await,
+            &job_storage(&pool).await,
+            &job_storage(&pool).await,
+            &job_storage(&pool).await,
+            &pool,
+        )
+        .await;
+        (db, service)
+    }
+
+    async fn job_storage<T: Job>(pool: &SqlitePool) -> SqliteStorage<T> {
+        let storage = SqliteStorage::new(pool.clone());
+        storage.setup().await.unwrap();
+        storage
+    }
+
+    async fn create_user(db: &DatabaseConnection, name: &str) -> user::Model {
+        user::ActiveModel {
+            name: ActiveValue::Set(name.to_owned()),
+            password: ActiveValue::Set("password".to_owned()),
+            lot: ActiveValue::Set(UserLot::Normal),
+            preferences: ActiveValue::Set(UserPreferences::default()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn create_movie(db: &DatabaseConnection, title: &str) -> metadata::Model {
+        metadata::ActiveModel {
+            lot: ActiveValue::Set(MetadataLot::Movie),
+            source: ActiveValue::Set(MetadataSource::Custom),
+            title: ActiveValue::Set(title.to_owned()),
+            identifier: ActiveValue::Set(title.to_owned()),
+            images: ActiveValue::Set(MetadataImages(vec![])),
+            creators: ActiveValue::Set(MetadataCreators(vec![])),
+            specifics: ActiveValue::Set(MediaSpecifics::Movie(MovieSpecifics { runtime: None })),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn create_podcast(db: &DatabaseConnection, title: &str) -> metadata::Model {
+        metadata::ActiveModel {
+            lot: ActiveValue::Set(MetadataLot::Podcast),
+            source: ActiveValue::Set(MetadataSource::Custom),
+            title: ActiveValue::Set(title.to_owned()),
+            identifier: ActiveValue::Set(title.to_owned()),
+            images: ActiveValue::Set(MetadataImages(vec![])),
+            creators: ActiveValue::Set(MetadataCreators(vec![])),
+            specifics: ActiveValue::Set(MediaSpecifics::Podcast(PodcastSpecifics {
+                episodes: vec![],
+                total_episodes: 0,
+            })),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    /// A show with a single season of two episodes that are 30 minutes long.
+    async fn create_show(db: &DatabaseConnection, title: &str) -> metadata::Model {
+        let episodes = (1..=2)
+            .map(|episode_number| ShowEpisode {
+                id: episode_number,
+                episode_number,
+                publish_date: None,
+                name: format!("Episode {episode_number}"),
+                overview: None,
+                poster_images: vec![],
+                runtime: Some(30),
+            })
+            .collect();
+        let season = ShowSeason {
+            id: 1,
+            season_number: 1,
+            name: "Season 1".to_owned(),
+            publish_date: None,
+            episodes,
+            overview: None,
+            poster_images: vec![],
+            backdrop_images: vec![],
+        };
+        metadata::ActiveModel {
+            lot: ActiveValue::Set(MetadataLot::Show),
+            source: ActiveValue::Set(MetadataSource::Custom),
+            title: ActiveValue::Set(title.to_owned()),
+            identifier: ActiveValue::Set(title.to_owned()),
+            images: ActiveValue::Set(MetadataImages(vec![])),
+            creators: ActiveValue::Set(MetadataCreators(vec![])),
+            specifics: ActiveValue::Set(MediaSpecifics::Show(ShowSpecifics {
+                seasons: vec![season],
+                average_episode_runtime: None,
+            })),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn mark_episode_as_seen(
+        db: &DatabaseConnection,
+        user_id: i32,
+        metadata_id: i32,
+        episode: i32,
+        progress: i32,
+        dropped: bool,
+    ) {
+        seen::ActiveModel {
+            progress: ActiveValue::Set(progress),
+            user_id: ActiveValue::Set(user_id),
+            metadata_id: ActiveValue::Set(metadata_id),
+            dropped: ActiveValue::Set(dropped),
+            last_updated_on: ActiveValue::Set(Utc::now()),
+            extra_information: ActiveValue::Set(Some(SeenExtraInformation::Show(
+                SeenShowExtraInformation { season: 1, episode },
+            ))),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    fn progress_input(metadata_id: i32, progress: i32) -> ProgressUpdateInput {
+        ProgressUpdateInput {
+            metadata_id,
+            progress: Some(progress),
+            date: Some(Utc::now().date_naive()),
+            show_season_number: None,
+            show_episode_number: None,
+            podcast_episode_number: None,
+            identifier: None,
+            rewatch: false,
+            note: None,
+        }
+    }
+
+    async fn mark_as_seen(db: &DatabaseConnection, user_id: i32, metadata_id: i32) {
+        seen::ActiveModel {
+            progress: ActiveValue::Set(100),
+            user_id: ActiveValue::Set(user_id),
+            metadata_id: ActiveValue::Set(metadata_id),
+            finished_on: ActiveValue::Set(Some(Utc::now().date_naive())),
+            last_updated_on: ActiveValue::Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn imported_seen_item_is_found_by_its_identifier() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let other_user = create_user(&db, "second").await;
+        let other_movie = create_movie(&db, "other").await;
+        let movie = create_movie(&db, "imported").await;
+        let seen_item = seen::ActiveModel {
+            progress: ActiveValue::Set(100),
+            user_id: ActiveValue::Set(user.id),
+            metadata_id: ActiveValue::Set(movie.id),
+            last_updated_on: ActiveValue::Set(Utc::now()),
+            identifier: ActiveValue::Set(Some("import-1".to_owned())),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        // the ids of the media and the `seen` item are different
+        assert_ne!(seen_item.id, movie.id);
+
+        let identifier = Some("import-1".to_owned());
+        let found = imported_seen_item(&db, user.id, movie.id, &identifier)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, seen_item.id);
+        assert_eq!(found.metadata_id, movie.id);
+        assert!(
+            imported_seen_item(&db, user.id, movie.id, &Some("import-2".to_owned()))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(imported_seen_item(&db, user.id, movie.id, &None)
+            .await
+            .unwrap()
+            .is_none());
+        // the same identifier does not match for another user or media
+        assert!(
+            imported_seen_item(&db, other_user.id, movie.id, &identifier)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            imported_seen_item(&db, user.id, other_movie.id, &identifier)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn progress_update_returns_the_seen_item() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        create_movie(&db, "other").await;
+        let movie = create_movie(&db, "movie").await;
+        let today = Utc::now().date_naive();
+        let input = || ProgressUpdateInput {
+            identifier: Some("import-1".to_owned()),
+            ..progress_input(movie.id, 100)
+        };
+
+        let (created, updated) = apply_progress_update(&db, input(), user.id, today)
+            .await
+            .unwrap();
+        assert!(updated);
+        assert_eq!(created.metadata_id, movie.id);
+
+        // the same import again resolves to the `seen` item created before
+        let (imported, updated) = apply_progress_update(&db, input(), user.id, today)
+            .await
+            .unwrap();
+        assert!(!updated);
+        assert_eq!(imported.id, created.id);
+        assert_eq!(Seen::find().count(&db).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn undropped_media_is_in_progress_again() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let movie = create_movie(&db, "dropped").await;
+        seen::ActiveModel {
+            progress: ActiveValue::Set(40),
+            user_id: ActiveValue::Set(user.id),
+            metadata_id: ActiveValue::Set(movie.id),
+            dropped: ActiveValue::Set(true),
+            last_updated_on: ActiveValue::Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let undropped = undrop_latest_seen(&db, user.id, movie.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!undropped.dropped);
+        assert_eq!(undropped.progress, 40);
+
+        // progress is resumed on the undropped item instead of starting over
+        let today = Utc::now().date_naive();
+        let (resumed, _) = apply_progress_update(&db, progress_input(movie.id, 60), user.id, today)
+            .await
+            .unwrap();
+        assert_eq!(resumed.id, undropped.id);
+        assert_eq!(resumed.progress, 60);
+        assert_eq!(Seen::find().count(&db).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn edited_progress_keeps_the_finish_date_in_sync() {
+        let (db, service) = setup().await;
+        let user = create_user(&db, "first").await;
+        let movie = create_movie(&db, "movie").await;
+        mark_as_seen(&db, user.id, movie.id).await;
+        let seen_id = Seen::find().one(&db).await.unwrap().unwrap().id;
+        let edit = |progress, finished_on| {
+            let service = &service;
+            async move {
+                let input = UpdateSeenItemInput {
+                    seen_id,
+                    started_on: MaybeUndefined::Undefined,
+                    finished_on,
+                    progress,
+                    note: None,
+                };
+                service.update_seen_item(input, user.id).await.unwrap();
+                let seen = Seen::find_by_id(seen_id).one(&service.db).await.unwrap();
+                seen.unwrap().finished_on
+            }
+        };
+
+        assert_eq!(edit(Some(50), MaybeUndefined::Undefined).await, None);
+        let today = UserPreferences::default().today();
+        assert_eq!(
+            edit(Some(100), MaybeUndefined::Undefined).await,
+            Some(today)
+        );
+        assert_eq!(edit(None, MaybeUndefined::Null).await, None);
+    }
+
+    #[tokio::test]
+    async fn unseen_filter_only_returns_media_not_seen_by_user() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let other_user = create_user(&db, "second").await;
+        let seen_movie = create_movie(&db, "seen").await;
+        let unseen_movie = create_movie(&db, "unseen").await;
+        mark_as_seen(&db, user.id, seen_movie.id).await;
+        mark_as_seen(&db, other_user.id, unseen_movie.id).await;
+        associate_user_with_metadata(&user.id, &unseen_movie.id, &db)
+            .await
+            .unwrap();
+
+        let metadata_alias = Alias::new("m");
+        let seen_alias = Alias::new("s");
+        let mut select = Query::select()
+            .column((metadata_alias.clone(), TempMetadata::Id))
+            .from_as(TempMetadata::Table, metadata_alias.clone())
+            .and_where(
+                Expr::col((metadata_alias.clone(), TempMetadata::Lot)).eq(MetadataLot::Movie),
+            )
+            .to_owned();
+        filter_unseen_media(&mut select, &metadata_alias, &seen_alias, user.id);
+        let ids = db
+            .query_all(db.get_database_backend().build(&select))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|qr| qr.try_get_by_index::<i32>(0).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![unseen_movie.id]);
+    }
+
+    async fn sorted_movie_ids(
+        db: &DatabaseConnection,
+        sort: impl FnOnce(&mut SelectStatement, &Alias),
+    ) -> Vec<i32> {
+        let metadata_alias = Alias::new("m");
+        let mut select = Query::select()
+            .column((metadata_alias.clone(), TempMetadata::Id))
+            .from_as(TempMetadata::Table, metadata_alias.clone())
+            .to_owned();
+        sort(&mut select, &metadata_alias);
+        db.query_all(db.get_database_backend().build(&select))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|qr| qr.try_get_by_index::<i32>(0).unwrap())
+            .collect()
+    }
+
+    fn collection_input(name: &str, update_id: Option<i32>) -> CreateOrUpdateCollectionInput {
+        CreateOrUpdateCollectionInput {
+            name: name.to_owned(),
+            description: None,
+            visibility: None,
+            update_id,
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrently_created_collections_are_not_duplicated() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+
+        let (first, second) = tokio::join!(
+            create_or_update_collection(&db, user.id, collection_input("Anime", None)),
+            create_or_update_collection(&db, user.id, collection_input("Anime", None)),
+        );
+        assert_eq!(first.unwrap().id, second.unwrap().id);
+        let count = Collection::find()
+            .filter(collection::Column::UserId.eq(user.id))
+            .filter(collection::Column::Name.eq("Anime"))
+            .count(&db)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn finished_media_are_added_to_the_completed_collection() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let movie = create_movie(&db, "movie").await;
+        let show = create_show(&db, "show").await;
+        let podcast = create_podcast(&db, "podcast").await;
+        let completed_items = || {
+            let db = &db;
+            async move {
+                MetadataToCollection::find()
+                    .inner_join(Collection)
+                    .filter(collection::Column::UserId.eq(user.id))
+                    .filter(collection::Column::Name.eq("Completed"))
+                    .all(db)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|c| c.metadata_id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // nothing happens when no collection is configured
+        add_media_to_completed_collection(&db, None, user.id, movie.id, MetadataLot::Movie)
+            .await
+            .unwrap();
+        assert!(find_collection_by_name(&db, user.id, "Completed")
+            .await
+            .unwrap()
+            .is_none());
+
+        for _ in 0..2 {
+            add_media_to_completed_collection(
+                &db,
+                Some("Completed"),
+                user.id,
+                movie.id,
+                MetadataLot::Movie,
+            )
+            .await
+            .unwrap();
+        }
+        assert!(find_collection_by_name(&db, user.id, "Completed")
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(completed_items().await, vec![movie.id]);
+
+        // shows and podcasts are seen one episode at a time
+        add_media_to_completed_collection(
+            &db,
+            Some("Completed"),
+            user.id,
+            show.id,
+            MetadataLot::Show,
+        )
+        .await
+        .unwrap();
+        add_media_to_completed_collection(
+            &db,
+            Some("Completed"),
+            user.id,
+            podcast.id,
+            MetadataLot::Podcast,
+        )
+        .await
+        .unwrap();
+        assert_eq!(completed_items().await, vec![movie.id]);
+    }
+
+    #[tokio::test]
+    async fn missing_or_foreign_collections_are_not_found() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let other_user = create_user(&db, "second").await;
+        let movie = create_movie(&db, "movie").await;
+        create_or_update_collection(&db, other_user.id, collection_input("Anime", None))
+            .await
+            .unwrap();
+
+        for name in ["Missing", "Anime"] {
+            let input = AddMediaToCollection {
+                collection_name: name.to_owned(),
+                media_id: movie.id,
+            };
+            assert!(add_media_to_collection(&db, user.id, input).await.is_err());
+            let removed = remove_media_item_from_collection(&db, user.id, movie.id, name).await;
+            assert!(removed.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn renamed_collection_keeps_its_media() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let movie = create_movie(&db, "movie").await;
+        let anime = create_or_update_collection(&db, user.id, collection_input("Anime", None))
+            .await
+            .unwrap();
+        create_or_update_collection(&db, user.id, collection_input("Manga", None))
+            .await
+            .unwrap();
+        let input = AddMediaToCollection {
+            collection_name: "Anime".to_owned(),
+            media_id: movie.id,
+        };
+        add_media_to_collection(&db, user.id, input).await.unwrap();
+        let collection = || async {
+            Collection::find_by_id(anime.id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap()
+        };
+
+        let taken = rename_collection(&db, collection().await, "Manga".to_owned()).await;
+        assert!(taken.is_err());
+        let renamed = rename_collection(&db, collection().await, "Shows".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(renamed.id, anime.id);
+        assert_eq!(collection().await.name, "Shows");
+        let media = MetadataToCollection::find()
+            .filter(metadata_to_collection::Column::CollectionId.eq(anime.id))
+            .count(&db)
+            .await
+            .unwrap();
+        assert_eq!(media, 1);
+    }
+
+    #[tokio::test]
+    async fn collection_can_not_be_renamed_to_an_existing_name() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        create_or_update_collection(&db, user.id, collection_input("Anime", None))
+            .await
+            .unwrap();
+        let other = create_or_update_collection(&db, user.id, collection_input("Manga", None))
+            .await
+            .unwrap();
+
+        let renamed =
+            create_or_update_collection(&db, user.id, collection_input("Anime", Some(other.id)))
+                .await;
+        assert!(renamed.is_err());
+    }
+
+    #[tokio::test]
+    async fn media_list_continues_after_cursor() {
+        let (db, _) = setup().await;
+        let first = create_movie(&db, "a").await;
+        let second = create_movie(&db, "b").await;
+        let third = create_movie(&db, "b").await;
+        let fourth = create_movie(&db, "c").await;
+
+        let after = |cursor: MediaListCursor, order: MediaSortOrder| {
+            move |select: &mut SelectStatement, alias: &Alias| {
+                select
+                    .cond_where(
+                        media_list_keyset_condition(
+                            &cursor,
+                            MediaSortBy::Title,
+                            order,
+                            alias,
+                            &Alias::new("mtu"),
+                        )
+                        .unwrap(),
+                    )
+                    .order_by((alias.clone(), TempMetadata::Title), Order::from(order))
+                    .order_by((alias.clone(), TempMetadata::Id), Order::Asc);
+            }
+        };
+        let cursor = |m: &metadata::Model| MediaListCursor {
+            key: MediaListCursorKey::Title(m.title.clone()),
+            id: m.id,
+        };
+        let ids = sorted_movie_ids(&db, after(cursor(&second), MediaSortOrder::Asc)).await;
+        assert_eq!(ids, vec![third.id, fourth.id]);
+        let ids = sorted_movie_ids(&db, after(cursor(&third), MediaSortOrder::Desc)).await;
+        assert_eq!(ids, vec![first.id]);
+    }
+
+    #[test]
+    fn search_language_must_be_supported_by_provider() {
+        assert!(validate_search_language(MetadataSource::Tmdb, "fr").is_ok());
+        assert!(validate_search_language(MetadataSource::Itunes, "ja_jp").is_ok());
+        // supported by the provider, but not per search
+        assert!(validate_search_language(MetadataSource::Audible, "gb").is_err());
+        assert!(validate_search_language(MetadataSource::Openlibrary, "us").is_err());
+        assert!(validate_search_language(MetadataSource::Tmdb, "klingon").is_err());
+    }
+
+    #[test]
+    fn content_hash_is_stable() {
+        // reference values of the 64 bit FNV-1a hash
+        assert_eq!(fnv1a_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_hash(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn media_list_cursor_is_decoded() {
+        let cursor = MediaListCursor {
+            key: MediaListCursorKey::ReleaseDate(None),
+            id: 3,
+        };
+        assert_eq!(MediaListCursor::decode(&cursor.encode()).unwrap(), cursor);
+        assert!(MediaListCursor::decode("page 2").is_err());
+    }
+
+    #[tokio::test]
+    async fn completed_episodes_are_detected() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let other_user = create_user(&db, "second").await;
+        let show = create_show(&db, "show").await;
+        for (episode, progress, dropped) in [(1, 100, false), (2, 50, false), (3, 100, true)] {
+            mark_episode_as_seen(&db, user.id, show.id, episode, progress, dropped).await;
+        }
+
+        let completed = |user_id, season, episode| {
+            has_completed_episode(&db, user_id, show.id, season, episode)
+        };
+        assert!(completed(user.id, 1, 1).await.unwrap());
+        assert!(!completed(user.id, 2, 1).await.unwrap());
+        assert!(!completed(user.id, 1, 2).await.unwrap());
+        assert!(!completed(user.id, 1, 3).await.unwrap());
+        assert!(!completed(other_user.id, 1, 1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn episode_marked_twice_is_counted_once_in_summary() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let show = create_show(&db, "show").await;
+
+        // the check used by progress updates and imports before marking an
+        // episode as seen
+        for _ in 0..2 {
+            if !has_completed_episode(&db, user.id, show.id, 1, 1)
+                .await
+                .unwrap()
+            {
+                mark_episode_as_seen(&db, user.id, show.id, 1, 100, false).await;
+            }
+        }
+
+        let summary = summarize_completed_media(&db, user.id, false)
+            .await
+            .unwrap();
+        assert_eq!(summary.shows.watched, 1);
+        assert_eq!(summary.shows.watched_episodes, 1);
+        assert_eq!(summary.shows.runtime, 30);
+    }
+
+    #[tokio::test]
+    async fn media_is_sorted_by_times_seen() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let other_user = create_user(&db, "second").await;
+        let once = create_movie(&db, "once").await;
+        let never = create_movie(&db, "never").await;
+        let twice = create_movie(&db, "twice").await;
+        mark_as_seen(&db, user.id, once.id).await;
+        mark_as_seen(&db, user.id, twice.id).await;
+        mark_as_seen(&db, user.id, twice.id).await;
+        mark_as_seen(&db, other_user.id, never.id).await;
+        mark_as_seen(&db, other_user.id, never.id).await;
+        mark_as_seen(&db, other_user.id, never.id).await;
+
+        let ids = sorted_movie_ids(&db, |select, alias| {
+            sort_by_times_seen(select, alias, &Alias::new("s"), user.id, Order::Desc)
+        })
+        .await;
+        assert_eq!(ids, vec![twice.id, once.id, never.id]);
+        let ids = sorted_movie_ids(&db, |select, alias| {
+            sort_by_times_seen(select, alias, &Alias::new("s"), user.id, Order::Asc)
+        })
+        .await;
+        assert_eq!(ids, vec![never.id, once.id, twice.id]);
+    }
+
+    #[tokio::test]
+    async fn media_is_sorted_by_runtime_with_missing_runtimes_last() {
+        let (db, _) = setup().await;
+        let with_runtime = |title, runtime| {
+            let db = &db;
+            async move {
+                let movie = create_movie(db, title).await;
+                let mut movie: metadata::ActiveModel = movie.into();
+                movie.specifics =
+                    ActiveValue::Set(MediaSpecifics::Movie(MovieSpecifics { runtime }));
+                movie.update(db).await.unwrap()
+            }
+        };
+        let long = with_runtime("long", Some(180)).await;
+        let unknown = with_runtime("unknown", None).await;
+        let short = with_runtime("short", Some(90)).await;
+
+        let ids = sorted_movie_ids(&db, |select, alias| {
+            sort_by_runtime(select, alias, DatabaseBackend::Sqlite, Order::Desc)
+        })
+        .await;
+        assert_eq!(ids, vec![long.id, short.id, unknown.id]);
+        let ids = sorted_movie_ids(&db, |select, alias| {
+            sort_by_runtime(select, alias, DatabaseBackend::Sqlite, Order::Asc)
+        })
+        .await;
+        assert_eq!(ids, vec![short.id, long.id, unknown.id]);
+    }
+
+    #[tokio::test]
+    async fn finished_audiobookshelf_item_is_committed_as_complete() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let book = create_movie(&db, "audiobook").await;
+        // the listening position of finished items is not always at the end
+        let payload: audiobookshelf::ItemProgress =
+            serde_json::from_str(r#"{"progress": 0.97, "isFinished": true}"#).unwrap();
+        let progress = yank_progress_to_commit(
+            payload.percent(),
+            default_yank_min_progress(),
+            default_yank_max_progress(),
+        )
+        .unwrap();
+        assert_eq!(progress, 100);
+
+        let today = Utc::now().date_naive();
+        let input = progress_input(book.id, progress);
+        let (seen_item, _) = apply_progress_update(&db, input, user.id, today)
+            .await
+            .unwrap();
+        assert_eq!(seen_item.progress, 100);
+        assert_eq!(seen_item.finished_on, Some(today));
+    }
+
+    #[test]
+    fn yank_progress_is_complete_from_the_max_progress() {
+        assert_eq!(yank_progress_to_commit(90, 1, 90), Some(100));
+        assert_eq!(yank_progress_to_commit(92, 1, 90), Some(100));
+        assert_eq!(yank_progress_to_commit(50, 1, 95), Some(50));
+        assert_eq!(yank_progress_to_commit(0, 1, 95), None);
+    }
+
+    #[test]
+    fn empty_review_is_rejected() {
+        let mut input = PostReviewInput {
+            rating: None,
+            text: Some("  ".to_owned()),
+            visibility: None,
+            spoiler: Some(true),
+            metadata_id: 1,
+            date: None,
+            identifier: None,
+            review_id: None,
+            season_number: None,
+            episode_number: None,
+            spoils_up_to_season: None,
+            spoils_up_to_episode: None,
+        };
+        assert!(validate_review(&input).is_err());
+        input.text = Some("Loved it".to_owned());
+        assert!(validate_review(&input).is_ok());
+        input.text = None;
+        input.rating = Some(Decimal::from(80));
+        assert!(validate_review(&input).is_ok());
+        input.spoils_up_to_episode = Some(3);
+        assert!(validate_review(&input).is_err());
+        input.spoils_up_to_season = Some(1);
+        assert!(validate_review(&input).is_ok());
+    }
+
+    #[test]
+    fn resume_position_is_estimated_from_progress() {
+        let movie = MediaSpecifics::Movie(MovieSpecifics { runtime: Some(120) });
+        assert_eq!(resume_position(&movie, 50).seconds, Some(3600));
+        let book = MediaSpecifics::Book(BookSpecifics { pages: Some(300) });
+        assert_eq!(resume_position(&book, 10).page, Some(30));
+        let unknown = MediaSpecifics::Book(BookSpecifics { pages: None });
+        assert_eq!(resume_position(&unknown, 10), ResumePosition::default());
+    }
+
+    #[test]
+    fn yank_integration_ids_are_not_reused_after_deletion() {
+        let integration = |id| UserYankIntegration {
+            id,
+            settings: UserYankIntegrationSetting::Audiobookshelf {
+                base_url: "http://localhost:13378".to_owned(),
+                token: "token".to_owned(),
+                min_progress: default_yank_min_progress(),
+                max_progress: default_yank_max_progress(),
+                library_id: None,
+            },
+            timestamp: Utc::now(),
+            last_synced_progress: HashMap::new(),
+        };
+        let mut integrations = vec![integration(1), integration(2)];
+        // the first integration is deleted
+        integrations.remove(0);
+        assert_eq!(next_yank_integration_id(2, &integrations), 3);
+        // all integrations are deleted
+        assert_eq!(next_yank_integration_id(2, &[]), 3);
+        // users whose integrations were created before the counter existed
+        assert_eq!(next_yank_integration_id(0, &integrations), 3);
+    }
+
+    #[test]
+    fn titles_are_normalized_for_duplicate_detection() {
+        assert_eq!(
+            normalize_title("Spider-Man: No Way Home"),
+            "spidermannowayhome"
+        );
+        assert_eq!(
+            normalize_title("spider man  no way home"),
+            normalize_title("Spider-Man: No Way Home")
+        );
+    }
+
+    #[test]
+    fn search_results_from_many_sources_are_deduplicated() {
+        let item = |source, title: &str, publish_year| SourcedMediaSearchItem {
+            source,
+            item: MediaSearchItem {
+                identifier: title.to_owned(),
+                lot: MetadataLot::Book,
+                title: title.to_owned(),
+                image: None,
+                publish_year,
+            },
+            database_id: None,
+        };
+        let items = dedup_search_results(vec![
+            item(MetadataSource::Openlibrary, "The Hobbit", Some(1937)),
+            item(MetadataSource::Openlibrary, "Dune", Some(1965)),
+            item(MetadataSource::GoogleBooks, "the hobbit", Some(1937)),
+            item(MetadataSource::GoogleBooks, "The Hobbit", Some(2012)),
+        ]);
+        assert_eq!(
+            items
+                .iter()
+                .map(|i| (i.source, i.item.publish_year))
+                .collect::<Vec<_>>(),
+            vec![
+                (MetadataSource::Openlibrary, Some(1937)),
+                (MetadataSource::Openlibrary, Some(1965)),
+                (MetadataSource::GoogleBooks, Some(2012)),
+            ]
+        );
+    }
+
+    #[test]
+    fn jaccard_similarity_of_libraries() {
+        let a = HashSet::from([1, 2, 3]);
+        let b = HashSet::from([2, 3, 4, 5]);
+        assert_eq!(jaccard_similarity(&a, &b), 0.4);
+        assert_eq!(jaccard_similarity(&a, &a), 1.0);
+        assert_eq!(jaccard_similarity(&HashSet::new(), &HashSet::new()), 0.0);
+    }
+
+    #[test]
+    fn diary_date_falls_back_to_logged_day_in_timezone() {
+        let mut seen = seen::Model {
+            id: 1,
+            progress: 100,
+            started_on: None,
+            finished_on: None,
+            last_updated_on: Utc.with_ymd_and_hms(2023, 7, 20, 22, 30, 0).unwrap(),
+            user_id: 1,
+            metadata_id: 1,
+            extra_information: None,
+            show_information: None,
+            podcast_information: None,
+            identifier: None,
+            dropped: false,
+            playthrough: None,
+            note: None,
+        };
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let ahead = FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(
+            diary_date(&seen, &utc),
+            NaiveDate::from_ymd_opt(2023, 7, 20).unwrap()
+        );
+        assert_eq!(
+            diary_date(&seen, &ahead),
+            NaiveDate::from_ymd_opt(2023, 7, 21).unwrap()
+        );
+        seen.finished_on = NaiveDate::from_ymd_opt(2023, 7, 19);
+        assert_eq!(
+            diary_date(&seen, &ahead),
+            NaiveDate::from_ymd_opt(2023, 7, 19).unwrap()
+        );
+    }
+
+    #[test]
+    fn blank_seen_notes_are_not_stored() {
+        assert_eq!(
+            seen_note(" watched with friends ".to_owned()),
+            Some("watched with friends".to_owned())
+        );
+        assert_eq!(seen_note("  ".to_owned()), None);
+    }
+
+    #[test]
+    fn runtime_is_broken_down_into_days_hours_and_minutes() {
+        assert_eq!(
+            RuntimeBreakdown::from_minutes(24 * 60 + 2 * 60 + 3),
+            RuntimeBreakdown {
+                days: 1,
+                hours: 2,
+                minutes: 3
+            }
+        );
+        assert_eq!(
+            RuntimeBreakdown::from_minutes(59),
+            RuntimeBreakdown {
+                days: 0,
+                hours: 0,
+                minutes: 59
+            }
+        );
+    }
+
+    #[test]
+    fn streaks_are_calculated_from_consecutive_days() {
+        let date = |d| NaiveDate::from_ymd_opt(2023, 7, d).unwrap();
+        let dates = vec![date(1), date(2), date(3), date(5), date(6)];
+        assert_eq!(
+            calculate_streaks(&dates, date(7)),
+            UserStreaks {
+                current: 2,
+                longest: 3
+            }
+        );
+        assert_eq!(
+            calculate_streaks(&dates, date(8)),
+            UserStreaks {
+                current: 0,
+                longest: 3
+            }
+        );
+        assert_eq!(calculate_streaks(&[], date(8)), UserStreaks::default());
+    }
+
+    #[tokio::test]
+    async fn exported_media_has_only_its_own_history() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let other = create_user(&db, "second").await;
+        let first = create_movie(&db, "first").await;
+        let second = create_movie(&db, "second").await;
+        let unseen = create_movie(&db, "unseen").await;
+        mark_as_seen(&db, user.id, first.id).await;
+        mark_as_seen(&db, user.id, first.id).await;
+        mark_as_seen(&db, user.id, second.id).await;
+        mark_as_seen(&db, other.id, second.id).await;
+
+        let page = export_media_page(&db, user.id, 1, None).await.unwrap();
+        assert_eq!(page.next_page, None);
+        let history = page
+            .items
+            .iter()
+            .map(|e| (e.ryot_id, e.seen_history.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(history, vec![(first.id, 2), (second.id, 1)]);
+        assert!(page.items.iter().all(|e| e
+            .seen_history
+            .iter()
+            .all(|s| s.user_id == user.id && s.metadata_id == e.ryot_id)));
+        assert!(page.items.iter().all(|e| e.ryot_id != unseen.id));
+        assert!(export_media_page(&db, user.id, 0, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn export_is_filtered_by_lots() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let movie = create_movie(&db, "movie").await;
+        mark_as_seen(&db, user.id, movie.id).await;
+
+        let movies = export_media_page(&db, user.id, 1, Some(vec![MetadataLot::Movie]))
+            .await
+            .unwrap();
+        assert_eq!(movies.items.len(), 1);
+        let books = export_media_page(&db, user.id, 1, Some(vec![MetadataLot::Book]))
+            .await
+            .unwrap();
+        assert!(books.items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn email_verification_token_can_only_be_used_once() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        assert!(!user.email_verified);
+        let mut model: user::ActiveModel = user.into();
+        model.email = ActiveValue::Set(Some("first@example.com".to_owned()));
+        model.email_verification_token = ActiveValue::Set(Some("token".to_owned()));
+        let user = model.update(&db).await.unwrap();
+
+        assert!(verify_email(&db, "token").await.unwrap());
+        let user = User::find_by_id(user.id).one(&db).await.unwrap().unwrap();
+        assert!(user.email_verified);
+        assert_eq!(user.email_verification_token, None);
+        assert!(!verify_email(&db, "token").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn failed_refreshes_are_counted_until_one_succeeds() {
+        let (db, _) = setup().await;
+        let custom = create_movie(&db, "custom").await;
+        let mut movie: metadata::ActiveModel = create_movie(&db, "delisted").await.into();
+        movie.source = ActiveValue::Set(MetadataSource::Tmdb);
+        let movie = movie.update(&db).await.unwrap();
+        let failed_refreshes = |id: i32| {
+            let db = &db;
+            async move {
+                Metadata::find_by_id(id)
+                    .one(db)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .failed_refreshes
+            }
+        };
+
+        let not_found: Result<()> = Err(coded_error(ErrorCode::NotFound, "Gone"));
+        let timed_out: Result<()> = Err(Error::new("Timed out"));
+        assert_eq!(RefreshResult::of(&not_found), RefreshResult::NotFound);
+        assert_eq!(RefreshResult::of(&timed_out), RefreshResult::Failed);
+        assert_eq!(RefreshResult::of(&Ok(())), RefreshResult::Found);
+
+        for _ in 0..3 {
+            record_refresh_result(&db, movie.id, RefreshResult::NotFound)
+                .await
+                .unwrap();
+            record_refresh_result(&db, movie.id, RefreshResult::Failed)
+                .await
+                .unwrap();
+            record_refresh_result(&db, custom.id, RefreshResult::NotFound)
+                .await
+                .unwrap();
+        }
+        assert_eq!(failed_refreshes(movie.id).await, 3);
+        assert_eq!(failed_refreshes(custom.id).await, 0);
+        assert!(is_unavailable(failed_refreshes(movie.id).await, 3));
+        assert!(!is_unavailable(failed_refreshes(movie.id).await, 0));
+        record_refresh_result(&db, movie.id, RefreshResult::Found)
+            .await
+            .unwrap();
+        assert_eq!(failed_refreshes(movie.id).await, 0);
+    }
+
+    #[tokio::test]
+    async fn transferred_library_merges_collections() {
+        let (db, _) = setup().await;
+        let from = create_user(&db, "from").await;
+        let to = create_user(&db, "to").await;
+        let shared = create_movie(&db, "shared").await;
+        let only_from = create_movie(&db, "only from").await;
+        mark_as_seen(&db, from.id, shared.id).await;
+        mark_as_seen(&db, from.id, only_from.id).await;
+        mark_as_seen(&db, to.id, shared.id).await;
+        for user in [&from, &to] {
+            create_or_update_collection(&db, user.id, collection_input("Watchlist", None))
+                .await
+                .unwrap();
+            add_media_to_collection(
+                &db,
+                user.id,
+                AddMediaToCollection {
+                    collection_name: "Watchlist".to_owned(),
+                    media_id: shared.id,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        add_media_to_collection(
+            &db,
+            from.id,
+            AddMediaToCollection {
+                collection_name: "Watchlist".to_owned(),
+                media_id: only_from.id,
+            },
+        )
+        .await
+        .unwrap();
+
+        transfer_user_library(&db, from.id, to.id).await.unwrap();
+
+        let seen_count = |user_id: i32| {
+            Seen::find()
+                .filter(seen::Column::UserId.eq(user_id))
+                .count(&db)
+        };
+        assert_eq!(seen_count(from.id).await.unwrap(), 0);
+        assert_eq!(seen_count(to.id).await.unwrap(), 3);
+        let collections = Collection::find()
+            .filter(collection::Column::UserId.is_in([from.id, to.id]))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].user_id, to.id);
+        let mut watchlist = collections[0]
+            .find_related(Metadata)
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect::<Vec<_>>();
+        watchlist.sort();
+        assert_eq!(watchlist, vec![shared.id, only_from.id]);
+        let associations = UserToMetadata::find()
+            .filter(user_to_metadata::Column::UserId.is_in([from.id, to.id]))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(associations.len(), 2);
+        assert!(associations.iter().all(|a| a.user_id == to.id));
+    }
+
+    #[tokio::test]
+    async fn franchise_media_excludes_itself_and_has_database_ids() {
+        let (db, _) = setup().await;
+        let first = create_movie(&db, "first").await;
+        let second = create_movie(&db, "second").await;
+        let part = |identifier: &str| MediaSearchItem {
+            identifier: identifier.to_owned(),
+            lot: MetadataLot::Movie,
+            title: identifier.to_owned(),
+            image: None,
+            publish_year: None,
+        };
+
+        let items = franchise_media_items(
+            &db,
+            &first,
+            vec![part("first"), part("second"), part("third")],
+        )
+        .await
+        .unwrap();
+        let items = items
+            .into_iter()
+            .map(|i| (i.item.identifier, i.database_id))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                ("second".to_owned(), Some(second.id)),
+                ("third".to_owned(), None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn only_own_reviews_are_deleted_in_bulk() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let other_user = create_user(&db, "second").await;
+        let movie = create_movie(&db, "movie").await;
+        let mut review_ids = vec![];
+        for user_id in [user.id, other_user.id, user.id] {
+            let review = review::ActiveModel {
+                rating: ActiveValue::Set(Some(Decimal::from(50))),
+                user_id: ActiveValue::Set(user_id),
+                metadata_id: ActiveValue::Set(movie.id),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            review_ids.push(review.id);
+        }
+        review_ids.push(1000);
+
+        let result = delete_reviews(&db, user.id, review_ids.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            DeleteReviewsResult {
+                deleted: 2,
+                rejected_ids: vec![review_ids[1]],
+            }
+        );
+        let remaining = Review::find().all(&db).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].user_id, other_user.id);
+    }
+
+    #[tokio::test]
+    async fn deleting_metadata_leaves_no_orphan_rows() {
+        let (db, _) = setup().await;
+        let user = create_user(&db, "first").await;
+        let movie = create_movie(&db, "movie").await;
+        let other_movie = create_movie(&db, "other").await;
+        mark_as_seen(&db, user.id, movie.id).await;
+        mark_as_seen(&db, user.id, other_movie.id).await;
+        review::ActiveModel {
+            rating: ActiveValue::Set(Some(Decimal::from(50))),
+            user_id: ActiveValue::Set(user.id),
+            metadata_id: ActiveValue::Set(movie.id),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let genre = genre::ActiveModel {
+            name: ActiveValue::Set("Drama".to_owned()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        metadata_to_genre::ActiveModel {
+            metadata_id: ActiveValue::Set(movie.id),
+            genre_id: ActiveValue::Set(genre.id),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let collection = collection::ActiveModel {
+            name: ActiveValue::Set("Watchlist".to_owned()),
+            user_id: ActiveValue::Set(user.id),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        metadata_to_collection::ActiveModel {
+            metadata_id: ActiveValue::Set(movie.id),
+            collection_id: ActiveValue::Set(collection.id),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        // the user and the deleted movie share the same id
+        assert_eq!(user.id, movie.id);
+        delete_metadata(&db, movie.id).await.unwrap();
+
+        assert!(Metadata::find_by_id(movie.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(MetadataToGenre::find().count(&db).await.unwrap(), 0);
+        assert_eq!(MetadataToCollection::find().count(&db).await.unwrap(), 0);
+        assert_eq!(Review::find().count(&db).await.unwrap(), 0);
+        let seen = Seen::find().all(&db).await.unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].metadata_id, other_movie.id);
+        let user_to_metadata = UserToMetadata::find().all(&db).await.unwrap();
+        assert_eq!(user_to_metadata.len(), 1);
+        assert_eq!(user_to_metadata[0].metadata_id, other_movie.id);
+    }
 }
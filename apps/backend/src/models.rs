@@ -7,13 +7,18 @@ use serde::{Deserialize, Serialize};
 use crate::{
     entities::{exercise::Model as ExerciseModel, review, seen},
     migrator::{MetadataLot, MetadataSource},
-    miscellaneous::{MediaSpecifics, MetadataCreator, MetadataImage},
+    miscellaneous::{
+        resolver::{ReviewItem, UserReviewItem},
+        MediaSpecifics, MetadataCreator, MetadataFranchise, MetadataImage,
+    },
 };
 
 #[derive(Serialize, Deserialize, Debug, SimpleObject, Clone)]
 #[graphql(concrete(name = "MediaSearchResults", params(media::MediaSearchItem)))]
 #[graphql(concrete(name = "MediaListResults", params(media::MediaListItem)))]
-#[graphql(concrete(name = "ExerciseSearchResults", params(ExerciseModel)))]
+#[graphql(concrete(name = "ExerciseSearchResults", params(fitness::ExerciseListItem)))]
+#[graphql(concrete(name = "ReviewItemResults", params(ReviewItem)))]
+#[graphql(concrete(name = "UserReviewItemResults", params(UserReviewItem)))]
 pub struct SearchResults<T: OutputType> {
     pub total: i32,
     pub items: Vec<T>,
@@ -35,6 +40,14 @@ pub mod media {
     pub struct MediaListItem {
         pub data: MediaSearchItem,
         pub average_rating: Option<Decimal>,
+        /// Only resolved when this field is part of the query
+        pub backdrop_image: Option<String>,
+        /// The fields (`title`, `description` or `creators`) that matched the
+        /// search query, empty when no query was given
+        pub matched_fields: Vec<String>,
+        /// Pass this as `after` to get the items that follow this one. Only set
+        /// when sorting by title, release date or last updated.
+        pub cursor: Option<String>,
     }
 
     #[derive(
@@ -121,6 +134,9 @@ pub mod media {
     #[graphql(input_name = "ShowSpecificsInput")]
     pub struct ShowSpecifics {
         pub seasons: Vec<ShowSeason>,
+        /// The typical runtime of an episode, used when an episode does not
+        /// have its own runtime.
+        pub average_episode_runtime: Option<i32>,
     }
 
     #[derive(
@@ -263,6 +279,13 @@ pub mod media {
         pub user_reviews: Vec<review::Model>,
     }
 
+    /// A page of the media of a user, along with the page to request next.
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ExportMediaPage {
+        pub items: Vec<ExportMedia>,
+        pub next_page: Option<u64>,
+    }
+
     #[derive(
         SimpleObject,
         Debug,
@@ -276,7 +299,11 @@ pub mod media {
     )]
     pub struct AudioBooksSummary {
         pub runtime: i32,
+        /// Distinct audio books that have been played
         pub played: i32,
+        /// Times audio books have been played, including replays
+        #[serde(default)]
+        pub total_played: i32,
     }
 
     #[derive(
@@ -291,7 +318,11 @@ pub mod media {
         FromJsonQueryResult,
     )]
     pub struct VideoGamesSummary {
+        /// Distinct video games that have been played
         pub played: i32,
+        /// Times video games have been played, including replays
+        #[serde(default)]
+        pub total_played: i32,
     }
 
     #[derive(
@@ -307,7 +338,11 @@ pub mod media {
     )]
     pub struct BooksSummary {
         pub pages: i32,
+        /// Distinct books that have been read
         pub read: i32,
+        /// Times books have been read, including re-reads
+        #[serde(default)]
+        pub total_read: i32,
     }
 
     #[derive(
@@ -323,7 +358,11 @@ pub mod media {
     )]
     pub struct MoviesSummary {
         pub runtime: i32,
+        /// Distinct movies that have been watched
         pub watched: i32,
+        /// Times movies have been watched, including re-watches
+        #[serde(default)]
+        pub total_watched: i32,
     }
 
     #[derive(
@@ -374,7 +413,11 @@ pub mod media {
     )]
     pub struct MangaSummary {
         pub chapters: i32,
+        /// Distinct manga that have been read
         pub read: i32,
+        /// Times manga have been read, including re-reads
+        #[serde(default)]
+        pub total_read: i32,
     }
 
     #[derive(
@@ -390,7 +433,31 @@ pub mod media {
     )]
     pub struct AnimeSummary {
         pub episodes: i32,
+        /// Distinct anime that have been watched
         pub watched: i32,
+        /// Times anime have been watched, including re-watches
+        #[serde(default)]
+        pub total_watched: i32,
+    }
+
+    #[derive(
+        SimpleObject,
+        Debug,
+        PartialEq,
+        Eq,
+        Clone,
+        Default,
+        Serialize,
+        Deserialize,
+        FromJsonQueryResult,
+    )]
+    pub struct BacklogSummary {
+        /// Distinct media that have an unfinished and not dropped seen item
+        pub in_progress: i32,
+        /// Distinct media that have been completed and are not being consumed again
+        pub completed: i32,
+        /// Distinct media in the user's collections that have never been started
+        pub unstarted: i32,
     }
 
     #[derive(
@@ -413,6 +480,8 @@ pub mod media {
         pub audio_books: AudioBooksSummary,
         pub anime: AnimeSummary,
         pub manga: MangaSummary,
+        #[serde(default)]
+        pub backlog: BacklogSummary,
     }
 
     #[derive(
@@ -429,6 +498,60 @@ pub mod media {
     pub struct UserSummary {
         pub media: UserMediaSummary,
         pub calculated_on: DateTimeUtc,
+        pub recently_finished: Vec<RecentlyFinishedItem>,
+        pub runtime: UserRuntimeSummary,
+    }
+
+    /// A duration split into whole days, hours and minutes.
+    #[derive(SimpleObject, Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+    pub struct RuntimeBreakdown {
+        pub days: i32,
+        pub hours: i32,
+        pub minutes: i32,
+    }
+
+    impl RuntimeBreakdown {
+        pub fn from_minutes(total: i32) -> Self {
+            Self {
+                days: total / (24 * 60),
+                hours: total % (24 * 60) / 60,
+                minutes: total % 60,
+            }
+        }
+    }
+
+    /// The runtimes in the media summary, broken down for display.
+    #[derive(SimpleObject, Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+    pub struct UserRuntimeSummary {
+        pub total: RuntimeBreakdown,
+        pub movies: RuntimeBreakdown,
+        pub shows: RuntimeBreakdown,
+        pub podcasts: RuntimeBreakdown,
+        pub audio_books: RuntimeBreakdown,
+    }
+
+    impl From<&UserMediaSummary> for UserRuntimeSummary {
+        fn from(media: &UserMediaSummary) -> Self {
+            let total = media.movies.runtime
+                + media.shows.runtime
+                + media.podcasts.runtime
+                + media.audio_books.runtime;
+            Self {
+                total: RuntimeBreakdown::from_minutes(total),
+                movies: RuntimeBreakdown::from_minutes(media.movies.runtime),
+                shows: RuntimeBreakdown::from_minutes(media.shows.runtime),
+                podcasts: RuntimeBreakdown::from_minutes(media.podcasts.runtime),
+                audio_books: RuntimeBreakdown::from_minutes(media.audio_books.runtime),
+            }
+        }
+    }
+
+    #[derive(SimpleObject, Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+    pub struct RecentlyFinishedItem {
+        pub metadata_id: i32,
+        pub title: String,
+        pub lot: MetadataLot,
+        pub finished_on: Option<NaiveDate>,
     }
 
     #[derive(Debug, InputObject)]
@@ -451,6 +574,18 @@ pub mod media {
         pub review_id: Option<i32>,
         pub season_number: Option<i32>,
         pub episode_number: Option<i32>,
+        /// The season of a show up to which this review has spoilers
+        pub spoils_up_to_season: Option<i32>,
+        /// The episode of `spoils_up_to_season` up to which this review has
+        /// spoilers
+        pub spoils_up_to_episode: Option<i32>,
+    }
+
+    impl PostReviewInput {
+        /// Whether this review has a rating or some non blank text.
+        pub fn has_content(&self) -> bool {
+            self.rating.is_some() || self.text.as_ref().map_or(false, |t| !t.trim().is_empty())
+        }
     }
 
     #[derive(Debug, Serialize, Deserialize, InputObject, Clone)]
@@ -463,6 +598,12 @@ pub mod media {
         pub podcast_episode_number: Option<i32>,
         /// If this update comes from a different source, this should be set
         pub identifier: Option<String>,
+        /// Always create a new `seen` item instead of updating the one underway
+        #[graphql(default)]
+        #[serde(default)]
+        pub rewatch: bool,
+        /// A note to save on the `seen` item, replacing any existing one
+        pub note: Option<String>,
     }
 
     #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -478,6 +619,14 @@ pub mod media {
         pub publish_year: Option<i32>,
         pub publish_date: Option<NaiveDate>,
         pub specifics: MediaSpecifics,
+        /// The aggregate rating on the provider, on the scale used by it
+        pub provider_rating: Option<Decimal>,
+        /// The ISO 639-1 code of the language the media was originally made in
+        pub original_language: Option<String>,
+        pub franchise: Option<MetadataFranchise>,
+        /// The slug used by the provider in the URL of this media, when it
+        /// can not be derived from the title
+        pub slug: Option<String>,
     }
 }
 
@@ -608,4 +757,12 @@ pub mod fitness {
         pub attributes: ExerciseAttributes,
         pub name: String,
     }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+    pub struct ExerciseListItem {
+        #[graphql(flatten)]
+        pub exercise: ExerciseModel,
+        /// Whether the logged in user has favorited this exercise
+        pub favorite: bool,
+    }
 }
@@ -9,10 +9,12 @@ use crate::{
     models::media::{MediaDetails, MediaSearchItem},
     models::SearchResults,
     traits::{MediaProvider, MediaProviderLanguages},
-    utils::PAGE_LIMIT,
+    utils::{validate_numeric_identifier, PAGE_LIMIT},
 };
 
-static URL: &str = "https://graphql.anilist.co";
+pub static URL: &str = "https://graphql.anilist.co";
+
+type CountryCode = String;
 
 #[derive(GraphQLQuery)]
 #[graphql(
@@ -30,6 +32,14 @@ struct SearchQuery;
 )]
 struct DetailsQuery;
 
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/providers/anilist/schema.json",
+    query_path = "src/providers/anilist/recommendations.graphql",
+    response_derives = "Debug"
+)]
+struct RecommendationsQuery;
+
 #[derive(Debug, Clone)]
 pub struct AnilistService {
     client: Client,
@@ -61,15 +71,25 @@ impl AnilistAnimeService {
 
 #[async_trait]
 impl MediaProvider for AnilistAnimeService {
+    fn validate_identifier(&self, identifier: &str) -> Result<()> {
+        validate_numeric_identifier(identifier, "Anilist")
+    }
+
     async fn details(&self, identifier: &str) -> Result<MediaDetails> {
         let details = utils::details(&self.base.client, identifier).await?;
         Ok(details)
     }
 
+    async fn recommendations(&self, identifier: &str) -> Result<Vec<MediaSearchItem>> {
+        utils::recommendations(&self.base.client, identifier).await
+    }
+
     async fn search(
         &self,
         query: &str,
         page: Option<i32>,
+        _year: Option<i32>,
+        _language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let (items, total, next_page) = utils::search(
             &self.base.client,
@@ -102,15 +122,25 @@ impl AnilistMangaService {
 
 #[async_trait]
 impl MediaProvider for AnilistMangaService {
+    fn validate_identifier(&self, identifier: &str) -> Result<()> {
+        validate_numeric_identifier(identifier, "Anilist")
+    }
+
     async fn details(&self, identifier: &str) -> Result<MediaDetails> {
         let details = utils::details(&self.base.client, identifier).await?;
         Ok(details)
     }
 
+    async fn recommendations(&self, identifier: &str) -> Result<Vec<MediaSearchItem>> {
+        utils::recommendations(&self.base.client, identifier).await
+    }
+
     async fn search(
         &self,
         query: &str,
         page: Option<i32>,
+        _year: Option<i32>,
+        _language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let (items, total, next_page) = utils::search(
             &self.base.client,
@@ -129,13 +159,14 @@ impl MediaProvider for AnilistMangaService {
 
 mod utils {
     use itertools::Itertools;
+    use rust_decimal::Decimal;
     use surf::{http::headers::ACCEPT, Url};
 
     use crate::{
         migrator::{MetadataImageLot, MetadataSource},
         miscellaneous::{MediaSpecifics, MetadataCreator, MetadataImage, MetadataImageUrl},
         models::media::{AnimeSpecifics, MangaSpecifics},
-        utils::get_base_http_client_config,
+        utils::{ensure_media_found, get_base_http_client_config},
     };
 
     use super::*;
@@ -150,18 +181,32 @@ mod utils {
         client
     }
 
+    /// Anilist only exposes the country a media was produced in, so the
+    /// language is derived from the countries it commonly lists.
+    fn country_to_language(country: &str) -> Option<String> {
+        let language = match country {
+            "JP" => "ja",
+            "KR" => "ko",
+            "CN" | "TW" => "zh",
+            _ => return None,
+        };
+        Some(language.to_owned())
+    }
+
     pub async fn details(client: &Client, id: &str) -> Result<MediaDetails> {
         let variables = details_query::Variables {
             id: id.parse::<i64>().unwrap(),
         };
         let body = DetailsQuery::build_query(variables);
-        let details = client
+        let mut rsp = client
             .post("")
             .body_json(&body)
             .unwrap()
             .send()
             .await
-            .map_err(|e| anyhow!(e))?
+            .map_err(|e| anyhow!(e))?;
+        ensure_media_found(&rsp)?;
+        let details = rsp
             .body_json::<Response<details_query::ResponseData>>()
             .await
             .map_err(|e| anyhow!(e))?
@@ -241,9 +286,68 @@ mod utils {
             publish_year: year,
             publish_date: None,
             specifics,
+            provider_rating: details.average_score.map(Decimal::from),
+            original_language: details
+                .country_of_origin
+                .and_then(|c| country_to_language(&c)),
+            franchise: None,
+            slug: None,
         })
     }
 
+    pub async fn recommendations(client: &Client, id: &str) -> Result<Vec<MediaSearchItem>> {
+        let variables = recommendations_query::Variables {
+            id: id
+                .parse::<i64>()
+                .map_err(|_| anyhow!("Anilist identifiers must be numeric, got {id:?}"))?,
+        };
+        let body = RecommendationsQuery::build_query(variables);
+        let response = client
+            .post("")
+            .body_json(&body)
+            .map_err(|e| anyhow!(e))?
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?
+            .body_json::<Response<recommendations_query::ResponseData>>()
+            .await
+            .map_err(|e| anyhow!(e))?;
+        let media = match response.data {
+            Some(data) => data.media,
+            None => {
+                return Err(anyhow!(
+                    "Anilist returned no recommendations: {:?}",
+                    response.errors.unwrap_or_default()
+                ))
+            }
+        };
+        let items = media
+            .and_then(|m| m.recommendations)
+            .and_then(|r| r.nodes)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|n| n.media_recommendation)
+            .filter_map(|m| {
+                let lot = match m.type_? {
+                    recommendations_query::MediaType::ANIME => MetadataLot::Anime,
+                    recommendations_query::MediaType::MANGA => MetadataLot::Manga,
+                    recommendations_query::MediaType::Other(_) => return None,
+                };
+                Some(MediaSearchItem {
+                    identifier: m.id.to_string(),
+                    lot,
+                    title: m.title?.user_preferred?,
+                    image: m.cover_image.and_then(|i| i.extra_large),
+                    publish_year: m
+                        .start_date
+                        .and_then(|b| b.year.map(|y| y.try_into().unwrap())),
+                })
+            })
+            .collect();
+        Ok(items)
+    }
+
     pub async fn search(
         client: &Client,
         media_type: search_query::MediaType,
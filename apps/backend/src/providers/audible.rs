@@ -15,8 +15,8 @@ use crate::{
     },
     traits::{MediaProvider, MediaProviderLanguages},
     utils::{
-        convert_date_to_year, convert_string_to_date, get_base_http_client_config, NamedObject,
-        PAGE_LIMIT,
+        convert_date_to_year, convert_string_to_date, ensure_media_found,
+        get_base_http_client_config, NamedObject, PAGE_LIMIT,
     },
 };
 
@@ -136,6 +136,7 @@ impl MediaProvider for AudibleService {
             .unwrap()
             .await
             .map_err(|e| anyhow!(e))?;
+        ensure_media_found(&rsp)?;
         let data: AudibleItemResponse = rsp.body_json().await.map_err(|e| anyhow!(e))?;
         let d = self.audible_response_to_search_response(data.product);
         Ok(d)
@@ -145,6 +146,8 @@ impl MediaProvider for AudibleService {
         &self,
         query: &str,
         page: Option<i32>,
+        _year: Option<i32>,
+        _language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let page = page.unwrap_or(1);
         #[derive(Serialize, Deserialize, Debug)]
@@ -250,6 +253,10 @@ impl AudibleService {
                 runtime: item.runtime_length_min,
             }),
             images,
+            provider_rating: None,
+            original_language: None,
+            franchise: None,
+            slug: None,
         }
     }
 }
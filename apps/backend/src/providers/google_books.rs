@@ -2,6 +2,7 @@ use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use convert_case::{Case, Casing};
 use itertools::Itertools;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use surf::{Client, Url};
 
@@ -14,7 +15,7 @@ use crate::{
         SearchResults,
     },
     traits::{MediaProvider, MediaProviderLanguages},
-    utils::{convert_date_to_year, get_base_http_client_config, PAGE_LIMIT},
+    utils::{convert_date_to_year, ensure_media_found, get_base_http_client_config, PAGE_LIMIT},
 };
 
 pub static URL: &str = "https://www.googleapis.com/books/v1/volumes/";
@@ -67,6 +68,7 @@ struct ItemVolumeInfo {
     main_category: Option<String>,
     categories: Option<Vec<String>>,
     page_count: Option<i32>,
+    average_rating: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -87,6 +89,7 @@ struct SearchResponse {
 impl MediaProvider for GoogleBooksService {
     async fn details(&self, identifier: &str) -> Result<MediaDetails> {
         let mut rsp = self.client.get(identifier).await.map_err(|e| anyhow!(e))?;
+        ensure_media_found(&rsp)?;
         let data: ItemResponse = rsp.body_json().await.map_err(|e| anyhow!(e))?;
         let d = self.google_books_response_to_search_response(data.volume_info, data.id);
         Ok(d)
@@ -96,6 +99,8 @@ impl MediaProvider for GoogleBooksService {
         &self,
         query: &str,
         page: Option<i32>,
+        _year: Option<i32>,
+        _language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let page = page.unwrap_or(1);
         let index = (page - 1) * PAGE_LIMIT;
@@ -230,6 +235,13 @@ impl GoogleBooksService {
                 pages: item.page_count,
             }),
             images: images.unique().collect(),
+            provider_rating: item
+                .average_rating
+                .and_then(Decimal::from_f64_retain)
+                .map(|r| r.round_dp(1)),
+            original_language: None,
+            franchise: None,
+            slug: None,
         }
     }
 }
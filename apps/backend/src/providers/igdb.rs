@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use chrono::Datelike;
+use chrono::{Datelike, NaiveDate};
 use itertools::Itertools;
 use sea_orm::prelude::DateTimeUtc;
 use serde::{Deserialize, Serialize};
@@ -15,7 +15,7 @@ use crate::{
         SearchResults,
     },
     traits::{MediaProvider, MediaProviderLanguages},
-    utils::{NamedObject, PAGE_LIMIT},
+    utils::{validate_numeric_identifier, MediaNotFoundError, NamedObject, PAGE_LIMIT},
 };
 
 pub static URL: &str = "https://api.igdb.com/v4/";
@@ -26,6 +26,7 @@ static FIELDS: &str = "
 fields
     id,
     name,
+    slug,
     summary,
     cover.*,
     first_release_date,
@@ -64,6 +65,7 @@ struct IgdbImage {
 struct IgdbSearchResponse {
     id: i32,
     name: String,
+    slug: Option<String>,
     summary: Option<String>,
     cover: Option<IgdbImage>,
     #[serde_as(as = "Option<TimestampSeconds<i64, Flexible>>")]
@@ -103,6 +105,10 @@ impl IgdbService {
 
 #[async_trait]
 impl MediaProvider for IgdbService {
+    fn validate_identifier(&self, identifier: &str) -> Result<()> {
+        validate_numeric_identifier(identifier, "IGDB")
+    }
+
     async fn details(&self, identifier: &str) -> Result<MediaDetails> {
         let client = utils::get_client(&self.config).await;
         let req_body = format!(
@@ -120,7 +126,7 @@ where id = {id};
             .map_err(|e| anyhow!(e))?;
 
         let mut details: Vec<IgdbSearchResponse> = rsp.body_json().await.map_err(|e| anyhow!(e))?;
-        let detail = details.pop().unwrap();
+        let detail = details.pop().ok_or(MediaNotFoundError)?;
         let d = self.igdb_response_to_search_response(detail);
         Ok(d)
     }
@@ -129,12 +135,27 @@ where id = {id};
         &self,
         query: &str,
         page: Option<i32>,
+        year: Option<i32>,
+        _language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let page = page.unwrap_or(1);
         let client = utils::get_client(&self.config).await;
+        // a later `where` clause replaces the one in `FIELDS`
+        let year_filter = year
+            .and_then(|y| {
+                let start = NaiveDate::from_ymd_opt(y, 1, 1)?;
+                let end = NaiveDate::from_ymd_opt(y + 1, 1, 1)?;
+                Some(format!(
+                    "where version_parent = null & first_release_date >= {} & first_release_date < {};",
+                    start.and_hms_opt(0, 0, 0)?.timestamp(),
+                    end.and_hms_opt(0, 0, 0)?.timestamp()
+                ))
+            })
+            .unwrap_or_default();
         let req_body = format!(
             r#"
 {field}
+{year_filter}
 search "{query}";
 limit {limit};
 offset: {offset};
@@ -252,6 +273,10 @@ impl IgdbService {
                     .map(|p| p.name)
                     .collect(),
             }),
+            provider_rating: None,
+            original_language: None,
+            franchise: None,
+            slug: item.slug,
         }
     }
 
@@ -15,7 +15,10 @@ use crate::{
         SearchResults,
     },
     traits::{MediaProvider, MediaProviderLanguages},
-    utils::{get_base_http_client_config, NamedObject, PAGE_LIMIT},
+    utils::{
+        get_base_http_client_config, validate_numeric_identifier, MediaNotFoundError, NamedObject,
+        PAGE_LIMIT,
+    },
 };
 
 pub static URL: &str = "https://itunes.apple.com/";
@@ -83,6 +86,10 @@ struct SearchResponse {
 
 #[async_trait]
 impl MediaProvider for ITunesService {
+    fn validate_identifier(&self, identifier: &str) -> Result<()> {
+        validate_numeric_identifier(identifier, "iTunes")
+    }
+
     async fn details(&self, identifier: &str) -> Result<MediaDetails> {
         let mut rsp = self
             .client
@@ -97,7 +104,10 @@ impl MediaProvider for ITunesService {
             .await
             .map_err(|e| anyhow!(e))?;
         let details: SearchResponse = rsp.body_json().await.map_err(|e| anyhow!(e))?;
-        let ht = details.results.unwrap()[0].clone();
+        let ht = details
+            .results
+            .and_then(|r| r.into_iter().next())
+            .ok_or(MediaNotFoundError)?;
         let description = ht.description.clone();
         let creators = Vec::from_iter(ht.artist_name.clone())
             .into_iter()
@@ -175,6 +185,10 @@ impl MediaProvider for ITunesService {
                 episodes,
                 total_episodes,
             }),
+            provider_rating: None,
+            original_language: None,
+            franchise: None,
+            slug: None,
         })
     }
 
@@ -182,6 +196,8 @@ impl MediaProvider for ITunesService {
         &self,
         query: &str,
         page: Option<i32>,
+        _year: Option<i32>,
+        language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let page = page.unwrap_or(1);
         let mut rsp = self
@@ -192,7 +208,7 @@ impl MediaProvider for ITunesService {
                 "limit": PAGE_LIMIT,
                 "media": "podcast",
                 "entity": "podcast",
-                "lang": self.language
+                "lang": language.unwrap_or(&self.language)
             }))
             .unwrap()
             .await
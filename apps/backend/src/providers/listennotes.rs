@@ -19,7 +19,7 @@ use crate::{
         SearchResults,
     },
     traits::{MediaProvider, MediaProviderLanguages},
-    utils::PAGE_LIMIT,
+    utils::{ensure_media_found, PAGE_LIMIT},
 };
 
 pub static URL: &str = "https://listen-api.listennotes.com/api/v2/";
@@ -85,6 +85,8 @@ impl MediaProvider for ListennotesService {
         &self,
         query: &str,
         page: Option<i32>,
+        _year: Option<i32>,
+        _language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let page = page.unwrap_or(1);
         #[serde_as]
@@ -173,6 +175,7 @@ impl ListennotesService {
             .unwrap()
             .await
             .map_err(|e| anyhow!(e))?;
+        ensure_media_found(&rsp)?;
         let d: Podcast = rsp.body_json().await.map_err(|e| anyhow!(e))?;
         Ok(MediaDetails {
             identifier: d.id,
@@ -210,6 +213,10 @@ impl ListennotesService {
                     .collect(),
                 total_episodes: d.total_episodes,
             }),
+            provider_rating: None,
+            original_language: None,
+            franchise: None,
+            slug: None,
         })
     }
 }
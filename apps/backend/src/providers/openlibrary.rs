@@ -17,7 +17,10 @@ use crate::{
         SearchResults,
     },
     traits::{MediaProvider, MediaProviderLanguages},
-    utils::{get_base_http_client_config, get_data_parallelly_from_sources, PAGE_LIMIT},
+    utils::{
+        ensure_media_found, get_base_http_client_config, get_data_parallelly_from_sources,
+        PAGE_LIMIT,
+    },
 };
 
 pub static URL: &str = "https://openlibrary.org";
@@ -46,6 +49,7 @@ pub struct BookSearchItem {
 pub struct OpenlibraryService {
     image_url: String,
     image_size: String,
+    prefer_edition_covers: bool,
     client: Client,
 }
 
@@ -68,6 +72,7 @@ impl OpenlibraryService {
         Self {
             image_url: IMAGE_URL.to_owned(),
             image_size: config.cover_image_size.to_string(),
+            prefer_edition_covers: config.prefer_edition_covers,
             client,
         }
     }
@@ -116,6 +121,7 @@ impl MediaProvider for OpenlibraryService {
             .get(format!("works/{}.json", identifier))
             .await
             .map_err(|e| anyhow!(e))?;
+        ensure_media_found(&rsp)?;
         let data: OpenlibraryBook = rsp.body_json().await.map_err(|e| anyhow!(e))?;
 
         #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -182,20 +188,16 @@ impl MediaProvider for OpenlibraryService {
             OpenlibraryDescription::Nested { value, .. } => value,
         });
 
-        let mut images = vec![];
-        for c in data.covers.iter().flatten() {
-            images.push(*c);
-        }
-        for c in entries
-            .iter()
-            .flat_map(|e| e.covers.to_owned().unwrap_or_default())
-        {
-            images.push(c);
-        }
-
+        let images = utils::cover_ids(
+            data.covers.unwrap_or_default(),
+            entries
+                .iter()
+                .map(|e| e.covers.to_owned().unwrap_or_default())
+                .collect(),
+            self.prefer_edition_covers,
+        );
         let images = images
             .into_iter()
-            .filter(|c| c > &0)
             .map(|c| MetadataImage {
                 url: MetadataImageUrl::Url(self.get_cover_image_url(c)),
                 lot: MetadataImageLot::Poster,
@@ -228,6 +230,10 @@ impl MediaProvider for OpenlibraryService {
             specifics: MediaSpecifics::Book(BookSpecifics {
                 pages: Some(num_pages),
             }),
+            provider_rating: None,
+            original_language: None,
+            franchise: None,
+            slug: None,
         })
     }
 
@@ -235,6 +241,8 @@ impl MediaProvider for OpenlibraryService {
         &self,
         query: &str,
         page: Option<i32>,
+        _year: Option<i32>,
+        _language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let page = page.unwrap_or(1);
         #[derive(Debug, Serialize, Deserialize, SimpleObject)]
@@ -349,4 +357,35 @@ pub mod utils {
             .unwrap()
             .to_owned()
     }
+
+    /// The ids of the covers to use for a work. Invalid ids are removed. When
+    /// the work does not have a cover of its own, the covers of its editions
+    /// are used if `prefer_edition_covers` is set.
+    pub fn cover_ids(
+        work_covers: Vec<i64>,
+        edition_covers: Vec<Vec<i64>>,
+        prefer_edition_covers: bool,
+    ) -> Vec<i64> {
+        let mut covers = work_covers
+            .into_iter()
+            .filter(|c| c > &0)
+            .collect::<Vec<_>>();
+        if covers.is_empty() && prefer_edition_covers {
+            covers.extend(edition_covers.into_iter().flatten().filter(|c| c > &0));
+        }
+        covers
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn edition_covers_are_used_when_the_work_has_none() {
+            let editions = vec![vec![], vec![-1, 20], vec![30]];
+            assert_eq!(cover_ids(vec![10], editions.clone(), true), vec![10]);
+            assert_eq!(cover_ids(vec![-1], editions.clone(), true), vec![20, 30]);
+            assert_eq!(cover_ids(vec![], editions, false), Vec::<i64>::new());
+        }
+    }
 }
@@ -2,6 +2,7 @@ use anyhow::{anyhow, Result};
 use async_graphql::SimpleObject;
 use async_trait::async_trait;
 use itertools::Itertools;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use surf::Client;
@@ -9,7 +10,10 @@ use surf::Client;
 use crate::{
     config::{MoviesTmdbConfig, ShowsTmdbConfig},
     migrator::{MetadataImageLot, MetadataLot, MetadataSource},
-    miscellaneous::{MediaSpecifics, MetadataCreator, MetadataImage, MetadataImageUrl},
+    miscellaneous::{
+        MediaSpecifics, MetadataCreator, MetadataFranchise, MetadataImage, MetadataImageUrl,
+        WatchProvider,
+    },
     models::{
         media::{
             MediaDetails, MediaSearchItem, MovieSpecifics, ShowEpisode, ShowSeason, ShowSpecifics,
@@ -17,7 +21,10 @@ use crate::{
         SearchResults,
     },
     traits::{MediaProvider, MediaProviderLanguages},
-    utils::{convert_date_to_year, convert_string_to_date, NamedObject},
+    utils::{
+        convert_date_to_year, convert_string_to_date, ensure_media_found,
+        validate_numeric_identifier, NamedObject,
+    },
 };
 
 pub static URL: &str = "https://api.themoviedb.org/3/";
@@ -32,6 +39,24 @@ impl TmdbService {
     fn get_cover_image_url(&self, c: String) -> String {
         format!("{}{}{}", self.image_url, "original", c)
     }
+
+    fn metadata_images(&self, image_ids: Vec<String>, logo_ids: Vec<String>) -> Vec<MetadataImage> {
+        let images = image_ids
+            .into_iter()
+            .unique()
+            .map(|p| (p, MetadataImageLot::Poster));
+        let logos = logo_ids
+            .into_iter()
+            .unique()
+            .map(|p| (p, MetadataImageLot::Logo));
+        images
+            .chain(logos)
+            .map(|(p, lot)| MetadataImage {
+                url: MetadataImageUrl::Url(self.get_cover_image_url(p)),
+                lot,
+            })
+            .collect()
+    }
 }
 
 impl MediaProviderLanguages for TmdbService {
@@ -63,10 +88,77 @@ impl TmdbMovieService {
             },
         }
     }
+
+    /// Get the watch providers of a movie in all regions.
+    pub async fn watch_providers(&self, identifier: &str) -> Result<Vec<WatchProvider>> {
+        utils::get_watch_providers(&self.client, &self.base, "movie", identifier).await
+    }
+
+    /// Get the movies of a franchise (called a collection by TMDb), in the
+    /// order they were released.
+    pub async fn franchise_parts(
+        &self,
+        franchise_identifier: &str,
+    ) -> Result<Vec<MediaSearchItem>> {
+        let mut rsp = self
+            .client
+            .get(format!("collection/{}", franchise_identifier))
+            .query(&json!({
+                "language": self.base.language,
+            }))
+            .unwrap()
+            .await
+            .map_err(|e| anyhow!(e))?;
+        let data: TmdbCollectionResponse = rsp.body_json().await.map_err(|e| anyhow!(e))?;
+        Ok(franchise_items(&self.base, data))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct TmdbCollectionPart {
+    id: i32,
+    title: String,
+    poster_path: Option<String>,
+    release_date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct TmdbCollectionResponse {
+    parts: Vec<TmdbCollectionPart>,
+}
+
+fn franchise_items(base: &TmdbService, data: TmdbCollectionResponse) -> Vec<MediaSearchItem> {
+    data.parts
+        .into_iter()
+        .map(|p| MediaSearchItem {
+            identifier: p.id.to_string(),
+            lot: MetadataLot::Movie,
+            title: p.title,
+            publish_year: p.release_date.and_then(|d| convert_date_to_year(&d)),
+            image: p.poster_path.map(|p| base.get_cover_image_url(p)),
+        })
+        // unreleased movies are sorted last
+        .sorted_by_key(|i| (i.publish_year.is_none(), i.publish_year))
+        .collect()
 }
 
 #[async_trait]
 impl MediaProvider for TmdbMovieService {
+    fn validate_identifier(&self, identifier: &str) -> Result<()> {
+        validate_numeric_identifier(identifier, "TMDb")
+    }
+
+    async fn recommendations(&self, identifier: &str) -> Result<Vec<MediaSearchItem>> {
+        utils::get_recommendations(
+            &self.client,
+            &self.base,
+            "movie",
+            MetadataLot::Movie,
+            identifier,
+        )
+        .await
+    }
+
     async fn details(&self, identifier: &str) -> Result<MediaDetails> {
         #[derive(Debug, Serialize, Deserialize, Clone)]
         struct TmdbMovie {
@@ -78,6 +170,14 @@ impl MediaProvider for TmdbMovieService {
             release_date: String,
             runtime: i32,
             genres: Vec<NamedObject>,
+            vote_average: Option<f64>,
+            original_language: Option<String>,
+            belongs_to_collection: Option<TmdbCollection>,
+        }
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        struct TmdbCollection {
+            id: i32,
+            name: String,
         }
         let mut rsp = self
             .client
@@ -88,6 +188,7 @@ impl MediaProvider for TmdbMovieService {
             .unwrap()
             .await
             .map_err(|e| anyhow!(e))?;
+        ensure_media_found(&rsp)?;
         let data: TmdbMovie = rsp.body_json().await.map_err(|e| anyhow!(e))?;
         #[derive(Debug, Serialize, Deserialize, Clone)]
         struct TmdbCreditsResponse {
@@ -140,7 +241,15 @@ impl MediaProvider for TmdbMovieService {
         if let Some(u) = data.backdrop_path {
             image_ids.push(u);
         }
-        utils::save_all_images(&self.client, "movie", identifier, &mut image_ids).await?;
+        let mut logo_ids = vec![];
+        utils::save_all_images(
+            &self.client,
+            "movie",
+            identifier,
+            &mut image_ids,
+            &mut logo_ids,
+        )
+        .await?;
 
         Ok(MediaDetails {
             identifier: data.id.to_string(),
@@ -149,20 +258,23 @@ impl MediaProvider for TmdbMovieService {
             title: data.title,
             genres: data.genres.into_iter().map(|g| g.name).collect(),
             creators: Vec::from_iter(all_creators),
-            images: image_ids
-                .into_iter()
-                .unique()
-                .map(|p| MetadataImage {
-                    url: MetadataImageUrl::Url(self.base.get_cover_image_url(p)),
-                    lot: MetadataImageLot::Poster,
-                })
-                .collect(),
+            images: self.base.metadata_images(image_ids, logo_ids),
             publish_year: convert_date_to_year(&data.release_date),
             publish_date: convert_string_to_date(&data.release_date),
             description: Some(data.overview),
             specifics: MediaSpecifics::Movie(MovieSpecifics {
                 runtime: Some(data.runtime),
             }),
+            provider_rating: data
+                .vote_average
+                .and_then(Decimal::from_f64_retain)
+                .map(|r| r.round_dp(1)),
+            original_language: data.original_language,
+            franchise: data.belongs_to_collection.map(|c| MetadataFranchise {
+                identifier: c.id.to_string(),
+                name: c.name,
+            }),
+            slug: None,
         })
     }
 
@@ -170,6 +282,8 @@ impl MediaProvider for TmdbMovieService {
         &self,
         query: &str,
         page: Option<i32>,
+        year: Option<i32>,
+        language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let page = page.unwrap_or(1);
         #[derive(Debug, Serialize, Deserialize, SimpleObject)]
@@ -187,14 +301,18 @@ impl MediaProvider for TmdbMovieService {
             results: Vec<TmdbMovie>,
             total_pages: i32,
         }
+        let mut params = json!({
+            "query": query.to_owned(),
+            "page": page,
+            "language": language.unwrap_or(&self.base.language),
+        });
+        if let Some(y) = year {
+            params["primary_release_year"] = json!(y);
+        }
         let mut rsp = self
             .client
             .get("search/movie")
-            .query(&json!({
-                "query": query.to_owned(),
-                "page": page,
-                "language": self.base.language,
-            }))
+            .query(&params)
             .unwrap()
             .await
             .map_err(|e| anyhow!(e))?;
@@ -241,10 +359,30 @@ impl TmdbShowService {
             },
         }
     }
+
+    /// Get the watch providers of a show in all regions.
+    pub async fn watch_providers(&self, identifier: &str) -> Result<Vec<WatchProvider>> {
+        utils::get_watch_providers(&self.client, &self.base, "tv", identifier).await
+    }
 }
 
 #[async_trait]
 impl MediaProvider for TmdbShowService {
+    fn validate_identifier(&self, identifier: &str) -> Result<()> {
+        validate_numeric_identifier(identifier, "TMDb")
+    }
+
+    async fn recommendations(&self, identifier: &str) -> Result<Vec<MediaSearchItem>> {
+        utils::get_recommendations(
+            &self.client,
+            &self.base,
+            "tv",
+            MetadataLot::Show,
+            identifier,
+        )
+        .await
+    }
+
     async fn details(&self, identifier: &str) -> Result<MediaDetails> {
         #[derive(Debug, Serialize, Deserialize, Clone)]
         struct TmdbSeasonNumber {
@@ -260,6 +398,10 @@ impl MediaProvider for TmdbShowService {
             first_air_date: Option<String>,
             seasons: Vec<TmdbSeasonNumber>,
             genres: Vec<NamedObject>,
+            #[serde(default)]
+            episode_run_time: Vec<i32>,
+            vote_average: Option<f64>,
+            original_language: Option<String>,
         }
         let mut rsp = self
             .client
@@ -270,12 +412,21 @@ impl MediaProvider for TmdbShowService {
             .unwrap()
             .await
             .map_err(|e| anyhow!(e))?;
+        ensure_media_found(&rsp)?;
         let data: TmdbShow = rsp.body_json().await.map_err(|e| anyhow!(e))?;
         let mut image_ids = Vec::from_iter(data.poster_path);
         if let Some(u) = data.backdrop_path {
             image_ids.push(u);
         }
-        utils::save_all_images(&self.client, "tv", identifier, &mut image_ids).await?;
+        let mut logo_ids = vec![];
+        utils::save_all_images(
+            &self.client,
+            "tv",
+            identifier,
+            &mut image_ids,
+            &mut logo_ids,
+        )
+        .await?;
 
         #[derive(Debug, Serialize, Deserialize, Clone)]
         struct TmdbEpisode {
@@ -369,6 +520,14 @@ impl MediaProvider for TmdbShowService {
             })
             .unique()
             .collect::<Vec<_>>();
+        let average_episode_runtime = if data.episode_run_time.is_empty() {
+            None
+        } else {
+            Some(
+                data.episode_run_time.iter().sum::<i32>()
+                    / i32::try_from(data.episode_run_time.len()).unwrap(),
+            )
+        };
         Ok(MediaDetails {
             identifier: data.id.to_string(),
             title: data.name,
@@ -378,16 +537,10 @@ impl MediaProvider for TmdbShowService {
             creators: author_names,
             genres: data.genres.into_iter().map(|g| g.name).unique().collect(),
             publish_date: convert_string_to_date(&data.first_air_date.clone().unwrap_or_default()),
-            images: image_ids
-                .into_iter()
-                .unique()
-                .map(|p| MetadataImage {
-                    url: MetadataImageUrl::Url(self.base.get_cover_image_url(p)),
-                    lot: MetadataImageLot::Poster,
-                })
-                .collect(),
+            images: self.base.metadata_images(image_ids, logo_ids),
             publish_year: convert_date_to_year(&data.first_air_date.unwrap_or_default()),
             specifics: MediaSpecifics::Show(ShowSpecifics {
+                average_episode_runtime,
                 seasons: seasons
                     .into_iter()
                     .map(|s| {
@@ -428,6 +581,13 @@ impl MediaProvider for TmdbShowService {
                     })
                     .collect(),
             }),
+            provider_rating: data
+                .vote_average
+                .and_then(Decimal::from_f64_retain)
+                .map(|r| r.round_dp(1)),
+            original_language: data.original_language,
+            franchise: None,
+            slug: None,
         })
     }
 
@@ -435,6 +595,8 @@ impl MediaProvider for TmdbShowService {
         &self,
         query: &str,
         page: Option<i32>,
+        year: Option<i32>,
+        language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>> {
         let page = page.unwrap_or(1);
         #[derive(Debug, Serialize, Deserialize, SimpleObject)]
@@ -452,14 +614,18 @@ impl MediaProvider for TmdbShowService {
             results: Vec<TmdbShow>,
             total_pages: i32,
         }
+        let mut params = json!({
+            "query": query.to_owned(),
+            "page": page,
+            "language": language.unwrap_or(&self.base.language),
+        });
+        if let Some(y) = year {
+            params["first_air_date_year"] = json!(y);
+        }
         let mut rsp = self
             .client
             .get("search/tv")
-            .query(&json!({
-                "query": query.to_owned(),
-                "page": page,
-                "language": self.base.language
-            }))
+            .query(&params)
             .unwrap()
             .await
             .map_err(|e| anyhow!(e))?;
@@ -490,7 +656,7 @@ impl MediaProvider for TmdbShowService {
 }
 
 mod utils {
-    use std::{env, fs};
+    use std::{collections::HashMap, env, fs};
 
     use surf::{http::headers::AUTHORIZATION, Url};
 
@@ -514,6 +680,7 @@ mod utils {
     pub struct TmdbImagesResponse {
         pub backdrops: Option<Vec<utils::TmdbImage>>,
         pub posters: Option<Vec<utils::TmdbImage>>,
+        pub logos: Option<Vec<utils::TmdbImage>>,
     }
 
     pub async fn get_client_config(url: &str, access_token: &str) -> (Client, String) {
@@ -548,6 +715,7 @@ mod utils {
         typ: &str,
         identifier: &str,
         images: &mut Vec<String>,
+        logos: &mut Vec<String>,
     ) -> Result<()> {
         let mut rsp = client
             .get(format!("{}/{}/images", typ, identifier))
@@ -564,6 +732,144 @@ mod utils {
                 images.push(image.file_path);
             }
         }
+        if let Some(imgs) = new_images.logos {
+            for image in imgs {
+                logos.push(image.file_path);
+            }
+        }
         Ok(())
     }
+
+    pub async fn get_watch_providers(
+        client: &Client,
+        base: &TmdbService,
+        typ: &str,
+        identifier: &str,
+    ) -> Result<Vec<WatchProvider>> {
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        struct TmdbWatchProvider {
+            provider_name: String,
+            logo_path: Option<String>,
+        }
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        struct TmdbWatchProviderRegion {
+            #[serde(default)]
+            flatrate: Vec<TmdbWatchProvider>,
+            #[serde(default)]
+            rent: Vec<TmdbWatchProvider>,
+            #[serde(default)]
+            buy: Vec<TmdbWatchProvider>,
+        }
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        struct TmdbWatchProvidersResponse {
+            results: HashMap<String, TmdbWatchProviderRegion>,
+        }
+        let mut rsp = client
+            .get(format!("{}/{}/watch/providers", typ, identifier))
+            .await
+            .map_err(|e| anyhow!(e))?;
+        let data: TmdbWatchProvidersResponse = rsp.body_json().await.map_err(|e| anyhow!(e))?;
+        let providers = data
+            .results
+            .into_iter()
+            .flat_map(|(region, r)| {
+                r.flatrate
+                    .into_iter()
+                    .chain(r.rent)
+                    .chain(r.buy)
+                    .map(move |p| WatchProvider {
+                        name: p.provider_name,
+                        logo: p.logo_path.map(|l| base.get_cover_image_url(l)),
+                        region: region.clone(),
+                    })
+            })
+            .unique()
+            .collect();
+        Ok(providers)
+    }
+
+    pub async fn get_recommendations(
+        client: &Client,
+        base: &TmdbService,
+        typ: &str,
+        lot: MetadataLot,
+        identifier: &str,
+    ) -> Result<Vec<MediaSearchItem>> {
+        // movies and shows use different names for the title and release date
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        struct TmdbRecommendation {
+            id: i32,
+            title: Option<String>,
+            name: Option<String>,
+            poster_path: Option<String>,
+            release_date: Option<String>,
+            first_air_date: Option<String>,
+        }
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        struct TmdbRecommendationsResponse {
+            results: Vec<TmdbRecommendation>,
+        }
+        let mut rsp = client
+            .get(format!("{}/{}/recommendations", typ, identifier))
+            .query(&json!({
+                "language": base.language,
+            }))
+            .unwrap()
+            .await
+            .map_err(|e| anyhow!(e))?;
+        let data: TmdbRecommendationsResponse = rsp.body_json().await.map_err(|e| anyhow!(e))?;
+        let items = data
+            .results
+            .into_iter()
+            .filter_map(|r| {
+                Some(MediaSearchItem {
+                    identifier: r.id.to_string(),
+                    lot,
+                    title: r.title.or(r.name)?,
+                    publish_year: r
+                        .release_date
+                        .or(r.first_air_date)
+                        .and_then(|d| convert_date_to_year(&d)),
+                    image: r.poster_path.map(|p| base.get_cover_image_url(p)),
+                })
+            })
+            .collect();
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn franchise_parts_are_sorted_by_release() {
+        let base = TmdbService {
+            image_url: "https://image.tmdb.org/t/p/".to_owned(),
+            language: "en".to_owned(),
+        };
+        let data: TmdbCollectionResponse = serde_json::from_value(json!({
+            "id": 10,
+            "name": "Star Wars Collection",
+            "parts": [
+                {"id": 3, "title": "Unannounced", "poster_path": null, "release_date": ""},
+                {"id": 2, "title": "Sequel", "poster_path": "/b.jpg", "release_date": "1980-05-20"},
+                {"id": 1, "title": "First", "poster_path": "/a.jpg", "release_date": "1977-05-25"},
+            ]
+        }))
+        .unwrap();
+
+        let items = franchise_items(&base, data);
+        let identifiers = items
+            .iter()
+            .map(|i| i.identifier.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(identifiers, vec!["1", "2", "3"]);
+        assert_eq!(items[0].publish_year, Some(1977));
+        assert_eq!(
+            items[0].image.as_deref(),
+            Some("https://image.tmdb.org/t/p/original/a.jpg")
+        );
+        assert_eq!(items[2].image, None);
+    }
 }
@@ -8,15 +8,32 @@ use crate::models::{
 
 #[async_trait]
 pub trait MediaProvider {
-    /// Search for something using a particular query and offset.
+    /// Search for something using a particular query and offset. Providers that
+    /// can not filter by release year should ignore `year`. `language` overrides
+    /// the configured language for this search, and is ignored by providers
+    /// whose language can only be set in the configuration.
     async fn search(
         &self,
         query: &str,
         page: Option<i32>,
+        year: Option<i32>,
+        language: Option<&str>,
     ) -> Result<SearchResults<MediaSearchItem>>;
 
     /// Get details about a media item for the particular identifier.
     async fn details(&self, identifier: &str) -> Result<MediaDetails>;
+
+    /// Reject identifiers that can never be valid for this provider, without
+    /// making any network requests.
+    fn validate_identifier(&self, _identifier: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Get the media that the provider considers related to the one with this
+    /// identifier. Providers without recommendations return nothing.
+    async fn recommendations(&self, _identifier: &str) -> Result<Vec<MediaSearchItem>> {
+        Ok(vec![])
+    }
 }
 
 pub trait MediaProviderLanguages {
@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+
 use async_graphql::SimpleObject;
+use chrono::{FixedOffset, NaiveDate, Utc};
 use sea_orm::{prelude::DateTimeUtc, FromJsonQueryResult};
 use serde::{Deserialize, Serialize};
 
+use crate::miscellaneous::{MediaSortBy, MediaSortOrder};
+
 #[derive(
     Debug, Serialize, Deserialize, SimpleObject, Clone, Eq, PartialEq, FromJsonQueryResult,
 )]
@@ -32,17 +37,119 @@ impl Default for UserFeaturesEnabledPreferences {
 }
 
 #[derive(
-    Debug, Serialize, Deserialize, SimpleObject, Clone, Eq, PartialEq, Default, FromJsonQueryResult,
+    Debug, Serialize, Deserialize, SimpleObject, Clone, Eq, PartialEq, FromJsonQueryResult,
 )]
 pub struct UserPreferences {
     #[serde(default)]
     pub features_enabled: UserFeaturesEnabledPreferences,
+    /// Either `UTC` or an offset from it like `+05:30`. Used to decide what
+    /// "today" is for the user.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// The ISO 3166-1 code of the country the user lives in, like `US`. Used
+    /// for region specific information like where to watch a media item.
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// The ordering used when listing the media in the user's library without
+    /// specifying one explicitly.
+    #[serde(default)]
+    pub media_list_sort: UserMediaListSortPreferences,
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, SimpleObject, Clone, Eq, PartialEq, FromJsonQueryResult,
+)]
+pub struct UserMediaListSortPreferences {
+    pub by: MediaSortBy,
+    pub order: MediaSortOrder,
+}
+
+impl Default for UserMediaListSortPreferences {
+    fn default() -> Self {
+        Self {
+            by: MediaSortBy::LastUpdated,
+            order: MediaSortOrder::Desc,
+        }
+    }
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            features_enabled: UserFeaturesEnabledPreferences::default(),
+            timezone: default_timezone(),
+            region: default_region(),
+            media_list_sort: UserMediaListSortPreferences::default(),
+        }
+    }
+}
+
+impl UserPreferences {
+    /// The current date in the user's timezone.
+    pub fn today(&self) -> NaiveDate {
+        Utc::now().with_timezone(&self.offset()).date_naive()
+    }
+
+    /// The offset of the user's timezone, falling back to UTC when it is invalid.
+    pub fn offset(&self) -> FixedOffset {
+        parse_timezone(&self.timezone).unwrap_or(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+fn default_timezone() -> String {
+    "UTC".to_owned()
+}
+
+fn default_region() -> String {
+    "US".to_owned()
+}
+
+/// Parse a timezone of the form `UTC` or `+HH:MM`/`-HH:MM`.
+pub fn parse_timezone(timezone: &str) -> Option<FixedOffset> {
+    if timezone.eq_ignore_ascii_case("UTC") {
+        return FixedOffset::east_opt(0);
+    }
+    let (sign, rest) = if let Some(r) = timezone.strip_prefix('+') {
+        (1, r)
+    } else if let Some(r) = timezone.strip_prefix('-') {
+        (-1, r)
+    } else {
+        return None;
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    let hours = hours.parse::<u32>().ok()?;
+    let minutes = minutes.parse::<u32>().ok()?;
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * i32::try_from(hours * 3600 + minutes * 60).ok()?)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, FromJsonQueryResult)]
 #[serde(tag = "t", content = "d")]
 pub enum UserYankIntegrationSetting {
-    Audiobookshelf { base_url: String, token: String },
+    Audiobookshelf {
+        base_url: String,
+        token: String,
+        /// progress below this value will be ignored
+        #[serde(default = "default_yank_min_progress")]
+        min_progress: i32,
+        /// progress above this value will mark the media as finished
+        #[serde(default = "default_yank_max_progress")]
+        max_progress: i32,
+        /// only items from this library will be synced, all libraries are
+        /// synced if unset
+        #[serde(default)]
+        library_id: Option<String>,
+    },
+}
+
+pub fn default_yank_min_progress() -> i32 {
+    1
+}
+
+pub fn default_yank_max_progress() -> i32 {
+    95
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, FromJsonQueryResult)]
@@ -51,6 +158,10 @@ pub struct UserYankIntegration {
     pub settings: UserYankIntegrationSetting,
     /// the date and time it was added on
     pub timestamp: DateTimeUtc,
+    /// the last progress that was synced for each identifier from this
+    /// integration
+    #[serde(default)]
+    pub last_synced_progress: HashMap<String, i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, FromJsonQueryResult)]
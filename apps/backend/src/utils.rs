@@ -1,17 +1,23 @@
+use std::collections::HashMap;
 use std::fs::File;
+use std::hash::Hash;
 use std::io::Read;
 use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use apalis::sqlite::SqliteStorage;
-use async_graphql::{Context, Error, InputObject, Result, SimpleObject};
+use async_graphql::{Context, Error, ErrorExtensions, InputObject, Result, SimpleObject, Value};
 use chrono::{NaiveDate, Utc};
 use darkbird::Storage;
-use sea_orm::{ActiveModelTrait, ActiveValue, ConnectionTrait, DatabaseConnection};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter,
+};
 use sea_query::{BinOper, Expr, Func, SimpleExpr};
 use serde::de::{self, DeserializeOwned};
 use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
 use surf::http::headers::USER_AGENT;
 use surf::{Client, Config};
 use tokio::task::JoinSet;
@@ -22,12 +28,14 @@ use crate::{
         UpdateMetadataJob, UserCreatedJob,
     },
     config::AppConfig,
-    entities::user_to_metadata,
+    entities::{prelude::Seen, seen, user_to_metadata},
     file_storage::FileStorageService,
     fitness::exercise::resolver::ExerciseService,
     graphql::USER_AGENT_STR,
     importer::ImporterService,
-    miscellaneous::resolver::MiscellaneousService,
+    miscellaneous::{
+        resolver::MiscellaneousService, SeenExtraInformation, SeenShowExtraInformation,
+    },
     GqlCtx, MemoryAuthData,
 };
 
@@ -55,6 +63,7 @@ pub async fn create_app_services(
     after_media_seen_job: &SqliteStorage<AfterMediaSeenJob>,
     update_metadata_job: &SqliteStorage<UpdateMetadataJob>,
     recalculate_user_summary_job: &SqliteStorage<RecalculateUserSummaryJob>,
+    scheduler_pool: &SqlitePool,
 ) -> AppServices {
     let file_storage_service = Arc::new(FileStorageService::new(
         s3_client,
@@ -78,6 +87,7 @@ pub async fn create_app_services(
             update_metadata_job,
             recalculate_user_summary_job,
             user_created_job,
+            scheduler_pool,
         )
         .await,
     );
@@ -100,10 +110,16 @@ pub struct NamedObject {
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, InputObject)]
+#[derive(Serialize, Deserialize, Debug, InputObject, Clone)]
 pub struct SearchInput {
     pub query: String,
     pub page: Option<i32>,
+    /// Only return results released in this year, if the provider supports it.
+    pub year: Option<i32>,
+    /// Search in this language instead of the configured one. It must be one of
+    /// the languages supported by the provider, and can only be set for TMDb and
+    /// iTunes.
+    pub language: Option<String>,
 }
 
 pub async fn associate_user_with_metadata<C>(user_id: &i32, metadata_id: &i32, db: &C) -> Result<()>
@@ -119,6 +135,88 @@ where
     Ok(())
 }
 
+/// Whether the user has already finished this episode of a show. Dropped and
+/// in progress `seen` items do not count.
+pub async fn has_completed_episode<C>(
+    db: &C,
+    user_id: i32,
+    metadata_id: i32,
+    season: i32,
+    episode: i32,
+) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    let episode = Some(SeenExtraInformation::Show(SeenShowExtraInformation {
+        season,
+        episode,
+    }));
+    let completed = Seen::find()
+        .filter(seen::Column::UserId.eq(user_id))
+        .filter(seen::Column::MetadataId.eq(metadata_id))
+        .filter(seen::Column::Progress.eq(100))
+        .filter(seen::Column::Dropped.ne(true))
+        .all(db)
+        .await?
+        .into_iter()
+        .any(|s| s.extra_information == episode);
+    Ok(completed)
+}
+
+/// Stable codes set on the `code` extension of API errors so that clients can
+/// handle them without parsing the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NotFound,
+    Forbidden,
+    Validation,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotFound => "NOT_FOUND",
+            Self::Forbidden => "FORBIDDEN",
+            Self::Validation => "VALIDATION",
+        }
+    }
+}
+
+pub fn coded_error(code: ErrorCode, message: impl Into<String>) -> Error {
+    Error::new(message.into()).extend_with(|_, e| e.set("code", code.as_str()))
+}
+
+/// Whether the error was created by [`coded_error`] with this code.
+pub fn has_error_code(error: &Error, code: ErrorCode) -> bool {
+    error
+        .extensions
+        .as_ref()
+        .and_then(|e| e.get("code"))
+        .map_or(false, |c| *c == Value::from(code.as_str()))
+}
+
+/// Returned by a provider when the media item does not exist on it (anymore),
+/// as opposed to a network or rate limit error.
+#[derive(Debug)]
+pub struct MediaNotFoundError;
+
+impl std::fmt::Display for MediaNotFoundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The provider does not have this media item")
+    }
+}
+
+impl std::error::Error for MediaNotFoundError {}
+
+/// Fail with a [`MediaNotFoundError`] if the provider responded that the
+/// requested media item does not exist.
+pub fn ensure_media_found(rsp: &surf::Response) -> anyhow::Result<()> {
+    match rsp.status() {
+        surf::StatusCode::NotFound | surf::StatusCode::Gone => Err(MediaNotFoundError.into()),
+        _ => Ok(()),
+    }
+}
+
 pub fn user_auth_token_from_ctx(ctx: &Context<'_>) -> Result<String> {
     let ctx = ctx.data_unchecked::<GqlCtx>();
     ctx.auth_token
@@ -147,6 +245,15 @@ pub async fn user_id_from_token(token: String, auth_db: &MemoryAuthDb) -> Result
     }
 }
 
+pub fn validate_numeric_identifier(identifier: &str, provider: &str) -> anyhow::Result<()> {
+    if identifier.is_empty() || !identifier.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow::anyhow!(
+            "{provider} identifiers must be numeric, got {identifier:?}"
+        ));
+    }
+    Ok(())
+}
+
 pub fn convert_string_to_date(d: &str) -> Option<NaiveDate> {
     NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()
 }
@@ -212,3 +319,83 @@ where
         Box::new(Func::lower(Expr::val(format!("%{}%", v))).into()),
     )
 }
+
+/// An in-memory cache whose entries expire after a fixed duration. When it is
+/// full, expired entries are evicted first and then the oldest one.
+pub struct MemoryCache<K, V> {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> MemoryCache<K, V> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|(inserted_on, _)| inserted_on.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.retain(|_, (inserted_on, _)| inserted_on.elapsed() < self.ttl);
+            if entries.len() >= self.capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, (inserted_on, _))| *inserted_on)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_cache_expires_and_evicts_entries() {
+        let cache = MemoryCache::new(Duration::from_secs(60), 2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(2));
+        assert_eq!(cache.get(&"c"), Some(3));
+
+        let cache = MemoryCache::new(Duration::ZERO, 2);
+        cache.insert("a", 1);
+        assert_eq!(cache.get(&"a"), None);
+    }
+
+    #[test]
+    fn coded_errors_carry_the_code_extension() {
+        let error = coded_error(ErrorCode::NotFound, "Collection not found");
+        assert_eq!(error.message, "Collection not found");
+        let code = error.extensions.unwrap().get("code").cloned();
+        assert_eq!(code, Some(async_graphql::Value::from("NOT_FOUND")));
+        assert!(has_error_code(&error, ErrorCode::NotFound));
+        assert!(!has_error_code(&error, ErrorCode::Validation));
+        assert!(!has_error_code(
+            &Error::new("Timed out"),
+            ErrorCode::NotFound
+        ));
+    }
+}
@@ -15,6 +15,7 @@ import {
 	Badge,
 	Box,
 	Button,
+	Center,
 	Collapse,
 	Container,
 	Flex,
@@ -23,6 +24,7 @@ import {
 	type MantineGradient,
 	Modal,
 	NumberInput,
+	Pagination,
 	Rating,
 	ScrollArea,
 	Select,
@@ -90,6 +92,8 @@ import invariant from "tiny-invariant";
 import { match } from "ts-pattern";
 import { withQuery } from "ufo";
 
+const REVIEWS_LIMIT = 20;
+
 const service = new HumanizeDurationLanguage();
 const humaizer = new HumanizeDuration(service);
 
@@ -330,7 +334,7 @@ const ReviewItem = ({
 	r,
 	metadataId,
 }: {
-	r: MediaItemReviewsQuery["mediaItemReviews"][number];
+	r: MediaItemReviewsQuery["mediaItemReviews"]["items"][number];
 	metadataId: number;
 }) => {
 	const [opened, { toggle }] = useDisclosure(false);
@@ -401,6 +405,7 @@ const Page: NextPageWithLayout = () => {
 	const metadataId = parseInt(router.query.item?.toString() || "0");
 	const theme = useMantineTheme();
 	const colors = Object.keys(theme.colors);
+	const [reviewsPage, setReviewsPage] = useState(1);
 
 	const mediaDetails = useQuery({
 		queryKey: ["details", metadataId],
@@ -441,12 +446,13 @@ const Page: NextPageWithLayout = () => {
 		},
 	});
 	const reviews = useQuery({
-		queryKey: ["reviews", metadataId],
+		queryKey: ["reviews", metadataId, reviewsPage],
 		queryFn: async () => {
 			const { mediaItemReviews } = await gqlClient.request(
 				MediaItemReviewsDocument,
 				{
 					metadataId: metadataId,
+					page: reviewsPage,
 				},
 			);
 			return mediaItemReviews;
@@ -1221,12 +1227,24 @@ const Page: NextPageWithLayout = () => {
 							</Tabs.Panel>
 						) : null}
 						<Tabs.Panel value="reviews">
-							{reviews.data && reviews.data.length > 0 ? (
+							{reviews.data && reviews.data.items.length > 0 ? (
 								<MediaScrollArea>
 									<Stack>
-										{reviews.data.map((r) => (
+										{reviews.data.items.map((r) => (
 											<ReviewItem r={r} key={r.id} metadataId={metadataId} />
 										))}
+										{reviews.data.total > REVIEWS_LIMIT ? (
+											<Center>
+												<Pagination
+													size="sm"
+													value={reviewsPage}
+													onChange={setReviewsPage}
+													total={Math.ceil(reviews.data.total / REVIEWS_LIMIT)}
+													boundaries={1}
+													siblings={0}
+												/>
+											</Center>
+										) : null}
 									</Stack>
 								</MediaScrollArea>
 							) : (
@@ -22,6 +22,7 @@ import {
 	MetadataLot,
 	ProgressUpdateDocument,
 	type ProgressUpdateMutationVariables,
+	SeenHistoryDocument,
 } from "@ryot/generated/graphql/backend/graphql";
 import { IconAlertCircle } from "@tabler/icons-react";
 import { useMutation, useQuery } from "@tanstack/react-query";
@@ -61,9 +62,28 @@ const Page: NextPageWithLayout = () => {
 	});
 	const progressUpdate = useMutation({
 		mutationFn: async (variables: ProgressUpdateMutationVariables) => {
+			// episodes that have already been seen are rejected unless they are
+			// marked as rewatches, so they are skipped when marking many at once
+			const seenEpisodes = new Set<string>();
+			if (completeShow || onlySeason) {
+				const { seenHistory } = await gqlClient.request(SeenHistoryDocument, {
+					metadataId,
+				});
+				for (const seen of seenHistory)
+					if (seen.progress === 100 && seen.showInformation)
+						seenEpisodes.add(
+							`${seen.showInformation.season}-${seen.showInformation.episode}`,
+						);
+			}
 			if (completeShow) {
 				for (const season of mediaDetails.data?.showSpecifics?.seasons || []) {
 					for (const episode of season.episodes) {
+						if (
+							seenEpisodes.has(
+								`${season.seasonNumber}-${episode.episodeNumber}`,
+							)
+						)
+							continue;
 						await gqlClient.request(ProgressUpdateDocument, {
 							input: {
 								...variables.input,
@@ -91,6 +111,12 @@ const Page: NextPageWithLayout = () => {
 				for (const episode of mediaDetails.data?.showSpecifics?.seasons.find(
 					(s) => s.seasonNumber.toString() === selectedShowSeasonNumber,
 				)?.episodes || []) {
+					if (
+						seenEpisodes.has(
+							`${selectedShowSeasonNumber}-${episode.episodeNumber}`,
+						)
+					)
+						continue;
 					await gqlClient.request(ProgressUpdateDocument, {
 						input: {
 							...variables.input,